@@ -4,8 +4,8 @@ use anyhow::Context;
 use clap::Parser;
 use env_logger::Env;
 use jgb_core::{
-    ControllerConfig, ControllerInput, GbColorScheme, GbcColorCorrection, HardwareMode,
-    HotkeyConfig, InputConfig, RunConfig,
+    ControllerConfig, ControllerInput, CpuAccuracy, GbColorScheme, GbcColorCorrection,
+    HardwareMode, HotkeyConfig, InputConfig, LinkCableConfig, RunConfig,
 };
 use std::sync::Arc;
 use std::sync::atomic::AtomicBool;
@@ -20,6 +20,11 @@ struct CliArgs {
     #[arg(long = "hardware-mode", default_value_t)]
     hardware_mode: HardwareMode,
 
+    /// Path to a boot ROM file to run before the cartridge; must be a DMG boot ROM in GB mode or a
+    /// CGB boot ROM in GBC mode
+    #[arg(long = "boot-rom")]
+    boot_rom_path: Option<String>,
+
     /// Disable audio
     #[arg(long = "no-audio", default_value_t = true)]
     audio_enabled: bool,
@@ -62,6 +67,11 @@ struct CliArgs {
     #[arg(long = "audio-60hz", default_value_t)]
     audio_60hz: bool,
 
+    /// CPU timing accuracy (Instruction / Cycle); cycle accuracy is slower but runs the rest of
+    /// the hardware between every memory access, which some games and test ROMs depend on
+    #[arg(long, default_value_t)]
+    cpu_accuracy: CpuAccuracy,
+
     /// GB color palette (BlackAndWhite / GreenTint / LimeGreen)
     #[arg(long = "color-scheme", default_value_t)]
     color_scheme: GbColorScheme,
@@ -118,10 +128,22 @@ struct CliArgs {
     #[arg(long)]
     hotkey_load_state: Option<String>,
 
+    /// Next save state slot hotkey (default F8)
+    #[arg(long)]
+    hotkey_next_save_slot: Option<String>,
+
+    /// Previous save state slot hotkey (default F7)
+    #[arg(long)]
+    hotkey_prev_save_slot: Option<String>,
+
     /// Fast forward hotkey (default Tab)
     #[arg(long)]
     hotkey_fast_forward: Option<String>,
 
+    /// Rewind hotkey (default Backspace)
+    #[arg(long)]
+    hotkey_rewind: Option<String>,
+
     /// Up controller input ("button N" / "axis N +" / "axis N -")
     #[arg(long)]
     controller_up: Option<String>,
@@ -161,6 +183,45 @@ struct CliArgs {
     /// Disable controller rumble
     #[arg(long = "no-controller-rumble", default_value_t = true, action = clap::ArgAction::SetFalse)]
     controller_rumble: bool,
+
+    /// Listen for a link cable connection from another instance on the given address ("host:port",
+    /// or "unix:<path>" for a Unix socket); blocks until the other instance connects
+    #[arg(long, conflicts_with = "link_connect")]
+    link_listen: Option<String>,
+
+    /// Connect a link cable to another instance that is listening on the given address
+    /// ("host:port", or "unix:<path>" for a Unix socket)
+    #[arg(long)]
+    link_connect: Option<String>,
+
+    /// Connect a Game Boy Printer to the link port; printed images are saved as PNG files next to
+    /// the ROM file
+    #[arg(long, conflicts_with_all = ["link_listen", "link_connect"])]
+    printer: bool,
+
+    /// Image source for the Pocket Camera: a PNG file to use as a still image, or a directory of
+    /// PNG files to play back as frames (a blank image is used if not set)
+    #[arg(long)]
+    camera_image: Option<String>,
+
+    /// Number of seconds of history to keep for rewinding (0 to disable rewind)
+    #[arg(long, default_value_t = 10)]
+    rewind_buffer_secs: u32,
+
+    /// Load the save state in the given slot (0-9) immediately after startup; the save state hotkeys
+    /// also start out using this slot
+    #[arg(long, value_parser = clap::value_parser!(u8).range(0..10))]
+    load_state_slot: Option<u8>,
+
+    /// Record input to a movie file at the given path, starting from power-on (or from the save
+    /// state loaded with --load-state-slot); the movie is written when the emulator exits
+    #[arg(long, conflicts_with = "play_movie")]
+    record_movie: Option<String>,
+
+    /// Play back the movie file at the given path; input is ignored until playback finishes. BizHawk
+    /// (.bk2) and VisualBoyAdvance (.vbm) movies that start from power-on are also supported
+    #[arg(long, conflicts_with = "load_state_slot")]
+    play_movie: Option<String>,
 }
 
 impl CliArgs {
@@ -185,7 +246,10 @@ impl CliArgs {
             toggle_fullscreen: self.hotkey_toggle_fullscreen.clone().or(default.toggle_fullscreen),
             save_state: self.hotkey_save_state.clone().or(default.save_state),
             load_state: self.hotkey_load_state.clone().or(default.load_state),
+            next_save_slot: self.hotkey_next_save_slot.clone().or(default.next_save_slot),
+            prev_save_slot: self.hotkey_prev_save_slot.clone().or(default.prev_save_slot),
             fast_forward: self.hotkey_fast_forward.clone().or(default.fast_forward),
+            rewind: self.hotkey_rewind.clone().or(default.rewind),
         }
     }
 
@@ -205,6 +269,14 @@ impl CliArgs {
         };
         Ok(config)
     }
+
+    fn link_cable_config(&self) -> Option<LinkCableConfig> {
+        match (&self.link_listen, &self.link_connect) {
+            (Some(address), _) => Some(LinkCableConfig::Listen(address.clone())),
+            (None, Some(address)) => Some(LinkCableConfig::Connect(address.clone())),
+            (None, None) => None,
+        }
+    }
 }
 
 fn parse_controller_input(
@@ -230,10 +302,12 @@ fn main() -> anyhow::Result<()> {
     let input_config = args.input_config();
     let hotkey_config = args.hotkey_config();
     let controller_config = args.controller_config()?;
+    let link_cable = args.link_cable_config();
 
     let run_config = RunConfig {
         gb_file_path: args.gb_file_path,
         hardware_mode: args.hardware_mode,
+        boot_rom_path: args.boot_rom_path,
         audio_enabled: args.audio_enabled,
         sync_to_audio: args.sync_to_audio,
         vsync_enabled: args.vsync_enabled,
@@ -244,11 +318,19 @@ fn main() -> anyhow::Result<()> {
         window_height: args.window_height,
         audio_debugging_enabled: args.audio_debugging_enabled,
         audio_60hz: args.audio_60hz,
+        cpu_accuracy: args.cpu_accuracy,
         color_scheme: args.color_scheme,
         gbc_color_correction: args.gbc_color_correction,
         input_config,
         hotkey_config,
         controller_config,
+        link_cable,
+        printer_enabled: args.printer,
+        camera_image_path: args.camera_image,
+        rewind_buffer_secs: args.rewind_buffer_secs,
+        load_state_slot: args.load_state_slot,
+        record_movie_path: args.record_movie,
+        play_movie_path: args.play_movie,
     };
 
     if let Err(err) = jgb_core::run(&run_config, Arc::new(AtomicBool::new(false))) {
@@ -1,8 +1,6 @@
-use crate::apu::ApuState;
 use crate::{RunConfig, apu};
 use sdl2::AudioSubsystem;
 use sdl2::audio::{AudioQueue, AudioSpecDesired};
-use std::collections::VecDeque;
 use std::thread;
 use std::time::Duration;
 use thiserror::Error;
@@ -29,10 +27,10 @@ pub fn initialize(audio_subsystem: &AudioSubsystem) -> Result<AudioQueue<f32>, S
 /// Push audio samples to the playback queue if it is not full.
 ///
 /// If it is full and `sync_to_audio` is enabled, this function will block until it is not full and
-/// then push samples.
+/// then push samples. If it is full and `sync_to_audio` is disabled, the samples are dropped.
 pub fn push_samples(
     device_queue: &AudioQueue<f32>,
-    apu_state: &mut ApuState,
+    samples: Vec<f32>,
     run_config: &RunConfig,
     fast_forwarding: bool,
 ) -> Result<(), AudioError> {
@@ -45,21 +43,22 @@ pub fn push_samples(
         thread::sleep(Duration::from_micros(250));
     }
 
-    let samples = drain_sample_queue(apu_state.get_sample_queue_mut(), fast_forwarding);
+    let samples = skip_samples_if_fast_forwarding(samples, fast_forwarding);
     device_queue.queue_audio(&samples).map_err(|msg| AudioError::Playback { msg })?;
 
     Ok(())
 }
 
-fn drain_sample_queue<T>(sample_queue: &mut VecDeque<T>, fast_forwarding: bool) -> Vec<T> {
+fn skip_samples_if_fast_forwarding<T>(samples: Vec<T>, fast_forwarding: bool) -> Vec<T> {
     if !fast_forwarding {
-        return sample_queue.drain(..).collect();
+        return samples;
     }
 
     // Skip every other sample when fast-forwarding
-    let drain_len = sample_queue.len() - (sample_queue.len() % 2);
-    sample_queue
-        .drain(..drain_len)
+    let len = samples.len() - (samples.len() % 2);
+    samples
+        .into_iter()
+        .take(len)
         .enumerate()
         .filter_map(|(i, sample)| (i % 2 == 0).then_some(sample))
         .collect()
@@ -0,0 +1,267 @@
+use std::fmt::Formatter;
+use std::fs::File;
+use std::path::{Path, PathBuf};
+use std::{fs, io};
+use thiserror::Error;
+
+/// Width of the image captured by the Pocket Camera sensor, in pixels.
+pub const CAMERA_WIDTH: usize = 128;
+/// Height of the image captured by the Pocket Camera sensor, in pixels. The sensor is 128 pixels
+/// tall, but the cartridge only uses the middle 112 rows.
+pub const CAMERA_HEIGHT: usize = 112;
+
+/// A source of images for the Pocket Camera's image sensor.
+pub trait CameraImageSource {
+    /// Capture the current image as `CAMERA_WIDTH * CAMERA_HEIGHT` 8-bit grayscale pixels in
+    /// row-major order, where 0x00 is black and 0xFF is white.
+    fn capture(&mut self) -> Vec<u8>;
+}
+
+impl std::fmt::Debug for dyn CameraImageSource {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "CameraImageSource")
+    }
+}
+
+/// An image source that always captures a flat gray image, used when no image source is
+/// configured.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct BlankImageSource;
+
+impl CameraImageSource for BlankImageSource {
+    fn capture(&mut self) -> Vec<u8> {
+        vec![0x80; CAMERA_WIDTH * CAMERA_HEIGHT]
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum CameraImageError {
+    #[error("error reading camera image from {path}: {source}")]
+    Read {
+        path: PathBuf,
+        #[source]
+        source: io::Error,
+    },
+    #[error("error decoding PNG image {path}: {source}")]
+    Decode {
+        path: PathBuf,
+        #[source]
+        source: png::DecodingError,
+    },
+    #[error("no PNG images found in directory {path}")]
+    EmptyDirectory { path: PathBuf },
+}
+
+/// An image source that captures the same still image every time.
+#[derive(Debug, Clone)]
+pub struct StillImageSource {
+    pixels: Vec<u8>,
+}
+
+impl StillImageSource {
+    /// Create an image source from grayscale pixels that are already sized for the camera.
+    ///
+    /// # Panics
+    ///
+    /// This function will panic if `pixels` does not contain `CAMERA_WIDTH * CAMERA_HEIGHT` values.
+    #[must_use]
+    pub fn new(pixels: Vec<u8>) -> Self {
+        assert_eq!(CAMERA_WIDTH * CAMERA_HEIGHT, pixels.len());
+        Self { pixels }
+    }
+
+    /// Load a still image from a PNG file, converting it to grayscale and scaling/cropping it to the
+    /// camera's resolution.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the file cannot be read or is not a valid PNG image.
+    pub fn from_png_file<P: AsRef<Path>>(path: P) -> Result<Self, CameraImageError> {
+        Ok(Self::new(load_png(path.as_ref())?))
+    }
+}
+
+impl CameraImageSource for StillImageSource {
+    fn capture(&mut self) -> Vec<u8> {
+        self.pixels.clone()
+    }
+}
+
+/// An image source that plays back a sequence of frames, advancing to the next frame on every
+/// capture and looping after the last frame.
+#[derive(Debug, Clone)]
+pub struct FrameSequenceSource {
+    frames: Vec<Vec<u8>>,
+    next_frame: usize,
+}
+
+impl FrameSequenceSource {
+    /// Load every PNG file in the given directory as a frame, in file name order.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the directory cannot be read, if it contains no PNG
+    /// files, or if any of the PNG files cannot be decoded.
+    pub fn from_directory<P: AsRef<Path>>(path: P) -> Result<Self, CameraImageError> {
+        let path = path.as_ref();
+        let read_err = |source| CameraImageError::Read { path: path.into(), source };
+
+        let mut frame_paths = Vec::new();
+        for entry in fs::read_dir(path).map_err(read_err)? {
+            let entry_path = entry.map_err(read_err)?.path();
+            if entry_path.extension().is_some_and(|extension| extension.eq_ignore_ascii_case("png"))
+            {
+                frame_paths.push(entry_path);
+            }
+        }
+        frame_paths.sort();
+
+        if frame_paths.is_empty() {
+            return Err(CameraImageError::EmptyDirectory { path: path.into() });
+        }
+
+        let frames =
+            frame_paths.iter().map(|frame_path| load_png(frame_path)).collect::<Result<_, _>>()?;
+
+        Ok(Self { frames, next_frame: 0 })
+    }
+}
+
+impl CameraImageSource for FrameSequenceSource {
+    fn capture(&mut self) -> Vec<u8> {
+        let frame = self.frames[self.next_frame].clone();
+        self.next_frame = (self.next_frame + 1) % self.frames.len();
+        frame
+    }
+}
+
+/// Create an image source from a path, which can be either a PNG file (a still image) or a
+/// directory of PNG files (a sequence of frames).
+///
+/// # Errors
+///
+/// This function will return an error if the image or images cannot be loaded.
+pub fn image_source_from_path<P: AsRef<Path>>(
+    path: P,
+) -> Result<Box<dyn CameraImageSource>, CameraImageError> {
+    let path = path.as_ref();
+    if path.is_dir() {
+        Ok(Box::new(FrameSequenceSource::from_directory(path)?))
+    } else {
+        Ok(Box::new(StillImageSource::from_png_file(path)?))
+    }
+}
+
+fn load_png(path: &Path) -> Result<Vec<u8>, CameraImageError> {
+    let file =
+        File::open(path).map_err(|source| CameraImageError::Read { path: path.into(), source })?;
+    let decode_err = |source| CameraImageError::Decode { path: path.into(), source };
+
+    let mut decoder = png::Decoder::new(file);
+    decoder.set_transformations(png::Transformations::normalize_to_color8());
+    let mut reader = decoder.read_info().map_err(decode_err)?;
+
+    let mut buffer = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut buffer).map_err(decode_err)?;
+
+    let buffer = &buffer[..info.buffer_size()];
+    let grayscale: Vec<u8> = match info.color_type {
+        png::ColorType::Grayscale => buffer.to_vec(),
+        png::ColorType::GrayscaleAlpha => buffer.iter().step_by(2).copied().collect(),
+        png::ColorType::Rgb => buffer.chunks_exact(3).map(luma).collect(),
+        png::ColorType::Rgba => buffer.chunks_exact(4).map(luma).collect(),
+        png::ColorType::Indexed => {
+            unreachable!("indexed images are expanded to RGB(A) by the normalize transformation")
+        }
+    };
+
+    Ok(scale_to_camera(&grayscale, info.width as usize, info.height as usize))
+}
+
+fn luma(rgb: &[u8]) -> u8 {
+    let [r, g, b] = [rgb[0], rgb[1], rgb[2]].map(u32::from);
+    ((299 * r + 587 * g + 114 * b) / 1000) as u8
+}
+
+// Crop the image to the camera's aspect ratio (keeping the center) and then scale it to the
+// camera's resolution using nearest-neighbor sampling
+fn scale_to_camera(pixels: &[u8], width: usize, height: usize) -> Vec<u8> {
+    let (crop_width, crop_height) = if width * CAMERA_HEIGHT > height * CAMERA_WIDTH {
+        (height * CAMERA_WIDTH / CAMERA_HEIGHT, height)
+    } else {
+        (width, width * CAMERA_HEIGHT / CAMERA_WIDTH)
+    };
+    let crop_width = crop_width.max(1);
+    let crop_height = crop_height.max(1);
+    let left = (width - crop_width) / 2;
+    let top = (height - crop_height) / 2;
+
+    let mut scaled = Vec::with_capacity(CAMERA_WIDTH * CAMERA_HEIGHT);
+    for y in 0..CAMERA_HEIGHT {
+        let source_y = top + y * crop_height / CAMERA_HEIGHT;
+        for x in 0..CAMERA_WIDTH {
+            let source_x = left + x * crop_width / CAMERA_WIDTH;
+            scaled.push(pixels[source_y * width + source_x]);
+        }
+    }
+    scaled
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::BufWriter;
+
+    fn test_output_dir(test_name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("jgb-camera-test-{test_name}"));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn write_png(path: &Path, width: u32, height: u32, rgb: &[u8]) {
+        let file = BufWriter::new(File::create(path).unwrap());
+        let mut encoder = png::Encoder::new(file, width, height);
+        encoder.set_color(png::ColorType::Rgb);
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder.write_header().unwrap().write_image_data(rgb).unwrap();
+    }
+
+    #[test]
+    fn scaling() {
+        // 256x256 image with a white left half; cropping should keep the middle 224 rows
+        let pixels: Vec<u8> =
+            (0..256 * 256).map(|i| if i % 256 < 128 { 0xFF } else { 0x00 }).collect();
+        let scaled = scale_to_camera(&pixels, 256, 256);
+
+        assert_eq!(CAMERA_WIDTH * CAMERA_HEIGHT, scaled.len());
+        assert_eq!(0xFF, scaled[0]);
+        assert_eq!(0xFF, scaled[63]);
+        assert_eq!(0x00, scaled[64]);
+        assert_eq!(0x00, scaled[CAMERA_WIDTH * CAMERA_HEIGHT - 1]);
+    }
+
+    #[test]
+    fn png_sources() {
+        let dir = test_output_dir("png_sources");
+
+        write_png(&dir.join("b.png"), 2, 2, &[0xFF; 12]);
+        write_png(&dir.join("a.png"), 1, 1, &[0xFF, 0x00, 0x00]);
+        fs::write(dir.join("notes.txt"), "not an image").unwrap();
+
+        let mut still = StillImageSource::from_png_file(dir.join("b.png")).unwrap();
+        assert!(still.capture().iter().all(|&pixel| pixel == 0xFF));
+
+        // Frames should play back in file name order and then loop
+        let mut frames = FrameSequenceSource::from_directory(&dir).unwrap();
+        assert!(frames.capture().iter().all(|&pixel| pixel == luma(&[0xFF, 0x00, 0x00])));
+        assert!(frames.capture().iter().all(|&pixel| pixel == 0xFF));
+        assert!(frames.capture().iter().all(|&pixel| pixel == luma(&[0xFF, 0x00, 0x00])));
+
+        let empty_dir = test_output_dir("png_sources_empty");
+        assert!(matches!(
+            FrameSequenceSource::from_directory(&empty_dir),
+            Err(CameraImageError::EmptyDirectory { .. })
+        ));
+    }
+}
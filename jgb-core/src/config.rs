@@ -1,5 +1,4 @@
 use jgb_proc_macros::{EnumDisplay, EnumFromStr, StrDeserialize, StrSerialize};
-use sdl2::keyboard::Keycode;
 use serde::{Deserialize, Serialize};
 use std::fmt::Formatter;
 use std::str::FromStr;
@@ -28,17 +27,18 @@ pub struct InputConfig {
     pub select: String,
 }
 
+// Default keys are specified using SDL2 keycode names
 impl Default for InputConfig {
     fn default() -> Self {
         Self {
-            up: Keycode::Up.name(),
-            down: Keycode::Down.name(),
-            left: Keycode::Left.name(),
-            right: Keycode::Right.name(),
-            a: Keycode::Z.name(),
-            b: Keycode::X.name(),
-            start: Keycode::Return.name(),
-            select: Keycode::RShift.name(),
+            up: "Up".into(),
+            down: "Down".into(),
+            left: "Left".into(),
+            right: "Right".into(),
+            a: "Z".into(),
+            b: "X".into(),
+            start: "Return".into(),
+            select: "Right Shift".into(),
         }
     }
 }
@@ -59,17 +59,23 @@ pub struct HotkeyConfig {
     pub toggle_fullscreen: Option<String>,
     pub save_state: Option<String>,
     pub load_state: Option<String>,
+    pub next_save_slot: Option<String>,
+    pub prev_save_slot: Option<String>,
     pub fast_forward: Option<String>,
+    pub rewind: Option<String>,
 }
 
 impl Default for HotkeyConfig {
     fn default() -> Self {
         Self {
-            exit: Some(Keycode::Escape.name()),
-            toggle_fullscreen: Some(Keycode::F9.name()),
-            save_state: Some(Keycode::F5.name()),
-            load_state: Some(Keycode::F6.name()),
-            fast_forward: Some(Keycode::Tab.name()),
+            exit: Some("Escape".into()),
+            toggle_fullscreen: Some("F9".into()),
+            save_state: Some("F5".into()),
+            load_state: Some("F6".into()),
+            next_save_slot: Some("F8".into()),
+            prev_save_slot: Some("F7".into()),
+            fast_forward: Some("Tab".into()),
+            rewind: Some("Backspace".into()),
         }
     }
 }
@@ -78,12 +84,15 @@ impl std::fmt::Display for HotkeyConfig {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         write!(
             f,
-            "Exit={}, ToggleFullscreen={}, SaveState={}, LoadState={}, FastForward={}",
+            "Exit={}, ToggleFullscreen={}, SaveState={}, LoadState={}, NextSaveSlot={}, PrevSaveSlot={}, FastForward={}, Rewind={}",
             fmt_option(self.exit.as_ref()),
             fmt_option(self.toggle_fullscreen.as_ref()),
             fmt_option(self.save_state.as_ref()),
             fmt_option(self.load_state.as_ref()),
-            fmt_option(self.fast_forward.as_ref())
+            fmt_option(self.next_save_slot.as_ref()),
+            fmt_option(self.prev_save_slot.as_ref()),
+            fmt_option(self.fast_forward.as_ref()),
+            fmt_option(self.rewind.as_ref())
         )
     }
 }
@@ -237,10 +246,43 @@ impl Default for GbcColorCorrection {
     }
 }
 
+/// How precisely the CPU is synchronized with the rest of the hardware.
+///
+/// `Instruction` executes each CPU instruction all at once and then advances the other processors
+/// by the instruction's cycle count. `Cycle` advances the other processors between every memory
+/// access that an instruction makes, which is slower but required for some games and for
+/// mid-instruction timing tests.
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, EnumDisplay, EnumFromStr, Serialize, Deserialize,
+)]
+pub enum CpuAccuracy {
+    #[default]
+    Instruction,
+    Cycle,
+}
+
+/// How to connect the link port to another jgb instance. Addresses are TCP addresses in `host:port`
+/// form, or Unix domain socket paths prefixed with `unix:`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LinkCableConfig {
+    Listen(String),
+    Connect(String),
+}
+
+impl std::fmt::Display for LinkCableConfig {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Listen(address) => write!(f, "Listen({address})"),
+            Self::Connect(address) => write!(f, "Connect({address})"),
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct RunConfig {
     pub gb_file_path: String,
     pub hardware_mode: HardwareMode,
+    pub boot_rom_path: Option<String>,
     pub audio_enabled: bool,
     pub sync_to_audio: bool,
     pub vsync_enabled: bool,
@@ -251,17 +293,26 @@ pub struct RunConfig {
     pub window_height: u32,
     pub audio_debugging_enabled: bool,
     pub audio_60hz: bool,
+    pub cpu_accuracy: CpuAccuracy,
     pub color_scheme: GbColorScheme,
     pub gbc_color_correction: GbcColorCorrection,
     pub input_config: InputConfig,
     pub hotkey_config: HotkeyConfig,
     pub controller_config: ControllerConfig,
+    pub link_cable: Option<LinkCableConfig>,
+    pub printer_enabled: bool,
+    pub camera_image_path: Option<String>,
+    pub rewind_buffer_secs: u32,
+    pub load_state_slot: Option<u8>,
+    pub record_movie_path: Option<String>,
+    pub play_movie_path: Option<String>,
 }
 
 impl std::fmt::Display for RunConfig {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         writeln!(f, "gb_file_path: {}", self.gb_file_path)?;
         writeln!(f, "hardware_mode: {}", self.hardware_mode)?;
+        writeln!(f, "boot_rom_path: {}", fmt_option(self.boot_rom_path.as_ref()))?;
         writeln!(f, "audio_enabled: {}", self.audio_enabled)?;
         writeln!(f, "sync_to_audio: {}", self.sync_to_audio)?;
         writeln!(f, "vsync_enabled: {}", self.vsync_enabled)?;
@@ -272,11 +323,19 @@ impl std::fmt::Display for RunConfig {
         writeln!(f, "window_height: {}", self.window_height)?;
         writeln!(f, "audio_debugging_enabled: {}", self.audio_debugging_enabled)?;
         writeln!(f, "audio_60hz: {}", self.audio_60hz)?;
+        writeln!(f, "cpu_accuracy: {}", self.cpu_accuracy)?;
         writeln!(f, "color_scheme: {}", self.color_scheme)?;
         writeln!(f, "gbc_color_correction: {}", self.gbc_color_correction)?;
         writeln!(f, "input_config: {}", self.input_config)?;
         writeln!(f, "hotkey_config: {}", self.hotkey_config)?;
         writeln!(f, "controller_config: {}", self.controller_config)?;
+        writeln!(f, "link_cable: {}", fmt_option(self.link_cable.as_ref()))?;
+        writeln!(f, "printer_enabled: {}", self.printer_enabled)?;
+        writeln!(f, "camera_image_path: {}", fmt_option(self.camera_image_path.as_ref()))?;
+        writeln!(f, "rewind_buffer_secs: {}", self.rewind_buffer_secs)?;
+        writeln!(f, "load_state_slot: {}", fmt_option(self.load_state_slot.as_ref()))?;
+        writeln!(f, "record_movie_path: {}", fmt_option(self.record_movie_path.as_ref()))?;
+        writeln!(f, "play_movie_path: {}", fmt_option(self.play_movie_path.as_ref()))?;
 
         Ok(())
     }
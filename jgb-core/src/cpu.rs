@@ -1,3 +1,4 @@
+mod bus;
 pub(crate) mod instructions;
 mod registers;
 
@@ -6,11 +7,12 @@ mod tests;
 
 use crate::memory::AddressSpace;
 use crate::memory::ioregisters::IoRegister;
-use crate::ppu::PpuState;
+pub use bus::{CpuBus, ImmediateBus};
 pub use registers::{CgbSpeedMode, CpuRegisters};
 use serde::{Deserialize, Serialize};
 
-/// The number of clock cycles required to execute the interrupt service routine.
+/// The number of clock cycles required to execute the interrupt service routine, not including the
+/// extra M-cycle to exit HALT.
 pub const ISR_CYCLES_REQUIRED: u32 = 20;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
@@ -71,40 +73,62 @@ pub fn interrupt_triggered_no_ime_check(address_space: &AddressSpace) -> bool {
     ie_value & if_value != 0
 }
 
-/// Execute the CPU's interrupt service routine.
+/// Execute the CPU's interrupt service routine. Returns the number of clock cycles that the
+/// routine took.
 ///
 /// The routine disables the IME flag and then functionally executes CALL N where N is the handler
 /// address for the highest priority requested & enabled interrupt type. It also un-halts the CPU
-/// if it was previously halted.
+/// if it was previously halted, which takes 1 extra M-cycle.
 ///
-/// # Panics
-///
-/// This function will panic if there are no interrupt types that are both enabled and requested.
-/// It should only be called if [`interrupt_triggered`] returns true.
-pub fn execute_interrupt_service_routine(
+/// The routine itself takes 5 M-cycles: 2 internal cycles, 2 cycles to push the PC onto the stack,
+/// and 1 more internal cycle to jump to the handler address. The handler address is not chosen
+/// until after the high byte of the PC has been pushed, so if that write changes the IE register
+/// (i.e. SP was 0x0000) then the CPU jumps to whichever interrupt is still enabled and requested at
+/// that point. If none are, the dispatch is cancelled and the CPU jumps to 0x0000 instead.
+pub fn execute_interrupt_service_routine<B: CpuBus>(
     cpu_registers: &mut CpuRegisters,
-    address_space: &mut AddressSpace,
-    ppu_state: &PpuState,
-) {
-    cpu_registers.sp -= 2;
-    address_space.write_address_u16(cpu_registers.sp, cpu_registers.pc, ppu_state);
+    bus: &mut B,
+) -> u32 {
+    let mut cycles = ISR_CYCLES_REQUIRED;
+    if cpu_registers.halted {
+        bus.idle();
+        cycles += 4;
+    }
+
+    bus.idle();
+    bus.idle();
+
+    let [pc_lsb, pc_msb] = cpu_registers.pc.to_le_bytes();
+    cpu_registers.sp = cpu_registers.sp.wrapping_sub(1);
+    bus.write(cpu_registers.sp, pc_msb);
 
+    let address_space = bus.address_space();
     let ie_value = address_space.get_ie_register();
-    let interrupt_type = address_space
-        .get_io_registers_mut()
-        .interrupt_flags()
-        .highest_priority_interrupt(ie_value)
-        .expect("execute_interrupt_service_routine should only be called when an interrupt has triggered");
+    let interrupt_type =
+        address_space.get_io_registers_mut().interrupt_flags().highest_priority_interrupt(ie_value);
+
+    let handler_address = match interrupt_type {
+        Some(interrupt_type) => {
+            address_space.get_io_registers_mut().interrupt_flags().clear(interrupt_type);
+            interrupt_type.handler_address()
+        }
+        None => 0x0000,
+    };
 
     log::trace!(
-        "Interrupt type {interrupt_type:?} triggered, replacing previous PC of {:04X} with {:04X}",
-        cpu_registers.pc,
-        interrupt_type.handler_address()
+        "Interrupt type {interrupt_type:?} triggered, replacing previous PC of {:04X} with {handler_address:04X}",
+        cpu_registers.pc
     );
 
-    cpu_registers.pc = interrupt_type.handler_address();
+    // Writing the low byte can also change IE, but this has no effect on the dispatch
+    cpu_registers.sp = cpu_registers.sp.wrapping_sub(1);
+    bus.write(cpu_registers.sp, pc_lsb);
 
-    address_space.get_io_registers_mut().interrupt_flags().clear(interrupt_type);
+    cpu_registers.pc = handler_address;
     cpu_registers.ime = false;
     cpu_registers.halted = false;
+
+    bus.idle();
+
+    cycles
 }
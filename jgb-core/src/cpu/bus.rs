@@ -0,0 +1,83 @@
+use crate::memory::AddressSpace;
+use crate::ppu::{self, OamBugAccess, PpuState};
+
+/// The CPU's view of the memory bus.
+///
+/// Every call to [`read`](CpuBus::read), [`write`](CpuBus::write), or [`idle`](CpuBus::idle)
+/// represents exactly one CPU M-cycle, and instructions make these calls in the same order that the
+/// actual hardware performs them. This allows a bus implementation to advance the rest of the
+/// hardware between memory accesses, which is what the M-cycle accurate CPU mode does.
+pub trait CpuBus {
+    /// Read a byte from memory during the current M-cycle.
+    fn read(&mut self, address: u16) -> u8;
+
+    /// Write a byte to memory during the current M-cycle.
+    fn write(&mut self, address: u16, value: u8);
+
+    /// Spend an M-cycle on an internal operation that does not access memory.
+    fn idle(&mut self);
+
+    /// Signal that the CPU is about to place the given address on the address bus in a way that
+    /// can trigger the DMG OAM corruption bug. This does not take a cycle; it should be called
+    /// immediately before the read, write, or idle cycle that performs the access.
+    fn trigger_oam_bug(&mut self, address: u16, access: OamBugAccess);
+
+    /// Direct access to the address space for internal register checks that do not take a cycle,
+    /// such as the HALT instruction checking the IE and IF registers.
+    fn address_space(&mut self) -> &mut AddressSpace;
+
+    fn ppu_state(&self) -> &PpuState;
+
+    /// Read a little-endian 16-bit value over 2 M-cycles, low byte first.
+    fn read_u16(&mut self, address: u16) -> u16 {
+        let lsb = self.read(address);
+        let msb = self.read(address.wrapping_add(1));
+        u16::from_le_bytes([lsb, msb])
+    }
+
+    /// Write a little-endian 16-bit value over 2 M-cycles, low byte first.
+    fn write_u16(&mut self, address: u16, value: u16) {
+        let [lsb, msb] = value.to_le_bytes();
+        self.write(address, lsb);
+        self.write(address.wrapping_add(1), msb);
+    }
+}
+
+/// A bus that performs every memory access immediately without advancing any other hardware.
+///
+/// This is used by the instruction-based CPU mode, which executes an entire instruction at once and
+/// then advances the other processors by the instruction's total cycle count.
+pub struct ImmediateBus<'a> {
+    address_space: &'a mut AddressSpace,
+    ppu_state: &'a PpuState,
+}
+
+impl<'a> ImmediateBus<'a> {
+    pub fn new(address_space: &'a mut AddressSpace, ppu_state: &'a PpuState) -> Self {
+        Self { address_space, ppu_state }
+    }
+}
+
+impl CpuBus for ImmediateBus<'_> {
+    fn read(&mut self, address: u16) -> u8 {
+        self.address_space.read_address_u8(address, self.ppu_state)
+    }
+
+    fn write(&mut self, address: u16, value: u8) {
+        self.address_space.write_address_u8(address, value, self.ppu_state);
+    }
+
+    fn idle(&mut self) {}
+
+    fn trigger_oam_bug(&mut self, address: u16, access: OamBugAccess) {
+        ppu::trigger_oam_bug(self.ppu_state, self.address_space, address, access);
+    }
+
+    fn address_space(&mut self) -> &mut AddressSpace {
+        self.address_space
+    }
+
+    fn ppu_state(&self) -> &PpuState {
+        self.ppu_state
+    }
+}
@@ -5,9 +5,9 @@ use crate::cpu::registers::{
 };
 use crate::memory::AddressSpace;
 
-use crate::cpu::{CgbSpeedMode, ExecutionMode};
+use crate::cpu::{CgbSpeedMode, CpuBus, ExecutionMode};
 use crate::memory::ioregisters::IoRegister;
-use crate::ppu::PpuState;
+use crate::ppu::OamBugAccess;
 pub use parse::{ParseError, parse_next_instruction};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -45,40 +45,37 @@ pub enum ReadTarget {
 }
 
 impl ReadTarget {
-    fn read_value(
-        self,
-        cpu_registers: &mut CpuRegisters,
-        address_space: &AddressSpace,
-        ppu_state: &PpuState,
-    ) -> u8 {
+    fn read_value<B: CpuBus>(self, cpu_registers: &mut CpuRegisters, bus: &mut B) -> u8 {
         match self {
             Self::Register(register) => cpu_registers.read_register(register),
             Self::Immediate(n) => n,
-            Self::IndirectHL => address_space.read_address_u8(cpu_registers.hl(), ppu_state),
+            Self::IndirectHL => bus.read(cpu_registers.hl()),
             Self::IndirectHLInc => {
                 let hl = cpu_registers.hl();
-                let value = address_space.read_address_u8(hl, ppu_state);
+                bus.trigger_oam_bug(hl, OamBugAccess::ReadIncrement);
+                let value = bus.read(hl);
                 cpu_registers.set_hl(hl.wrapping_add(1));
                 value
             }
             Self::IndirectHLDec => {
                 let hl = cpu_registers.hl();
-                let value = address_space.read_address_u8(hl, ppu_state);
+                bus.trigger_oam_bug(hl, OamBugAccess::ReadIncrement);
+                let value = bus.read(hl);
                 cpu_registers.set_hl(hl.wrapping_sub(1));
                 value
             }
-            Self::IndirectBC => address_space.read_address_u8(cpu_registers.bc(), ppu_state),
-            Self::IndirectDE => address_space.read_address_u8(cpu_registers.de(), ppu_state),
+            Self::IndirectBC => bus.read(cpu_registers.bc()),
+            Self::IndirectDE => bus.read(cpu_registers.de()),
             Self::Accumulator => cpu_registers.accumulator,
             Self::FFIndirectC => {
                 let address = u16::from_be_bytes([0xFF, cpu_registers.c]);
-                address_space.read_address_u8(address, ppu_state)
+                bus.read(address)
             }
             Self::FFDirect(n) => {
                 let address = u16::from_be_bytes([0xFF, n]);
-                address_space.read_address_u8(address, ppu_state)
+                bus.read(address)
             }
-            Self::Direct(nn) => address_space.read_address_u8(nn, ppu_state),
+            Self::Direct(nn) => bus.read(nn),
         }
     }
 
@@ -113,49 +110,45 @@ pub enum WriteTarget {
 }
 
 impl WriteTarget {
-    fn write_value(
-        self,
-        value: u8,
-        cpu_registers: &mut CpuRegisters,
-        address_space: &mut AddressSpace,
-        ppu_state: &PpuState,
-    ) {
+    fn write_value<B: CpuBus>(self, value: u8, cpu_registers: &mut CpuRegisters, bus: &mut B) {
         match self {
             Self::Register(register) => {
                 cpu_registers.set_register(register, value);
             }
             Self::IndirectHL => {
-                address_space.write_address_u8(cpu_registers.hl(), value, ppu_state);
+                bus.write(cpu_registers.hl(), value);
             }
             Self::IndirectHLInc => {
                 let hl = cpu_registers.hl();
-                address_space.write_address_u8(hl, value, ppu_state);
+                bus.trigger_oam_bug(hl, OamBugAccess::Write);
+                bus.write(hl, value);
                 cpu_registers.set_hl(hl.wrapping_add(1));
             }
             Self::IndirectHLDec => {
                 let hl = cpu_registers.hl();
-                address_space.write_address_u8(hl, value, ppu_state);
+                bus.trigger_oam_bug(hl, OamBugAccess::Write);
+                bus.write(hl, value);
                 cpu_registers.set_hl(hl.wrapping_sub(1));
             }
             Self::IndirectBC => {
-                address_space.write_address_u8(cpu_registers.bc(), value, ppu_state);
+                bus.write(cpu_registers.bc(), value);
             }
             Self::IndirectDE => {
-                address_space.write_address_u8(cpu_registers.de(), value, ppu_state);
+                bus.write(cpu_registers.de(), value);
             }
             Self::Accumulator => {
                 cpu_registers.accumulator = value;
             }
             Self::FFIndirectC => {
                 let address = u16::from_be_bytes([0xFF, cpu_registers.c]);
-                address_space.write_address_u8(address, value, ppu_state);
+                bus.write(address, value);
             }
             Self::FFDirect(n) => {
                 let address = u16::from_be_bytes([0xFF, n]);
-                address_space.write_address_u8(address, value, ppu_state);
+                bus.write(address, value);
             }
             Self::Direct(nn) => {
-                address_space.write_address_u8(nn, value, ppu_state);
+                bus.write(nn, value);
             }
         }
     }
@@ -183,32 +176,21 @@ pub enum ModifyTarget {
 }
 
 impl ModifyTarget {
-    fn read_value(
-        self,
-        cpu_registers: &CpuRegisters,
-        address_space: &AddressSpace,
-        ppu_state: &PpuState,
-    ) -> u8 {
+    fn read_value<B: CpuBus>(self, cpu_registers: &CpuRegisters, bus: &mut B) -> u8 {
         match self {
             Self::Register(register) => cpu_registers.read_register(register),
-            Self::IndirectHL => address_space.read_address_u8(cpu_registers.hl(), ppu_state),
+            Self::IndirectHL => bus.read(cpu_registers.hl()),
             Self::Accumulator => cpu_registers.accumulator,
         }
     }
 
-    fn write_value(
-        self,
-        value: u8,
-        cpu_registers: &mut CpuRegisters,
-        address_space: &mut AddressSpace,
-        ppu_state: &PpuState,
-    ) {
+    fn write_value<B: CpuBus>(self, value: u8, cpu_registers: &mut CpuRegisters, bus: &mut B) {
         match self {
             Self::Register(register) => {
                 cpu_registers.set_register(register, value);
             }
             Self::IndirectHL => {
-                address_space.write_address_u8(cpu_registers.hl(), value, ppu_state);
+                bus.write(cpu_registers.hl(), value);
             }
             Self::Accumulator => {
                 cpu_registers.accumulator = value;
@@ -337,45 +319,33 @@ impl Instruction {
     ///
     /// This method does *not* update the PC register for the given instruction. It expects that the
     /// PC register was updated before this method was called.
-    pub fn execute(
-        self,
-        address_space: &mut AddressSpace,
-        cpu_registers: &mut CpuRegisters,
-        ppu_state: &PpuState,
-    ) {
+    pub fn execute<B: CpuBus>(self, bus: &mut B, cpu_registers: &mut CpuRegisters) {
         match self {
             Self::Load(write_target, read_target) => {
-                let value = read_target.read_value(cpu_registers, address_space, ppu_state);
-                write_target.write_value(value, cpu_registers, address_space, ppu_state);
+                let value = read_target.read_value(cpu_registers, bus);
+                write_target.write_value(value, cpu_registers, bus);
             }
             Self::LoadRegisterPairImmediate(rr, nn) => {
                 cpu_registers.set_register_pair(rr, nn);
             }
             Self::LoadDirectStackPointer(nn) => {
-                address_space.write_address_u16(nn, cpu_registers.sp, ppu_state);
+                bus.write_u16(nn, cpu_registers.sp);
             }
             Self::LoadStackPointerHL => {
+                bus.idle();
                 cpu_registers.sp = cpu_registers.hl();
             }
             Self::PushStack(rr) => {
-                cpu_registers.sp -= 2;
-                address_space.write_address_u16(
-                    cpu_registers.sp,
-                    cpu_registers.read_register_pair(rr),
-                    ppu_state,
-                );
+                push_stack(bus, cpu_registers, cpu_registers.read_register_pair(rr));
             }
             Self::PopStack(rr) => {
-                cpu_registers.set_register_pair(
-                    rr,
-                    address_space.read_address_u16(cpu_registers.sp, ppu_state),
-                );
-                cpu_registers.sp += 2;
+                let value = pop_stack(bus, cpu_registers);
+                cpu_registers.set_register_pair(rr, value);
             }
             Self::Add(read_target) => {
                 let (sum, c_flag, h_flag) = add(
                     cpu_registers.accumulator,
-                    read_target.read_value(cpu_registers, address_space, ppu_state),
+                    read_target.read_value(cpu_registers, bus),
                     false,
                 );
                 cpu_registers.accumulator = sum;
@@ -384,7 +354,7 @@ impl Instruction {
             Self::AddWithCarry(read_target) => {
                 let (sum, c_flag, h_flag) = add(
                     cpu_registers.accumulator,
-                    read_target.read_value(cpu_registers, address_space, ppu_state),
+                    read_target.read_value(cpu_registers, bus),
                     cpu_registers.c_flag(),
                 );
                 cpu_registers.accumulator = sum;
@@ -393,7 +363,7 @@ impl Instruction {
             Self::Subtract(read_target) => {
                 let (difference, c_flag, h_flag) = sub(
                     cpu_registers.accumulator,
-                    read_target.read_value(cpu_registers, address_space, ppu_state),
+                    read_target.read_value(cpu_registers, bus),
                     false,
                 );
                 cpu_registers.accumulator = difference;
@@ -402,7 +372,7 @@ impl Instruction {
             Self::SubtractWithCarry(read_target) => {
                 let (difference, c_flag, h_flag) = sub(
                     cpu_registers.accumulator,
-                    read_target.read_value(cpu_registers, address_space, ppu_state),
+                    read_target.read_value(cpu_registers, bus),
                     cpu_registers.c_flag(),
                 );
                 cpu_registers.accumulator = difference;
@@ -411,15 +381,15 @@ impl Instruction {
             Self::Compare(read_target) => {
                 let (difference, c_flag, h_flag) = sub(
                     cpu_registers.accumulator,
-                    read_target.read_value(cpu_registers, address_space, ppu_state),
+                    read_target.read_value(cpu_registers, bus),
                     false,
                 );
                 cpu_registers.set_flags(ZFlag(difference == 0), NFlag(true), h_flag, c_flag);
             }
             Self::Increment(modify_target) => {
-                let value = modify_target.read_value(cpu_registers, address_space, ppu_state);
+                let value = modify_target.read_value(cpu_registers, bus);
                 let (sum, _, h_flag) = add(value, 1, false);
-                modify_target.write_value(sum, cpu_registers, address_space, ppu_state);
+                modify_target.write_value(sum, cpu_registers, bus);
                 cpu_registers.set_some_flags(
                     Some(ZFlag(sum == 0)),
                     Some(NFlag(false)),
@@ -428,9 +398,9 @@ impl Instruction {
                 );
             }
             Self::Decrement(modify_target) => {
-                let value = modify_target.read_value(cpu_registers, address_space, ppu_state);
+                let value = modify_target.read_value(cpu_registers, bus);
                 let (difference, _, h_flag) = sub(value, 1, false);
-                modify_target.write_value(difference, cpu_registers, address_space, ppu_state);
+                modify_target.write_value(difference, cpu_registers, bus);
                 cpu_registers.set_some_flags(
                     Some(ZFlag(difference == 0)),
                     Some(NFlag(true)),
@@ -439,14 +409,12 @@ impl Instruction {
                 );
             }
             Self::And(read_target) => {
-                let value = cpu_registers.accumulator
-                    & read_target.read_value(cpu_registers, address_space, ppu_state);
+                let value = cpu_registers.accumulator & read_target.read_value(cpu_registers, bus);
                 cpu_registers.accumulator = value;
                 cpu_registers.set_flags(ZFlag(value == 0), NFlag(false), HFlag(true), CFlag(false));
             }
             Self::Or(read_target) => {
-                let value = cpu_registers.accumulator
-                    | read_target.read_value(cpu_registers, address_space, ppu_state);
+                let value = cpu_registers.accumulator | read_target.read_value(cpu_registers, bus);
                 cpu_registers.accumulator = value;
                 cpu_registers.set_flags(
                     ZFlag(value == 0),
@@ -456,8 +424,7 @@ impl Instruction {
                 );
             }
             Self::Xor(read_target) => {
-                let value = cpu_registers.accumulator
-                    ^ read_target.read_value(cpu_registers, address_space, ppu_state);
+                let value = cpu_registers.accumulator ^ read_target.read_value(cpu_registers, bus);
                 cpu_registers.accumulator = value;
                 cpu_registers.set_flags(
                     ZFlag(value == 0),
@@ -467,71 +434,75 @@ impl Instruction {
                 );
             }
             Self::AddHLRegister(rr) => {
+                bus.idle();
                 let (sum, c_flag, h_flag) =
                     add_u16(cpu_registers.hl(), cpu_registers.read_register_pair(rr));
                 cpu_registers.set_hl(sum);
                 cpu_registers.set_some_flags(None, Some(NFlag(false)), Some(h_flag), Some(c_flag));
             }
             Self::IncRegisterPair(rr) => {
-                cpu_registers
-                    .set_register_pair(rr, cpu_registers.read_register_pair(rr).wrapping_add(1));
+                let value = cpu_registers.read_register_pair(rr);
+                bus.trigger_oam_bug(value, OamBugAccess::Write);
+                bus.idle();
+                cpu_registers.set_register_pair(rr, value.wrapping_add(1));
             }
             Self::DecRegisterPair(rr) => {
-                cpu_registers
-                    .set_register_pair(rr, cpu_registers.read_register_pair(rr).wrapping_sub(1));
+                let value = cpu_registers.read_register_pair(rr);
+                bus.trigger_oam_bug(value, OamBugAccess::Write);
+                bus.idle();
+                cpu_registers.set_register_pair(rr, value.wrapping_sub(1));
             }
             Self::AddSPImmediate(e) => {
+                bus.idle();
+                bus.idle();
                 let (sp, c_flag, h_flag) = add_sp_offset(cpu_registers.sp, e);
                 cpu_registers.sp = sp;
                 cpu_registers.set_flags(ZFlag(false), NFlag(false), h_flag, c_flag);
             }
             Self::LoadHLStackPointerOffset(e) => {
+                bus.idle();
                 let (sp, c_flag, h_flag) = add_sp_offset(cpu_registers.sp, e);
                 cpu_registers.set_hl(sp);
                 cpu_registers.set_flags(ZFlag(false), NFlag(false), h_flag, c_flag);
             }
             Self::RotateLeft(modify_target) => {
-                let (value, c_flag) =
-                    rotate_left(modify_target.read_value(cpu_registers, address_space, ppu_state));
-                modify_target.write_value(value, cpu_registers, address_space, ppu_state);
+                let (value, c_flag) = rotate_left(modify_target.read_value(cpu_registers, bus));
+                modify_target.write_value(value, cpu_registers, bus);
                 let z_flag = ZFlag(modify_target != ModifyTarget::Accumulator && value == 0);
                 cpu_registers.set_flags(z_flag, NFlag(false), HFlag(false), c_flag);
             }
             Self::RotateLeftThruCarry(modify_target) => {
                 let (value, c_flag) = rotate_left_thru_carry(
-                    modify_target.read_value(cpu_registers, address_space, ppu_state),
+                    modify_target.read_value(cpu_registers, bus),
                     cpu_registers.c_flag(),
                 );
-                modify_target.write_value(value, cpu_registers, address_space, ppu_state);
+                modify_target.write_value(value, cpu_registers, bus);
                 let z_flag = ZFlag(modify_target != ModifyTarget::Accumulator && value == 0);
                 cpu_registers.set_flags(z_flag, NFlag(false), HFlag(false), c_flag);
             }
             Self::RotateRight(modify_target) => {
-                let (value, c_flag) =
-                    rotate_right(modify_target.read_value(cpu_registers, address_space, ppu_state));
-                modify_target.write_value(value, cpu_registers, address_space, ppu_state);
+                let (value, c_flag) = rotate_right(modify_target.read_value(cpu_registers, bus));
+                modify_target.write_value(value, cpu_registers, bus);
                 let z_flag = ZFlag(modify_target != ModifyTarget::Accumulator && value == 0);
                 cpu_registers.set_flags(z_flag, NFlag(false), HFlag(false), c_flag);
             }
             Self::RotateRightThruCarry(modify_target) => {
                 let (value, c_flag) = rotate_right_thru_carry(
-                    modify_target.read_value(cpu_registers, address_space, ppu_state),
+                    modify_target.read_value(cpu_registers, bus),
                     cpu_registers.c_flag(),
                 );
-                modify_target.write_value(value, cpu_registers, address_space, ppu_state);
+                modify_target.write_value(value, cpu_registers, bus);
                 let z_flag = ZFlag(modify_target != ModifyTarget::Accumulator && value == 0);
                 cpu_registers.set_flags(z_flag, NFlag(false), HFlag(false), c_flag);
             }
             Self::ShiftLeft(modify_target) => {
-                let (value, c_flag) =
-                    shift_left(modify_target.read_value(cpu_registers, address_space, ppu_state));
-                modify_target.write_value(value, cpu_registers, address_space, ppu_state);
+                let (value, c_flag) = shift_left(modify_target.read_value(cpu_registers, bus));
+                modify_target.write_value(value, cpu_registers, bus);
                 cpu_registers.set_flags(ZFlag(value == 0), NFlag(false), HFlag(false), c_flag);
             }
             Self::Swap(modify_target) => {
-                let value =
-                    swap_bits(modify_target.read_value(cpu_registers, address_space, ppu_state));
-                modify_target.write_value(value, cpu_registers, address_space, ppu_state);
+                let value = swap_bits(modify_target.read_value(cpu_registers, bus));
+                modify_target.write_value(value, cpu_registers, bus);
                 cpu_registers.set_flags(
                     ZFlag(value == 0),
                     NFlag(false),
@@ -540,25 +511,19 @@ impl Instruction {
                 );
             }
             Self::ArithmeticShiftRight(modify_target) => {
-                let (value, c_flag) = shift_right_arithmetic(modify_target.read_value(
-                    cpu_registers,
-                    address_space,
-                    ppu_state,
-                ));
-                modify_target.write_value(value, cpu_registers, address_space, ppu_state);
+                let (value, c_flag) =
+                    shift_right_arithmetic(modify_target.read_value(cpu_registers, bus));
+                modify_target.write_value(value, cpu_registers, bus);
                 cpu_registers.set_flags(ZFlag(value == 0), NFlag(false), HFlag(false), c_flag);
             }
             Self::LogicalShiftRight(modify_target) => {
-                let (value, c_flag) = shift_right_logical(modify_target.read_value(
-                    cpu_registers,
-                    address_space,
-                    ppu_state,
-                ));
-                modify_target.write_value(value, cpu_registers, address_space, ppu_state);
+                let (value, c_flag) =
+                    shift_right_logical(modify_target.read_value(cpu_registers, bus));
+                modify_target.write_value(value, cpu_registers, bus);
                 cpu_registers.set_flags(ZFlag(value == 0), NFlag(false), HFlag(false), c_flag);
             }
             Self::TestBit(n, read_target) => {
-                let r_value = read_target.read_value(cpu_registers, address_space, ppu_state);
+                let r_value = read_target.read_value(cpu_registers, bus);
                 let z_flag = ZFlag(r_value & (1 << n) == 0);
                 cpu_registers.set_some_flags(
                     Some(z_flag),
@@ -568,14 +533,12 @@ impl Instruction {
                 );
             }
             Self::SetBit(n, modify_target) => {
-                let value =
-                    (1 << n) | modify_target.read_value(cpu_registers, address_space, ppu_state);
-                modify_target.write_value(value, cpu_registers, address_space, ppu_state);
+                let value = (1 << n) | modify_target.read_value(cpu_registers, bus);
+                modify_target.write_value(value, cpu_registers, bus);
             }
             Self::ResetBit(n, modify_target) => {
-                let value =
-                    !(1 << n) & modify_target.read_value(cpu_registers, address_space, ppu_state);
-                modify_target.write_value(value, cpu_registers, address_space, ppu_state);
+                let value = !(1 << n) & modify_target.read_value(cpu_registers, bus);
+                modify_target.write_value(value, cpu_registers, bus);
             }
             Self::ComplementCarryFlag => {
                 cpu_registers.set_some_flags(
@@ -601,6 +564,7 @@ impl Instruction {
                 cpu_registers.set_some_flags(None, Some(NFlag(true)), Some(HFlag(true)), None);
             }
             Self::Jump(nn) => {
+                bus.idle();
                 cpu_registers.pc = nn;
             }
             Self::JumpHL => {
@@ -608,49 +572,51 @@ impl Instruction {
             }
             Self::JumpCond(cc, nn) => {
                 if cc.check(cpu_registers) {
+                    bus.idle();
                     cpu_registers.pc = nn;
                 }
             }
             Self::RelativeJump(e) => {
+                bus.idle();
                 let pc = (i32::from(cpu_registers.pc) + i32::from(e)) as u16;
                 cpu_registers.pc = pc;
             }
             Self::RelativeJumpCond(cc, e) => {
                 if cc.check(cpu_registers) {
+                    bus.idle();
                     let pc = (i32::from(cpu_registers.pc) + i32::from(e)) as u16;
                     cpu_registers.pc = pc;
                 }
             }
             Self::Call(nn) => {
-                cpu_registers.sp -= 2;
-                address_space.write_address_u16(cpu_registers.sp, cpu_registers.pc, ppu_state);
+                push_stack(bus, cpu_registers, cpu_registers.pc);
                 cpu_registers.pc = nn;
             }
             Self::CallCond(cc, nn) => {
                 if cc.check(cpu_registers) {
-                    cpu_registers.sp -= 2;
-                    address_space.write_address_u16(cpu_registers.sp, cpu_registers.pc, ppu_state);
+                    push_stack(bus, cpu_registers, cpu_registers.pc);
                     cpu_registers.pc = nn;
                 }
             }
             Self::Return => {
-                cpu_registers.pc = address_space.read_address_u16(cpu_registers.sp, ppu_state);
-                cpu_registers.sp += 2;
+                cpu_registers.pc = pop_stack(bus, cpu_registers);
+                bus.idle();
             }
             Self::ReturnCond(cc) => {
+                // Checking the condition takes an extra M-cycle
+                bus.idle();
                 if cc.check(cpu_registers) {
-                    cpu_registers.pc = address_space.read_address_u16(cpu_registers.sp, ppu_state);
-                    cpu_registers.sp += 2;
+                    cpu_registers.pc = pop_stack(bus, cpu_registers);
+                    bus.idle();
                 }
             }
             Self::ReturnFromInterruptHandler => {
-                cpu_registers.pc = address_space.read_address_u16(cpu_registers.sp, ppu_state);
-                cpu_registers.sp += 2;
+                cpu_registers.pc = pop_stack(bus, cpu_registers);
+                bus.idle();
                 cpu_registers.ime = true;
             }
             Self::RestartCall(rst_address) => {
-                cpu_registers.sp -= 2;
-                address_space.write_address_u16(cpu_registers.sp, cpu_registers.pc, ppu_state);
+                push_stack(bus, cpu_registers, cpu_registers.pc);
                 cpu_registers.pc = rst_address.into();
             }
             Self::Halt => {
@@ -658,6 +624,7 @@ impl Instruction {
 
                 // The HALT bug: If HALT is executed while IME=0 and (IE & IF) != 0, the PC is not
                 // incremented after the next opcode read
+                let address_space = bus.address_space();
                 let ie_value = address_space.get_ie_register();
                 let if_value = address_space.get_io_registers().read_register(IoRegister::IF);
                 if !cpu_registers.ime && ie_value & if_value != 0 {
@@ -665,6 +632,7 @@ impl Instruction {
                 }
             }
             Self::Stop => {
+                let address_space = bus.address_space();
                 let key1_value = address_space.get_io_registers().read_register(IoRegister::KEY1);
                 if matches!(cpu_registers.execution_mode, ExecutionMode::GameBoyColor)
                     && key1_value & 0x01 != 0
@@ -786,6 +754,34 @@ impl Instruction {
     }
 }
 
+/// Push a 16-bit value onto the stack over 3 M-cycles: an internal cycle to decrement SP, and then
+/// 2 writes. The high byte is written first.
+fn push_stack<B: CpuBus>(bus: &mut B, cpu_registers: &mut CpuRegisters, value: u16) {
+    let [lsb, msb] = value.to_le_bytes();
+
+    bus.trigger_oam_bug(cpu_registers.sp, OamBugAccess::Write);
+    bus.idle();
+
+    for byte in [msb, lsb] {
+        cpu_registers.sp = cpu_registers.sp.wrapping_sub(1);
+        bus.trigger_oam_bug(cpu_registers.sp, OamBugAccess::Write);
+        bus.write(cpu_registers.sp, byte);
+    }
+}
+
+/// Pop a 16-bit value off of the stack over 2 M-cycles.
+fn pop_stack<B: CpuBus>(bus: &mut B, cpu_registers: &mut CpuRegisters) -> u16 {
+    bus.trigger_oam_bug(cpu_registers.sp, OamBugAccess::ReadIncrement);
+    let lsb = bus.read(cpu_registers.sp);
+    cpu_registers.sp = cpu_registers.sp.wrapping_add(1);
+
+    bus.trigger_oam_bug(cpu_registers.sp, OamBugAccess::Read);
+    let msb = bus.read(cpu_registers.sp);
+    cpu_registers.sp = cpu_registers.sp.wrapping_add(1);
+
+    u16::from_le_bytes([lsb, msb])
+}
+
 fn add(l_value: u8, r_value: u8, carry: bool) -> (u8, CFlag, HFlag) {
     let carry = u8::from(carry);
     let (sum, carry_flag) = match l_value.overflowing_add(r_value) {
@@ -1,7 +1,6 @@
+use crate::cpu::CpuBus;
 use crate::cpu::instructions::{Instruction, JumpCondition, ModifyTarget, ReadTarget, WriteTarget};
 use crate::cpu::registers::{CpuRegister, CpuRegisterPair};
-use crate::memory::AddressSpace;
-use crate::ppu::PpuState;
 use thiserror::Error;
 
 #[derive(Error, Debug)]
@@ -10,13 +9,12 @@ pub enum ParseError {
     InvalidOpcode { opcodes: Vec<u8> },
 }
 
-pub fn parse_next_instruction(
-    address_space: &AddressSpace,
+pub fn parse_next_instruction<B: CpuBus>(
+    bus: &mut B,
     mut pc: u16,
-    ppu_state: &PpuState,
     halt_bug_triggered: bool,
 ) -> Result<(Instruction, u16), ParseError> {
-    let opcode = address_space.read_address_u8(pc, ppu_state);
+    let opcode = bus.read(pc);
 
     // If HALT bug triggered, act as if the opcode read did not advance the PC
     if halt_bug_triggered {
@@ -27,7 +25,7 @@ pub fn parse_next_instruction(
         0x00 => Ok((Instruction::NoOp, pc + 1)),
         0x01 | 0x11 | 0x21 | 0x31 => {
             let rr = register_pair_for_other_ops(opcode);
-            let nn = address_space.read_address_u16(pc + 1, ppu_state);
+            let nn = bus.read_u16(pc + 1);
             Ok((Instruction::LoadRegisterPairImmediate(rr, nn), pc + 3))
         }
         0x02 => Ok((Instruction::Load(WriteTarget::IndirectBC, ReadTarget::Accumulator), pc + 1)),
@@ -48,12 +46,12 @@ pub fn parse_next_instruction(
         0x06 | 0x0E | 0x16 | 0x1E | 0x26 | 0x2E | 0x36 | 0x3E => {
             let write_target = CpuRegister::from_mid_opcode_bits(opcode)
                 .map_or(WriteTarget::IndirectHL, WriteTarget::Register);
-            let n = address_space.read_address_u8(pc + 1, ppu_state);
+            let n = bus.read(pc + 1);
             Ok((Instruction::Load(write_target, ReadTarget::Immediate(n)), pc + 2))
         }
         0x07 => Ok((Instruction::RotateLeft(ModifyTarget::Accumulator), pc + 1)),
         0x08 => {
-            let nn = address_space.read_address_u16(pc + 1, ppu_state);
+            let nn = bus.read_u16(pc + 1);
             Ok((Instruction::LoadDirectStackPointer(nn), pc + 3))
         }
         0x09 | 0x19 | 0x29 | 0x39 => {
@@ -70,14 +68,14 @@ pub fn parse_next_instruction(
         0x12 => Ok((Instruction::Load(WriteTarget::IndirectDE, ReadTarget::Accumulator), pc + 1)),
         0x17 => Ok((Instruction::RotateLeftThruCarry(ModifyTarget::Accumulator), pc + 1)),
         0x18 => {
-            let e = address_space.read_address_u8(pc + 1, ppu_state) as i8;
+            let e = bus.read(pc + 1) as i8;
             Ok((Instruction::RelativeJump(e), pc + 2))
         }
         0x1A => Ok((Instruction::Load(WriteTarget::Accumulator, ReadTarget::IndirectDE), pc + 1)),
         0x1F => Ok((Instruction::RotateRightThruCarry(ModifyTarget::Accumulator), pc + 1)),
         0x20 | 0x28 | 0x30 | 0x38 => {
             let cc = parse_jump_condition(opcode);
-            let e = address_space.read_address_u8(pc + 1, ppu_state) as i8;
+            let e = bus.read(pc + 1) as i8;
             Ok((Instruction::RelativeJumpCond(cc, e), pc + 2))
         }
         0x22 => {
@@ -157,16 +155,16 @@ pub fn parse_next_instruction(
         }
         0xC2 | 0xCA | 0xD2 | 0xDA => {
             let cc = parse_jump_condition(opcode);
-            let nn = address_space.read_address_u16(pc + 1, ppu_state);
+            let nn = bus.read_u16(pc + 1);
             Ok((Instruction::JumpCond(cc, nn), pc + 3))
         }
         0xC3 => {
-            let nn = address_space.read_address_u16(pc + 1, ppu_state);
+            let nn = bus.read_u16(pc + 1);
             Ok((Instruction::Jump(nn), pc + 3))
         }
         0xC4 | 0xCC | 0xD4 | 0xDC => {
             let cc = parse_jump_condition(opcode);
-            let nn = address_space.read_address_u16(pc + 1, ppu_state);
+            let nn = bus.read_u16(pc + 1);
             Ok((Instruction::CallCond(cc, nn), pc + 3))
         }
         0xC5 | 0xD5 | 0xE5 | 0xF5 => {
@@ -174,7 +172,7 @@ pub fn parse_next_instruction(
             Ok((Instruction::PushStack(rr), pc + 1))
         }
         0xC6 => {
-            let n = address_space.read_address_u8(pc + 1, ppu_state);
+            let n = bus.read(pc + 1);
             Ok((Instruction::Add(ReadTarget::Immediate(n)), pc + 2))
         }
         0xC7 | 0xCF | 0xD7 | 0xDF | 0xE7 | 0xEF | 0xF7 | 0xFF => {
@@ -182,80 +180,76 @@ pub fn parse_next_instruction(
             Ok((Instruction::RestartCall(rst_address), pc + 1))
         }
         0xC9 => Ok((Instruction::Return, pc + 1)),
-        0xCB => Ok(parse_cb_prefixed_opcode(address_space, pc, ppu_state)),
+        0xCB => Ok(parse_cb_prefixed_opcode(bus, pc)),
         0xCD => {
-            let nn = address_space.read_address_u16(pc + 1, ppu_state);
+            let nn = bus.read_u16(pc + 1);
             Ok((Instruction::Call(nn), pc + 3))
         }
         0xCE => {
-            let n = address_space.read_address_u8(pc + 1, ppu_state);
+            let n = bus.read(pc + 1);
             Ok((Instruction::AddWithCarry(ReadTarget::Immediate(n)), pc + 2))
         }
         0xD6 => {
-            let n = address_space.read_address_u8(pc + 1, ppu_state);
+            let n = bus.read(pc + 1);
             Ok((Instruction::Subtract(ReadTarget::Immediate(n)), pc + 2))
         }
         0xD9 => Ok((Instruction::ReturnFromInterruptHandler, pc + 1)),
         0xDE => {
-            let n = address_space.read_address_u8(pc + 1, ppu_state);
+            let n = bus.read(pc + 1);
             Ok((Instruction::SubtractWithCarry(ReadTarget::Immediate(n)), pc + 2))
         }
         0xE0 => {
-            let n = address_space.read_address_u8(pc + 1, ppu_state);
+            let n = bus.read(pc + 1);
             Ok((Instruction::Load(WriteTarget::FFDirect(n), ReadTarget::Accumulator), pc + 2))
         }
         0xE2 => Ok((Instruction::Load(WriteTarget::FFIndirectC, ReadTarget::Accumulator), pc + 1)),
         0xE6 => {
-            let n = address_space.read_address_u8(pc + 1, ppu_state);
+            let n = bus.read(pc + 1);
             Ok((Instruction::And(ReadTarget::Immediate(n)), pc + 2))
         }
         0xE8 => {
-            let e = address_space.read_address_u8(pc + 1, ppu_state) as i8;
+            let e = bus.read(pc + 1) as i8;
             Ok((Instruction::AddSPImmediate(e), pc + 2))
         }
         0xE9 => Ok((Instruction::JumpHL, pc + 1)),
         0xEA => {
-            let nn = address_space.read_address_u16(pc + 1, ppu_state);
+            let nn = bus.read_u16(pc + 1);
             Ok((Instruction::Load(WriteTarget::Direct(nn), ReadTarget::Accumulator), pc + 3))
         }
         0xEE => {
-            let n = address_space.read_address_u8(pc + 1, ppu_state);
+            let n = bus.read(pc + 1);
             Ok((Instruction::Xor(ReadTarget::Immediate(n)), pc + 2))
         }
         0xF0 => {
-            let n = address_space.read_address_u8(pc + 1, ppu_state);
+            let n = bus.read(pc + 1);
             Ok((Instruction::Load(WriteTarget::Accumulator, ReadTarget::FFDirect(n)), pc + 2))
         }
         0xF2 => Ok((Instruction::Load(WriteTarget::Accumulator, ReadTarget::FFIndirectC), pc + 1)),
         0xF3 => Ok((Instruction::DisableInterrupts, pc + 1)),
         0xF6 => {
-            let n = address_space.read_address_u8(pc + 1, ppu_state);
+            let n = bus.read(pc + 1);
             Ok((Instruction::Or(ReadTarget::Immediate(n)), pc + 2))
         }
         0xF8 => {
-            let e = address_space.read_address_u8(pc + 1, ppu_state) as i8;
+            let e = bus.read(pc + 1) as i8;
             Ok((Instruction::LoadHLStackPointerOffset(e), pc + 2))
         }
         0xF9 => Ok((Instruction::LoadStackPointerHL, pc + 1)),
         0xFA => {
-            let nn = address_space.read_address_u16(pc + 1, ppu_state);
+            let nn = bus.read_u16(pc + 1);
             Ok((Instruction::Load(WriteTarget::Accumulator, ReadTarget::Direct(nn)), pc + 3))
         }
         0xFB => Ok((Instruction::EnableInterrupts, pc + 1)),
         0xFE => {
-            let n = address_space.read_address_u8(pc + 1, ppu_state);
+            let n = bus.read(pc + 1);
             Ok((Instruction::Compare(ReadTarget::Immediate(n)), pc + 2))
         }
         _ => Err(ParseError::InvalidOpcode { opcodes: vec![opcode] }),
     }
 }
 
-fn parse_cb_prefixed_opcode(
-    address_space: &AddressSpace,
-    pc: u16,
-    ppu_state: &PpuState,
-) -> (Instruction, u16) {
-    let opcode = address_space.read_address_u8(pc + 1, ppu_state);
+fn parse_cb_prefixed_opcode<B: CpuBus>(bus: &mut B, pc: u16) -> (Instruction, u16) {
+    let opcode = bus.read(pc + 1);
     match opcode {
         opcode @ 0x00..=0x07 => {
             let modify_target = CpuRegister::from_low_opcode_bits(opcode)
@@ -179,6 +179,22 @@ impl CpuRegisters {
         }
     }
 
+    /// Creates a new `CpuRegisters` value with all registers zeroed, which is the state the CPU
+    /// is in at power-on before running the boot ROM.
+    pub fn new_pre_boot(execution_mode: ExecutionMode) -> Self {
+        Self {
+            accumulator: 0x00,
+            flags: 0x00,
+            c: 0x00,
+            e: 0x00,
+            h: 0x00,
+            l: 0x00,
+            pc: 0x0000,
+            sp: 0x0000,
+            ..Self::new(execution_mode)
+        }
+    }
+
     /// Read the A and F registers together as a 16-bit value.
     pub fn af(&self) -> u16 {
         u16::from_be_bytes([self.accumulator, self.flags])
@@ -6,10 +6,11 @@ mod bitshift;
 mod controlflow;
 mod cyclecount;
 mod load;
+mod mcycle;
 mod singlebit;
 
 use crate::cpu::registers::CpuRegister;
-use crate::cpu::{CpuRegisters, ExecutionMode, instructions};
+use crate::cpu::{CpuRegisters, ExecutionMode, ImmediateBus, instructions};
 use crate::memory::{AddressSpace, Cartridge};
 use std::collections::HashMap;
 use std::fmt::Formatter;
@@ -176,17 +177,14 @@ fn run_test(program_hex: &str, expected_state: &ExpectedState) {
 
     let ppu_state = PpuState::new(ExecutionMode::GameBoy);
 
+    let mut bus = ImmediateBus::new(&mut address_space, &ppu_state);
     while cpu_registers.pc >= 0x0100 && cpu_registers.pc < rom_len {
-        let (instruction, pc) = instructions::parse_next_instruction(
-            &address_space,
-            cpu_registers.pc,
-            &ppu_state,
-            false,
-        )
-        .expect("all instructions in program should be valid");
+        let (instruction, pc) =
+            instructions::parse_next_instruction(&mut bus, cpu_registers.pc, false)
+                .expect("all instructions in program should be valid");
         cpu_registers.pc = pc;
 
-        instruction.execute(&mut address_space, &mut cpu_registers, &ppu_state);
+        instruction.execute(&mut bus, &mut cpu_registers);
     }
 
     expected_state.assert_matches(&cpu_registers, &address_space, &ppu_state);
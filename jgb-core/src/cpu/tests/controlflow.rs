@@ -1,4 +1,9 @@
 use super::{ExpectedState, hash_map, run_test};
+use crate::cpu::{self, CpuRegisters, ExecutionMode, ImmediateBus};
+use crate::memory::ioregisters::IoRegister;
+use crate::memory::{AddressSpace, Cartridge};
+use crate::ppu::PpuState;
+use crate::startup::ControllerStates;
 
 #[test]
 fn jump() {
@@ -708,3 +713,90 @@ fn return_from_interrupt_handler() {
         },
     );
 }
+
+// Request the given interrupts and run the interrupt service routine from the given SP and PC.
+// Returns the resulting CPU registers, the resulting IE and IF values, and the number of cycles that
+// the routine took.
+fn run_interrupt_service_routine(
+    sp: u16,
+    pc: u16,
+    ie_value: u8,
+    if_value: u8,
+    halted: bool,
+) -> (CpuRegisters, u8, u8, u32) {
+    let mut address_space = AddressSpace::new(
+        Cartridge::new(vec![0x00; 0x8000], None, ControllerStates::default())
+            .expect("synthesized test ROM should be valid"),
+        ExecutionMode::GameBoy,
+    );
+    let ppu_state = PpuState::new(ExecutionMode::GameBoy);
+
+    address_space.write_address_u8(0xFFFF, ie_value, &ppu_state);
+    address_space.get_io_registers_mut().write_register(IoRegister::IF, if_value);
+
+    let mut cpu_registers = CpuRegisters::new(ExecutionMode::GameBoy);
+    cpu_registers.sp = sp;
+    cpu_registers.pc = pc;
+    cpu_registers.ime = true;
+    cpu_registers.halted = halted;
+
+    assert!(cpu::interrupt_triggered(&cpu_registers, &address_space));
+    let cycles = cpu::execute_interrupt_service_routine(
+        &mut cpu_registers,
+        &mut ImmediateBus::new(&mut address_space, &ppu_state),
+    );
+
+    let ie_value = address_space.get_ie_register();
+    let if_value = address_space.get_io_registers().read_register(IoRegister::IF) & 0x1F;
+    (cpu_registers, ie_value, if_value, cycles)
+}
+
+#[test]
+fn interrupt_dispatch() {
+    // Highest priority interrupt should be serviced first
+    let (cpu_registers, ie_value, if_value, cycles) =
+        run_interrupt_service_routine(0xD000, 0x1234, 0x1F, 0x14, false);
+    assert_eq!(0x0050, cpu_registers.pc);
+    assert_eq!(0xCFFE, cpu_registers.sp);
+    assert!(!cpu_registers.ime);
+    assert_eq!(0x1F, ie_value);
+    assert_eq!(0x10, if_value);
+    assert_eq!(20, cycles);
+
+    // Exiting HALT takes an extra M-cycle
+    let (cpu_registers, _, if_value, cycles) =
+        run_interrupt_service_routine(0xD000, 0x1234, 0x01, 0x01, true);
+    assert_eq!(0x0040, cpu_registers.pc);
+    assert!(!cpu_registers.halted);
+    assert_eq!(0x00, if_value);
+    assert_eq!(24, cycles);
+}
+
+#[test]
+fn interrupt_dispatch_ie_push() {
+    // With SP=0x0000, pushing the high byte of PC overwrites IE. If that disables the requested
+    // interrupt then the dispatch is cancelled and the CPU jumps to 0x0000 without clearing IF
+    let (cpu_registers, ie_value, if_value, _) =
+        run_interrupt_service_routine(0x0000, 0x0200, 0x01, 0x01, false);
+    assert_eq!(0x0000, cpu_registers.pc);
+    assert_eq!(0xFFFE, cpu_registers.sp);
+    assert!(!cpu_registers.ime);
+    assert_eq!(0x02, ie_value);
+    assert_eq!(0x01, if_value);
+
+    // If the new IE value enables a different requested interrupt then the CPU jumps to that
+    // interrupt's handler instead
+    let (cpu_registers, ie_value, if_value, _) =
+        run_interrupt_service_routine(0x0000, 0x0400, 0x01, 0x05, false);
+    assert_eq!(0x0050, cpu_registers.pc);
+    assert_eq!(0x04, ie_value);
+    assert_eq!(0x01, if_value);
+
+    // The handler address has already been chosen by the time the low byte is pushed, so
+    // overwriting IE with the low byte does not affect the dispatch
+    let (cpu_registers, ie_value, if_value, _) =
+        run_interrupt_service_routine(0x0001, 0x0200, 0x01, 0x01, false);
+    assert_eq!(0x0040, cpu_registers.pc);
+    assert_eq!(0x00, ie_value);
+    assert_eq!(0x00, if_value);
+}
@@ -0,0 +1,181 @@
+use crate::cpu::instructions::{self, Instruction};
+use crate::cpu::registers::CpuRegisterPair;
+use crate::cpu::{self, CpuBus, CpuRegisters, ExecutionMode, ImmediateBus};
+use crate::memory::ioregisters::IoRegister;
+use crate::memory::{AddressSpace, Cartridge};
+use crate::ppu::{OamBugAccess, PpuState};
+use crate::startup::ControllerStates;
+
+const PROGRAM_START: u16 = 0xC000;
+const STACK_START: u16 = 0xD000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum BusCycle {
+    Read(u16),
+    Write(u16, u8),
+    Idle,
+}
+
+// Records every M-cycle that the CPU spends so that tests can verify access timing
+struct RecordingBus<'a> {
+    inner: ImmediateBus<'a>,
+    cycles: Vec<BusCycle>,
+}
+
+impl CpuBus for RecordingBus<'_> {
+    fn read(&mut self, address: u16) -> u8 {
+        self.cycles.push(BusCycle::Read(address));
+        self.inner.read(address)
+    }
+
+    fn write(&mut self, address: u16, value: u8) {
+        self.cycles.push(BusCycle::Write(address, value));
+        self.inner.write(address, value);
+    }
+
+    fn idle(&mut self) {
+        self.cycles.push(BusCycle::Idle);
+    }
+
+    fn trigger_oam_bug(&mut self, address: u16, access: OamBugAccess) {
+        self.inner.trigger_oam_bug(address, access);
+    }
+
+    fn address_space(&mut self) -> &mut AddressSpace {
+        self.inner.address_space()
+    }
+
+    fn ppu_state(&self) -> &PpuState {
+        self.inner.ppu_state()
+    }
+}
+
+fn new_test_state(program: &[u8], flags: u8) -> (AddressSpace, CpuRegisters, PpuState) {
+    let cartridge = Cartridge::new(vec![0x00; 0x8000], None, ControllerStates::default())
+        .expect("synthesized test ROM should be valid");
+    let mut address_space = AddressSpace::new(cartridge, ExecutionMode::GameBoy);
+    let ppu_state = PpuState::new(ExecutionMode::GameBoy);
+
+    for (address, &byte) in (PROGRAM_START..).zip(program) {
+        address_space.write_address_u8(address, byte, &ppu_state);
+    }
+
+    // Point every register pair at working RAM so that memory accesses are never blocked
+    let mut cpu_registers = CpuRegisters::new(ExecutionMode::GameBoy);
+    cpu_registers.flags = flags;
+    cpu_registers.pc = PROGRAM_START;
+    cpu_registers.sp = STACK_START;
+    cpu_registers.set_register_pair(CpuRegisterPair::BC, 0xC900);
+    cpu_registers.set_register_pair(CpuRegisterPair::DE, 0xCA00);
+    cpu_registers.set_hl(0xC800);
+
+    (address_space, cpu_registers, ppu_state)
+}
+
+// Parse and execute a single instruction, returning the instruction, the number of clock cycles
+// that the instruction-based mode thinks it takes, and the M-cycles that were actually recorded
+fn record_instruction(program: &[u8], flags: u8) -> (Instruction, u32, Vec<BusCycle>) {
+    let (mut address_space, mut cpu_registers, ppu_state) = new_test_state(program, flags);
+    let mut bus =
+        RecordingBus { inner: ImmediateBus::new(&mut address_space, &ppu_state), cycles: vec![] };
+
+    let (instruction, pc) =
+        instructions::parse_next_instruction(&mut bus, cpu_registers.pc, false).unwrap();
+    cpu_registers.pc = pc;
+
+    let cycles_required = instruction.cycles_required(&cpu_registers);
+    instruction.execute(&mut bus, &mut cpu_registers);
+
+    (instruction, cycles_required, bus.cycles)
+}
+
+#[test]
+fn m_cycles_match_cycles_required() {
+    const INVALID_OPCODES: [u8; 11] =
+        [0xD3, 0xDB, 0xDD, 0xE3, 0xE4, 0xEB, 0xEC, 0xED, 0xF4, 0xFC, 0xFD];
+
+    for opcode in 0x00..=0xFF {
+        // STOP is not implemented outside of CGB speed switches
+        if INVALID_OPCODES.contains(&opcode) || opcode == 0x10 {
+            continue;
+        }
+
+        // Check both outcomes of conditional instructions
+        for flags in [0x00, 0xF0] {
+            let (instruction, cycles_required, cycles) =
+                record_instruction(&[opcode, 0x00, 0xC8], flags);
+            assert_eq!(
+                cycles_required,
+                4 * cycles.len() as u32,
+                "{instruction:?} with flags {flags:02X}: {cycles:?}"
+            );
+        }
+    }
+
+    for opcode in 0x00..=0xFF {
+        let (instruction, cycles_required, cycles) = record_instruction(&[0xCB, opcode], 0x00);
+        assert_eq!(cycles_required, 4 * cycles.len() as u32, "{instruction:?}: {cycles:?}");
+    }
+}
+
+#[test]
+fn m_cycle_access_order() {
+    use BusCycle::{Idle, Read, Write};
+
+    // LD (HL), 0x5A
+    let (_, _, cycles) = record_instruction(&[0x36, 0x5A], 0x00);
+    assert_eq!(vec![Read(0xC000), Read(0xC001), Write(0xC800, 0x5A)], cycles);
+
+    // PUSH BC: high byte is pushed first
+    let (_, _, cycles) = record_instruction(&[0xC5], 0x00);
+    assert_eq!(vec![Read(0xC000), Idle, Write(0xCFFF, 0xC9), Write(0xCFFE, 0x00)], cycles);
+
+    // CALL 0xC800
+    let (_, _, cycles) = record_instruction(&[0xCD, 0x00, 0xC8], 0x00);
+    assert_eq!(
+        vec![
+            Read(0xC000),
+            Read(0xC001),
+            Read(0xC002),
+            Idle,
+            Write(0xCFFF, 0xC0),
+            Write(0xCFFE, 0x03)
+        ],
+        cycles
+    );
+
+    // RET NZ: condition check takes a cycle before popping
+    let (_, _, cycles) = record_instruction(&[0xC0], 0x00);
+    assert_eq!(vec![Read(0xC000), Idle, Read(0xD000), Read(0xD001), Idle], cycles);
+
+    // INC (HL): read then write
+    let (_, _, cycles) = record_instruction(&[0x34], 0x00);
+    assert_eq!(vec![Read(0xC000), Read(0xC800), Write(0xC800, 0x01)], cycles);
+}
+
+#[test]
+fn interrupt_service_routine_m_cycles() {
+    let (mut address_space, mut cpu_registers, ppu_state) = new_test_state(&[], 0x00);
+    address_space.write_address_u8(0xFFFF, 0x04, &ppu_state);
+    address_space.get_io_registers_mut().write_register(IoRegister::IF, 0x04);
+    cpu_registers.ime = true;
+    cpu_registers.pc = 0x1234;
+
+    let mut bus =
+        RecordingBus { inner: ImmediateBus::new(&mut address_space, &ppu_state), cycles: vec![] };
+    assert!(cpu::interrupt_triggered(&cpu_registers, bus.address_space()));
+    let cycles = cpu::execute_interrupt_service_routine(&mut cpu_registers, &mut bus);
+
+    assert_eq!(
+        vec![
+            BusCycle::Idle,
+            BusCycle::Idle,
+            BusCycle::Write(0xCFFF, 0x12),
+            BusCycle::Write(0xCFFE, 0x34),
+            BusCycle::Idle
+        ],
+        bus.cycles
+    );
+    assert_eq!(cycles, 4 * bus.cycles.len() as u32);
+    assert_eq!(0x0050, cpu_registers.pc);
+}
@@ -0,0 +1,699 @@
+use crate::apu::ApuState;
+use crate::camera::CameraImageSource;
+use crate::config::CpuAccuracy;
+use crate::cpu::instructions::ParseError;
+use crate::cpu::{CgbSpeedMode, CpuBus, CpuRegisters, ExecutionMode, ImmediateBus, instructions};
+use crate::input::JoypadState;
+use crate::memory::AddressSpace;
+use crate::memory::ioregisters::IoRegister;
+use crate::movie::{Movie, MovieError, MovieStart, MovieStatus, UnsavedEmulatorState};
+use crate::ppu::{FrameBuffer, OamBugAccess, PpuMode, PpuState};
+use crate::rewind::RewindBuffer;
+use crate::serial::{Disconnected, Printer, SerialDevice, SerialPort, SocketLinkCable};
+use crate::serialize::SaveStateError;
+#[cfg(feature = "sdl")]
+use crate::startup::ControllerStates;
+use crate::startup::{EmulationState, StartupError};
+use crate::timer::TimerCounter;
+use crate::{RunConfig, apu, cpu, input, ppu, serial, serialize, startup, timer};
+use std::io;
+use std::path::Path;
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum EmulatorError {
+    #[error("error parsing CPU instruction: {source}")]
+    InstructionParse {
+        #[from]
+        source: ParseError,
+    },
+    #[error("error taking rewind snapshot: {source}")]
+    RewindSnapshot {
+        #[from]
+        source: SaveStateError,
+    },
+}
+
+// The number of 4MHz clock cycles in a full PPU frame (154 scanlines * 456 dots). Used as an upper
+// bound for a single call to run_frame so that it returns even if the PPU is disabled.
+const CYCLES_PER_FRAME: u64 = 154 * 456;
+
+// The number of frames after power-on during which a button combination can override the DMG
+// compatibility palettes. The CGB boot ROM checks for these while the logo is displayed.
+const COMPATIBILITY_PALETTE_SELECT_FRAMES: u32 = 60;
+
+/// The output of emulating a single frame.
+#[derive(Debug)]
+pub struct FrameOutput<'a> {
+    /// The current contents of the PPU frame buffer. If `gbc_colors` is false then each value is
+    /// a shade from 0-3, otherwise each value is a 15-bit BGR555 color.
+    pub frame_buffer: &'a FrameBuffer,
+    /// Whether the frame buffer contains GBC colors. This is true in GBC mode and also when
+    /// running in DMG compatibility mode on GBC hardware.
+    pub gbc_colors: bool,
+    /// Whether the PPU completed a frame that should be displayed. This is false if the frame was
+    /// cut short because the PPU was disabled for the entire frame, or if the PPU was just
+    /// powered on (the first frame after power-on is not displayed on real hardware).
+    pub frame_rendered: bool,
+    /// Interleaved stereo audio samples at [`OUTPUT_FREQUENCY`](crate::OUTPUT_FREQUENCY) that were
+    /// generated during this frame.
+    pub audio_samples: Vec<f32>,
+}
+
+/// A headless emulator instance that owns the complete emulation state. Does not depend on SDL2.
+pub struct Emulator {
+    state: EmulationState,
+    joypad_state: JoypadState,
+    serial_port: SerialPort,
+    serial_device: Box<dyn SerialDevice>,
+    // Track how many 4MHz clock cycles are "left over" when running in double speed mode
+    leftover_cpu_cycles: u32,
+    audio_60hz: bool,
+    cpu_accuracy: CpuAccuracy,
+    compatibility_palette_frames_remaining: u32,
+    rewind_buffer: RewindBuffer,
+    movie: Option<Movie>,
+}
+
+impl Emulator {
+    /// Create a new emulator instance, loading the cartridge specified in the given config.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the cartridge cannot be loaded or if a link cable is
+    /// configured and cannot be connected.
+    pub fn new(run_config: &RunConfig) -> Result<Self, StartupError> {
+        let emulation_state = startup::init_emulation_state(run_config)?;
+
+        let serial_device: Box<dyn SerialDevice> = match &run_config.link_cable {
+            Some(link_cable_config) => Box::new(
+                SocketLinkCable::open(link_cable_config)
+                    .map_err(|err| StartupError::LinkCable { source: err })?,
+            ),
+            None if run_config.printer_enabled => Box::new(Printer::new(&run_config.gb_file_path)),
+            None => Box::new(Disconnected),
+        };
+
+        // If startup already put the hardware in DMG compatibility mode then no boot ROM is running,
+        // so the emulator needs to check for palette button combinations itself
+        let compatibility_palette_frames_remaining =
+            if emulation_state.address_space.get_io_registers().dmg_compatibility_mode() {
+                COMPATIBILITY_PALETTE_SELECT_FRAMES
+            } else {
+                0
+            };
+
+        Ok(Self {
+            state: emulation_state,
+            joypad_state: JoypadState::new(),
+            serial_port: SerialPort::new(),
+            serial_device,
+            leftover_cpu_cycles: 0,
+            audio_60hz: run_config.audio_60hz,
+            cpu_accuracy: run_config.cpu_accuracy,
+            compatibility_palette_frames_remaining,
+            rewind_buffer: RewindBuffer::new(run_config.rewind_buffer_secs),
+            movie: None,
+        })
+    }
+
+    #[must_use]
+    pub fn execution_mode(&self) -> ExecutionMode {
+        self.state.execution_mode
+    }
+
+    /// Connect a device to the link port, replacing whatever device was previously connected. No
+    /// device is connected by default.
+    pub fn connect_serial_device(&mut self, device: Box<dyn SerialDevice>) {
+        self.serial_device = device;
+    }
+
+    /// Set the image source used by the Pocket Camera's image sensor, replacing any image source
+    /// from the run config. Has no effect if the cartridge is not a Pocket Camera.
+    pub fn set_camera_image_source(&mut self, image_source: Box<dyn CameraImageSource>) {
+        *self.state.controller_states.camera_image_source.borrow_mut() = Some(image_source);
+    }
+
+    #[cfg(feature = "sdl")]
+    pub(crate) fn controller_states(&self) -> &ControllerStates {
+        &self.state.controller_states
+    }
+
+    /// Run the emulator until the PPU finishes rendering the next frame, using the given joypad
+    /// state as input for the entire frame.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the CPU encounters an invalid instruction or if a
+    /// rewind snapshot cannot be serialized.
+    pub fn run_frame(&mut self, input: JoypadState) -> Result<FrameOutput<'_>, EmulatorError> {
+        self.joypad_state = match &mut self.movie {
+            Some(movie) => movie.next_input(input, &self.state),
+            None => input,
+        };
+
+        if self.compatibility_palette_frames_remaining > 0 {
+            self.compatibility_palette_frames_remaining -= 1;
+            if let Some(palettes) = ppu::palettes_for_buttons(&self.joypad_state) {
+                let io_registers = self.state.address_space.get_io_registers_mut();
+                io_registers.load_compatibility_palettes(&palettes);
+                self.compatibility_palette_frames_remaining = 0;
+            }
+        }
+
+        let mut frame_cycles = 0;
+        let frame_rendered = loop {
+            let (cycles, frame_finished) = self.tick()?;
+            frame_cycles += u64::from(cycles);
+
+            if frame_finished {
+                break self.state.ppu_state.should_render_current_frame();
+            }
+
+            if frame_cycles >= CYCLES_PER_FRAME {
+                break false;
+            }
+        };
+
+        if let Some(movie) = &mut self.movie {
+            movie.end_frame(frame_cycles, &mut self.state);
+        }
+
+        self.state.address_space.update_rtc();
+        self.serial_device.end_frame();
+        self.rewind_buffer.end_frame(&self.state)?;
+
+        let mut audio_samples: Vec<_> =
+            self.state.apu_state.get_sample_queue_mut().drain(..).collect();
+        self.state.address_space.mix_cartridge_audio(&mut audio_samples);
+
+        Ok(FrameOutput {
+            frame_buffer: self.state.ppu_state.frame_buffer(),
+            gbc_colors: self.state.gbc_colors(),
+            frame_rendered,
+            audio_samples,
+        })
+    }
+
+    /// Write cartridge RAM and real-time clock state to disk if the cartridge has a battery and
+    /// the state has changed since the last write. Does nothing after a movie has been played
+    /// back, so that the movie's cartridge RAM does not overwrite the sav file.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if unable to write to the sav/rtc files.
+    pub fn persist_cartridge_state(&mut self) -> Result<(), io::Error> {
+        if self.movie.as_ref().is_some_and(Movie::is_playback) {
+            return Ok(());
+        }
+
+        self.state.address_space.persist_cartridge_state()
+    }
+
+    /// Write a save state of the current emulation state to the given path.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if serialization fails or if unable to write the file.
+    pub fn save_state<P>(&self, path: P) -> Result<(), SaveStateError>
+    where
+        P: AsRef<Path>,
+    {
+        serialize::save_state(&self.state, path)
+    }
+
+    /// Load a save state from the given path. The current state is left unchanged if loading
+    /// fails.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if unable to read the file or if deserialization fails.
+    pub fn load_state<P>(&mut self, path: P) -> Result<(), SaveStateError>
+    where
+        P: AsRef<Path>,
+    {
+        serialize::load_state(path, &mut self.state)
+    }
+
+    /// Step backward to the most recent rewind snapshot. Returns false if rewinding is disabled
+    /// or no snapshots have been taken yet.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the snapshot cannot be deserialized.
+    pub fn rewind(&mut self) -> Result<bool, SaveStateError> {
+        self.rewind_buffer.rewind(&mut self.state)
+    }
+
+    /// Start recording input to a movie file at the given path, starting from the current state.
+    /// Any movie that is already being recorded or played back is stopped first. The movie is
+    /// written to disk when recording stops, or when the emulator is dropped if recording was
+    /// never stopped.
+    ///
+    /// While recording, cartridge real-time clocks use an emulated time that advances with
+    /// emulation instead of the system time so that the movie plays back deterministically.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the current movie cannot be stopped or if the
+    /// current state cannot be serialized.
+    pub fn start_movie_recording<P>(&mut self, path: P, start: MovieStart) -> Result<(), MovieError>
+    where
+        P: AsRef<Path>,
+    {
+        self.stop_movie()?;
+
+        let unsaved_state = self.unsaved_state();
+        self.movie =
+            Some(Movie::start_recording(path.as_ref(), start, &mut self.state, unsaved_state)?);
+
+        Ok(())
+    }
+
+    /// Play back the movie at the given path, replacing the current state with the movie's
+    /// starting state. Any movie that is already being recorded or played back is stopped first.
+    ///
+    /// BizHawk (`.bk2`) and VisualBoyAdvance (`.vbm`) movies are also supported, detected by file
+    /// extension. These do not contain a starting state, so their input is replayed from the
+    /// current state, which should be immediately after power-on. Cartridge RAM and real-time
+    /// clocks are reset as if there were no save file, without modifying the save file. Frames
+    /// where playback may have desynced are reported through
+    /// [`movie_desync_frames`](Self::movie_desync_frames).
+    ///
+    /// During playback the input passed to [`run_frame`](Self::run_frame) is ignored until every
+    /// frame of the movie has been played. Native movies also switch the CPU accuracy to the
+    /// setting that the movie was recorded with.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the current movie cannot be stopped, if unable to
+    /// read the movie file, if a native movie was recorded with a different ROM, or if the movie
+    /// uses features that are not supported. Imported movies are not rejected for a different ROM;
+    /// a VBM movie whose ROM title or checksum does not match only logs a warning, and BK2 movies
+    /// are not checked.
+    pub fn start_movie_playback<P>(&mut self, path: P) -> Result<(), MovieError>
+    where
+        P: AsRef<Path>,
+    {
+        self.stop_movie()?;
+
+        let unsaved_state = self.unsaved_state();
+        let (movie, unsaved_state) =
+            Movie::start_playback(path.as_ref(), &mut self.state, unsaved_state)?;
+        self.leftover_cpu_cycles = unsaved_state.leftover_cpu_cycles;
+        self.compatibility_palette_frames_remaining =
+            unsaved_state.compatibility_palette_frames_remaining;
+        if unsaved_state.cpu_accuracy != self.cpu_accuracy {
+            log::warn!(
+                "Movie was recorded with CPU accuracy {}, using it for playback instead of {}",
+                unsaved_state.cpu_accuracy,
+                self.cpu_accuracy
+            );
+            self.cpu_accuracy = unsaved_state.cpu_accuracy;
+        }
+        self.movie = Some(movie);
+
+        Ok(())
+    }
+
+    /// Stop recording or playing back the current movie, if any. A movie that was being recorded
+    /// is written to disk.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if unable to write the recorded movie.
+    pub fn stop_movie(&mut self) -> Result<(), MovieError> {
+        match self.movie.take() {
+            Some(movie) => movie.stop(&self.state),
+            None => Ok(()),
+        }
+    }
+
+    #[must_use]
+    pub fn movie_status(&self) -> MovieStatus {
+        self.movie.as_ref().map_or(MovieStatus::None, Movie::status)
+    }
+
+    /// The frames of the current movie where playback may have desynced so far, in ascending
+    /// order. See [`start_movie_playback`](Self::start_movie_playback).
+    #[must_use]
+    pub fn movie_desync_frames(&self) -> &[usize] {
+        self.movie.as_ref().map_or(&[], Movie::desync_frames)
+    }
+
+    fn unsaved_state(&self) -> UnsavedEmulatorState {
+        UnsavedEmulatorState {
+            leftover_cpu_cycles: self.leftover_cpu_cycles,
+            compatibility_palette_frames_remaining: self.compatibility_palette_frames_remaining,
+            cpu_accuracy: self.cpu_accuracy,
+        }
+    }
+
+    // Run the CPU for at least 1 M-cycle and then advance the other processors to match. Returns
+    // the number of 4MHz clock cycles that elapsed and whether the PPU just finished a frame.
+    fn tick(&mut self) -> Result<(u32, bool), EmulatorError> {
+        input::update_joyp_register(
+            &self.joypad_state,
+            self.state.address_space.get_io_registers_mut(),
+        );
+
+        match self.cpu_accuracy {
+            CpuAccuracy::Instruction => self.tick_instruction(),
+            CpuAccuracy::Cycle => self.tick_m_cycle(),
+        }
+    }
+
+    // Execute whole CPU instructions and then advance the other processors by the total number of
+    // cycles that the instructions took
+    fn tick_instruction(&mut self) -> Result<(u32, bool), EmulatorError> {
+        let EmulationState {
+            execution_mode,
+            address_space,
+            cpu_registers,
+            ppu_state,
+            apu_state,
+            timer_counter,
+            ..
+        } = &mut self.state;
+
+        // The number of 4MHz clock cycles
+        // (CPU M-cycles * 4 in normal speed, CPU M-cycles * 2 in double speed)
+        let mut cycles_required = self.leftover_cpu_cycles;
+        while cycles_required < 4 {
+            let tick_cycles =
+                tick_cpu(&mut ImmediateBus::new(address_space, ppu_state), cpu_registers)?;
+
+            if matches!(cpu_registers.cgb_speed_mode, CgbSpeedMode::Double) {
+                cycles_required += tick_cycles / 2;
+            } else {
+                cycles_required += tick_cycles;
+            }
+        }
+        self.leftover_cpu_cycles = cycles_required & 0x00000003;
+        cycles_required &= 0xFFFFFFFC;
+
+        sync_execution_mode(execution_mode, address_space, cpu_registers, ppu_state);
+
+        let mut peripherals = Peripherals {
+            address_space,
+            ppu_state,
+            apu_state,
+            timer_counter,
+            serial_port: &mut self.serial_port,
+            serial_device: self.serial_device.as_mut(),
+            execution_mode: *execution_mode,
+            cgb_speed_mode: cpu_registers.cgb_speed_mode,
+            audio_60hz: self.audio_60hz,
+        };
+        let frame_finished = peripherals.advance(cycles_required);
+
+        Ok((cycles_required, frame_finished))
+    }
+
+    // Execute CPU instructions while advancing the other processors by 1 M-cycle after every
+    // memory access
+    fn tick_m_cycle(&mut self) -> Result<(u32, bool), EmulatorError> {
+        let EmulationState {
+            execution_mode,
+            address_space,
+            cpu_registers,
+            ppu_state,
+            apu_state,
+            timer_counter,
+            ..
+        } = &mut self.state;
+
+        let mut bus = SteppedBus {
+            peripherals: Peripherals {
+                address_space,
+                ppu_state,
+                apu_state,
+                timer_counter,
+                serial_port: &mut self.serial_port,
+                serial_device: self.serial_device.as_mut(),
+                execution_mode: *execution_mode,
+                cgb_speed_mode: cpu_registers.cgb_speed_mode,
+                audio_60hz: self.audio_60hz,
+            },
+            pending_cycles: self.leftover_cpu_cycles,
+            elapsed_cycles: 0,
+            frame_finished: false,
+        };
+        while bus.elapsed_cycles < 4 {
+            tick_cpu(&mut bus, cpu_registers)?;
+
+            // STOP can switch speed modes
+            bus.peripherals.cgb_speed_mode = cpu_registers.cgb_speed_mode;
+        }
+        self.leftover_cpu_cycles = bus.pending_cycles;
+        let SteppedBus { elapsed_cycles, frame_finished, .. } = bus;
+
+        sync_execution_mode(execution_mode, address_space, cpu_registers, ppu_state);
+
+        Ok((elapsed_cycles, frame_finished))
+    }
+}
+
+// The CGB boot ROM can switch the hardware to DMG compatibility mode when it unmaps itself
+fn sync_execution_mode(
+    execution_mode: &mut ExecutionMode,
+    address_space: &AddressSpace,
+    cpu_registers: &mut CpuRegisters,
+    ppu_state: &mut PpuState,
+) {
+    if address_space.execution_mode() != *execution_mode {
+        *execution_mode = address_space.execution_mode();
+        cpu_registers.execution_mode = *execution_mode;
+        ppu_state.set_execution_mode(*execution_mode);
+    }
+}
+
+// Everything that runs alongside the CPU, borrowed from the emulator for a single tick
+struct Peripherals<'a> {
+    address_space: &'a mut AddressSpace,
+    ppu_state: &'a mut PpuState,
+    apu_state: &'a mut ApuState,
+    timer_counter: &'a mut TimerCounter,
+    serial_port: &'a mut SerialPort,
+    serial_device: &'a mut dyn SerialDevice,
+    execution_mode: ExecutionMode,
+    cgb_speed_mode: CgbSpeedMode,
+    audio_60hz: bool,
+}
+
+impl Peripherals<'_> {
+    // Advance the timer, serial port, PPU, DMA transfers, and APU by the given number of 4MHz clock
+    // cycles, which must be a multiple of 4. Returns whether the PPU just finished a frame.
+    fn advance(&mut self, cycles: u32) -> bool {
+        let Self {
+            address_space,
+            ppu_state,
+            apu_state,
+            timer_counter,
+            serial_port,
+            serial_device,
+            execution_mode,
+            cgb_speed_mode,
+            audio_60hz,
+        } = self;
+
+        let double_speed = matches!(cgb_speed_mode, CgbSpeedMode::Double);
+
+        // Timer updates pause while a VRAM DMA transfer is in progress
+        if !ppu_state.is_vram_dma_in_progress() {
+            let timer_cycles = if double_speed {
+                // Timer and divider registers update twice as fast in double speed mode
+                2 * u64::from(cycles)
+            } else {
+                cycles.into()
+            };
+            timer::update_timer_registers(
+                address_space.get_io_registers_mut(),
+                timer_counter,
+                timer_cycles,
+            );
+        }
+
+        // The serial clock also runs twice as fast in double speed mode
+        let serial_cycles = if double_speed { 2 * u64::from(cycles) } else { cycles.into() };
+        serial::tick(
+            serial_port,
+            address_space.get_io_registers_mut(),
+            *serial_device,
+            *execution_mode,
+            serial_cycles,
+        );
+
+        let prev_mode = ppu_state.mode();
+        let prev_enabled = ppu_state.enabled();
+        for _ in (0..cycles).step_by(4) {
+            ppu::progress_oam_dma_transfer(ppu_state, address_space);
+            if double_speed {
+                // OAM DMA transfers progress at double speed in double speed mode so call twice
+                ppu::progress_oam_dma_transfer(ppu_state, address_space);
+            }
+
+            // Shadow prev_mode so that it correctly updates when doing VRAM DMA transfers in double
+            // speed mode
+            let prev_mode = ppu_state.mode();
+            ppu::tick_m_cycle(ppu_state, address_space);
+
+            // Progress VRAM DMA transfer by 2 bytes per PPU M-cycle
+            let current_mode = ppu_state.mode();
+            ppu::progress_vram_dma_transfer(ppu_state, address_space, prev_mode);
+            ppu::progress_vram_dma_transfer(ppu_state, address_space, current_mode);
+
+            apu::tick_m_cycle(
+                apu_state,
+                address_space.get_io_registers_mut(),
+                *cgb_speed_mode,
+                *audio_60hz,
+            );
+        }
+
+        // The PPU finishes a frame when it enters VBlank mode. Also treat the PPU being disabled
+        // as the end of a (blank) frame.
+        (prev_mode != PpuMode::VBlank && ppu_state.mode() == PpuMode::VBlank)
+            || (prev_enabled && !ppu_state.enabled())
+    }
+}
+
+// A CPU bus that advances the other processors by 1 M-cycle after every memory access and every
+// internal CPU cycle
+struct SteppedBus<'a> {
+    peripherals: Peripherals<'a>,
+    // 4MHz clock cycles that have not been applied to the other processors yet. This can be 2 in
+    // double speed mode, where CPU M-cycles are only 2 clock cycles long.
+    pending_cycles: u32,
+    elapsed_cycles: u32,
+    frame_finished: bool,
+}
+
+impl SteppedBus<'_> {
+    fn end_m_cycle(&mut self) {
+        self.pending_cycles += match self.peripherals.cgb_speed_mode {
+            CgbSpeedMode::Normal => 4,
+            CgbSpeedMode::Double => 2,
+        };
+
+        if self.pending_cycles >= 4 {
+            self.pending_cycles -= 4;
+            self.elapsed_cycles += 4;
+
+            self.frame_finished |= self.peripherals.advance(4);
+        }
+    }
+}
+
+impl CpuBus for SteppedBus<'_> {
+    fn read(&mut self, address: u16) -> u8 {
+        let value =
+            self.peripherals.address_space.read_address_u8(address, self.peripherals.ppu_state);
+        self.end_m_cycle();
+        value
+    }
+
+    fn write(&mut self, address: u16, value: u8) {
+        self.peripherals.address_space.write_address_u8(address, value, self.peripherals.ppu_state);
+        self.end_m_cycle();
+    }
+
+    fn idle(&mut self) {
+        self.end_m_cycle();
+    }
+
+    fn trigger_oam_bug(&mut self, address: u16, access: OamBugAccess) {
+        ppu::trigger_oam_bug(
+            self.peripherals.ppu_state,
+            self.peripherals.address_space,
+            address,
+            access,
+        );
+    }
+
+    fn address_space(&mut self) -> &mut AddressSpace {
+        self.peripherals.address_space
+    }
+
+    fn ppu_state(&self) -> &PpuState {
+        self.peripherals.ppu_state
+    }
+}
+
+// Run the CPU for a single instruction, a single interrupt service routine, or a single M-cycle of
+// waiting. Returns the number of clock cycles that this took, which is what the instruction-based
+// CPU mode uses to advance the other processors.
+fn tick_cpu<B: CpuBus>(
+    bus: &mut B,
+    cpu_registers: &mut CpuRegisters,
+) -> Result<u32, EmulatorError> {
+    if bus.ppu_state().is_vram_dma_in_progress() {
+        // CPU is halted while a VRAM DMA transfer is actively copying bytes
+        bus.idle();
+        return Ok(4);
+    }
+
+    let result = if let Some(wait_cycles_remaining) =
+        cpu_registers.speed_switch_wait_cycles_remaining
+    {
+        if wait_cycles_remaining == 1 {
+            cpu_registers.speed_switch_wait_cycles_remaining = None;
+        } else {
+            cpu_registers.speed_switch_wait_cycles_remaining = Some(wait_cycles_remaining - 1);
+        }
+        bus.idle();
+
+        4
+    } else if cpu::interrupt_triggered(cpu_registers, bus.address_space()) {
+        cpu::execute_interrupt_service_routine(cpu_registers, bus)
+    } else if !cpu_registers.halted || cpu::interrupt_triggered_no_ime_check(bus.address_space()) {
+        cpu_registers.halted = false;
+
+        let (instruction, pc) = instructions::parse_next_instruction(
+            bus,
+            cpu_registers.pc,
+            cpu_registers.halt_bug_triggered,
+        )?;
+
+        cpu_registers.halt_bug_triggered = false;
+
+        log::trace!("Updating PC from 0x{:04X} to {:04X}", cpu_registers.pc, pc);
+        cpu_registers.pc = pc;
+
+        let cycles_required = instruction.cycles_required(cpu_registers);
+        let address_space = bus.address_space();
+
+        log::trace!("Executing instruction {instruction:04X?}, will take {cycles_required} cycles");
+        log::trace!("CPU registers before instruction execution: {cpu_registers:04X?}");
+        log::trace!(
+            "Other registers before execution: IE={:02X}, IF={:02X}, LCDC={:02X}, LY={:02X}, LYC={:02X}, STAT={:02X}, SCX={:02X}, SCY={:02X}, WX={:02X}, WY={:02X}",
+            address_space.get_ie_register(),
+            address_space.get_io_registers().read_register(IoRegister::IF),
+            address_space.get_io_registers().read_register(IoRegister::LCDC),
+            address_space.get_io_registers().read_register(IoRegister::LY),
+            address_space.get_io_registers().read_register(IoRegister::LYC),
+            address_space.get_io_registers().read_register(IoRegister::STAT),
+            address_space.get_io_registers().read_register(IoRegister::SCX),
+            address_space.get_io_registers().read_register(IoRegister::SCY),
+            address_space.get_io_registers().read_register(IoRegister::WX),
+            address_space.get_io_registers().read_register(IoRegister::WY)
+        );
+        log::trace!(
+            "IE register before instruction execution: {:02X}",
+            address_space.get_ie_register()
+        );
+        log::trace!(
+            "IF register before instruction execution: {:02X}",
+            address_space.get_io_registers().read_register(IoRegister::IF)
+        );
+        instruction.execute(bus, cpu_registers);
+
+        cycles_required
+    } else {
+        // Do nothing, let other processors execute for 1 M-cycle
+        bus.idle();
+        4
+    };
+
+    Ok(result)
+}
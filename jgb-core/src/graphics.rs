@@ -1,6 +1,5 @@
 use crate::config::GbColorScheme;
-use crate::cpu::ExecutionMode;
-use crate::ppu::{FrameBuffer, PpuState};
+use crate::ppu::FrameBuffer;
 use crate::{GbcColorCorrection, HardwareMode, RunConfig, ppu};
 use sdl2::IntegerOrSdlError;
 use sdl2::pixels::{Color, PixelFormatEnum};
@@ -206,6 +205,60 @@ fn gbc_texture_updater_corrected_colors(
     }
 }
 
+/// Convert a frame buffer to packed 24-bit RGB pixels, using the same colors that are used to
+/// render frames to the emulator window.
+pub fn frame_buffer_to_rgb(
+    gbc_colors: bool,
+    frame_buffer: &FrameBuffer,
+    color_scheme: GbColorScheme,
+    gbc_color_correction: GbcColorCorrection,
+) -> Vec<u8> {
+    let pitch = 3 * usize::from(ppu::SCREEN_WIDTH);
+    let mut pixels = vec![0; pitch * usize::from(ppu::SCREEN_HEIGHT)];
+    write_frame_pixels(
+        gbc_colors,
+        frame_buffer,
+        color_scheme,
+        gbc_color_correction,
+        &mut pixels,
+        pitch,
+    );
+    pixels
+}
+
+fn write_frame_pixels(
+    gbc_colors: bool,
+    frame_buffer: &FrameBuffer,
+    color_scheme: GbColorScheme,
+    gbc_color_correction: GbcColorCorrection,
+    pixels: &mut [u8],
+    pitch: usize,
+) {
+    // Cludge to avoid a pointless heap allocation via Box. Trying to use `&dyn` without doing this
+    // will result in "does not live long enough" errors
+    let gb_updater;
+    let gbc_raw_updater;
+    let gbc_corrected_updater;
+
+    let texture_updater: &dyn Fn(&mut [u8], usize) = if !gbc_colors {
+        gb_updater = gb_texture_updater(frame_buffer, palette_for(color_scheme));
+        &gb_updater
+    } else {
+        match gbc_color_correction {
+            GbcColorCorrection::None => {
+                gbc_raw_updater = gbc_texture_updater_raw_colors(frame_buffer);
+                &gbc_raw_updater
+            }
+            GbcColorCorrection::GbcLcd => {
+                gbc_corrected_updater = gbc_texture_updater_corrected_colors(frame_buffer);
+                &gbc_corrected_updater
+            }
+        }
+    };
+
+    texture_updater(pixels, pitch);
+}
+
 pub const FONT_SIZE: u16 = 16;
 
 #[derive(Debug, Clone)]
@@ -229,8 +282,8 @@ impl Modal {
 /// With VSync enabled this function will block until the next screen refresh.
 #[allow(clippy::too_many_arguments)]
 pub fn render_frame<T>(
-    execution_mode: ExecutionMode,
-    ppu_state: &PpuState,
+    gbc_colors: bool,
+    frame_buffer: &FrameBuffer,
     canvas: &mut WindowCanvas,
     texture_creator: &TextureCreator<T>,
     texture: &mut GbFrameTexture<'_>,
@@ -238,32 +291,19 @@ pub fn render_frame<T>(
     modals: &[Modal],
     run_config: &RunConfig,
 ) -> Result<(), GraphicsError> {
-    let frame_buffer = ppu_state.frame_buffer();
-
-    // Cludge to avoid a pointless heap allocation via Box. Trying to use `&dyn` without doing this
-    // will result in "does not live long enough" errors
-    let gb_updater;
-    let gbc_raw_updater;
-    let gbc_corrected_updater;
-
-    let texture_updater: &dyn Fn(&mut [u8], usize) = match execution_mode {
-        ExecutionMode::GameBoy => {
-            gb_updater = gb_texture_updater(frame_buffer, palette_for(run_config.color_scheme));
-            &gb_updater
-        }
-        ExecutionMode::GameBoyColor => match run_config.gbc_color_correction {
-            GbcColorCorrection::None => {
-                gbc_raw_updater = gbc_texture_updater_raw_colors(frame_buffer);
-                &gbc_raw_updater
-            }
-            GbcColorCorrection::GbcLcd => {
-                gbc_corrected_updater = gbc_texture_updater_corrected_colors(frame_buffer);
-                &gbc_corrected_updater
-            }
-        },
-    };
-
-    texture.0.with_lock(None, texture_updater).map_err(|msg| GraphicsError::Texture { msg })?;
+    texture
+        .0
+        .with_lock(None, |pixels, pitch| {
+            write_frame_pixels(
+                gbc_colors,
+                frame_buffer,
+                run_config.color_scheme,
+                run_config.gbc_color_correction,
+                pixels,
+                pitch,
+            );
+        })
+        .map_err(|msg| GraphicsError::Texture { msg })?;
 
     let dst_rect = if run_config.force_integer_scaling {
         let (w, h) = canvas.window().size();
@@ -1,30 +1,57 @@
 #![forbid(unsafe_code)]
 
 mod apu;
+#[cfg(feature = "sdl")]
 mod audio;
+mod camera;
 mod config;
 mod cpu;
 mod debug;
+mod emulator;
+#[cfg(feature = "sdl")]
 mod eventloop;
+#[cfg(feature = "sdl")]
 pub mod font;
+#[cfg(feature = "sdl")]
 mod graphics;
 mod input;
 mod memory;
+mod movie;
 mod ppu;
+mod rewind;
+mod serial;
 mod serialize;
 mod startup;
 mod timer;
 
+#[cfg(feature = "sdl")]
 use std::sync::Arc;
+#[cfg(feature = "sdl")]
 use std::sync::atomic::AtomicBool;
 use thiserror::Error;
 
+#[cfg(feature = "sdl")]
 use crate::eventloop::RunError;
-use crate::startup::StartupError;
+pub use apu::OUTPUT_FREQUENCY;
+pub use camera::{
+    BlankImageSource, CAMERA_HEIGHT, CAMERA_WIDTH, CameraImageError, CameraImageSource,
+    FrameSequenceSource, StillImageSource, image_source_from_path,
+};
 pub use config::{
-    ControllerConfig, ControllerInput, GbColorScheme, GbcColorCorrection, HardwareMode,
-    HatDirection, HotkeyConfig, InputConfig, RunConfig,
+    ControllerConfig, ControllerInput, CpuAccuracy, GbColorScheme, GbcColorCorrection,
+    HardwareMode, HatDirection, HotkeyConfig, InputConfig, LinkCableConfig, RunConfig,
+};
+pub use cpu::ExecutionMode;
+pub use emulator::{Emulator, EmulatorError, FrameOutput};
+pub use input::{Button, JoypadState};
+pub use movie::{MovieError, MovieStart, MovieStatus};
+pub use ppu::{FrameBuffer, SCREEN_HEIGHT, SCREEN_WIDTH};
+pub use serial::{Disconnected, LinkCable, Loopback, Printer, SerialDevice, SocketLinkCable};
+pub use serialize::{
+    SAVE_STATE_SLOTS, SaveStateError, SaveStateHeader, determine_save_state_path,
+    read_save_state_header,
 };
+pub use startup::StartupError;
 
 #[derive(Error, Debug)]
 pub enum EmulationError {
@@ -33,6 +60,7 @@ pub enum EmulationError {
         #[from]
         source: StartupError,
     },
+    #[cfg(feature = "sdl")]
     #[error("runtime error: {source}")]
     Runtime {
         #[from]
@@ -46,12 +74,13 @@ pub enum EmulationError {
 /// # Errors
 ///
 /// This function will return an error if emulation terminates unexpectedly.
+#[cfg(feature = "sdl")]
 pub fn run(run_config: &RunConfig, quit_signal: Arc<AtomicBool>) -> Result<(), EmulationError> {
-    let emulation_state = startup::init_emulation_state(run_config)?;
+    let emulator = Emulator::new(run_config)?;
 
     let sdl_state = startup::init_sdl_state(run_config)?;
 
-    eventloop::run(emulation_state, sdl_state, run_config, quit_signal)?;
+    eventloop::run(emulator, sdl_state, run_config, quit_signal)?;
 
     Ok(())
 }
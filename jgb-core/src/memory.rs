@@ -3,11 +3,12 @@ pub mod ioregisters;
 mod mapper;
 
 use crate::cpu::ExecutionMode;
-use crate::memory::ioregisters::IoRegisters;
-use crate::memory::mapper::{Mapper, MapperType, RamMapResult, RealTimeClock};
+use crate::memory::ioregisters::{IoRegister, IoRegisters};
+use crate::memory::mapper::{Mapper, MapperType, RamMapResult};
 use crate::ppu::{PpuMode, PpuState};
 use crate::startup::ControllerStates;
 use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
 use std::path::{Path, PathBuf};
 use std::{fs, io};
 use thiserror::Error;
@@ -41,7 +42,7 @@ impl FsRamBattery {
         self.dirty = true;
     }
 
-    fn persist_state(&mut self, ram: &[u8], rtc: Option<&RealTimeClock>) -> Result<(), io::Error> {
+    fn persist_state(&mut self, ram: &[u8], rtc_bytes: Option<Vec<u8>>) -> Result<(), io::Error> {
         if !self.dirty {
             return Ok(());
         }
@@ -50,11 +51,8 @@ impl FsRamBattery {
         fs::write(&tmp_file, ram)?;
         fs::rename(&tmp_file, &self.sav_path)?;
 
-        if let Some(rtc) = rtc {
+        if let Some(rtc_bytes) = rtc_bytes {
             let rtc_file = self.sav_path.with_extension("rtc");
-            let rtc_bytes = bincode::serialize(rtc)
-                .expect("RTC value-to-bytes serialization should never fail");
-
             fs::write(rtc_file, rtc_bytes)?;
         }
 
@@ -90,7 +88,8 @@ where
     Ok(ram)
 }
 
-fn load_rtc<P>(rtc_file: P) -> Result<RealTimeClock, String>
+// The format of the RTC file depends on the mapper, so leave deserialization to the mapper
+fn load_rtc<P>(rtc_file: P) -> Result<Vec<u8>, String>
 where
     P: AsRef<Path>,
 {
@@ -101,19 +100,13 @@ where
         }
     };
 
-    let rtc = match bincode::deserialize(&rtc_bytes) {
-        Ok(rtc) => rtc,
-        Err(err) => {
-            return Err(format!(
-                "error deserializing RTC bytes from {}: {err}",
-                rtc_file.as_ref().display()
-            ));
-        }
-    };
+    log::info!("Loaded real-time clock state from {}", rtc_file.as_ref().display());
 
-    log::info!("Successfully loaded real-time clock state from {}", rtc_file.as_ref().display());
+    Ok(rtc_bytes)
+}
 
-    Ok(rtc)
+fn header_offset(rom: &[u8]) -> usize {
+    mapper::mmm01_header_offset(rom).unwrap_or(0)
 }
 
 #[derive(Serialize, Deserialize)]
@@ -146,10 +139,24 @@ impl Cartridge {
             return Err(CartridgeLoadError::HeaderTooShort { header_len: rom.len() });
         }
 
-        let mapper_byte = rom[address::MAPPER as usize];
-        let Some((mapper_type, mapper_features)) = mapper::parse_byte(mapper_byte) else {
+        let header_offset = header_offset(&rom);
+        let header = &rom[header_offset..];
+
+        let mapper_byte = header[address::MAPPER as usize];
+        // Unlicensed mappers can't be identified by the mapper byte alone. MMM01 multicarts are
+        // excluded because the header at the start of the ROM belongs to the first game
+        let unlicensed_mapper =
+            if header_offset == 0 { mapper::detect_unlicensed_mapper(&rom) } else { None };
+        let Some((mapper_type, mapper_features)) =
+            unlicensed_mapper.or_else(|| mapper::parse_byte(mapper_byte))
+        else {
             return Err(CartridgeLoadError::InvalidMapper { mapper_byte });
         };
+        let mapper_type = if mapper_type == MapperType::MBC1 && mapper::is_mbc1_multicart(&rom) {
+            MapperType::MBC1M
+        } else {
+            mapper_type
+        };
 
         log::info!("Detected mapper type {mapper_type:?} (byte: {mapper_byte:02X})");
         log::info!("Mapper features: {mapper_features}");
@@ -157,11 +164,11 @@ impl Cartridge {
         let loaded_ram =
             if let Some(sav_path) = &sav_path { load_sav_file(sav_path)? } else { None };
 
-        let rtc = match (mapper_features.has_rtc, &sav_path) {
+        let rtc_bytes = match (mapper_features.has_rtc, &sav_path) {
             (true, Some(sav_path)) => {
                 let rtc_path = sav_path.with_extension("rtc");
                 match load_rtc(rtc_path) {
-                    Ok(rtc) => Some(rtc),
+                    Ok(rtc_bytes) => Some(rtc_bytes),
                     Err(err) => {
                         log::warn!("error attempting to load previous RTC, resetting: {err}");
                         None
@@ -174,11 +181,15 @@ impl Cartridge {
         let expected_ram_size = match (mapper_type, mapper_features.has_ram) {
             // MBC2 cartridges always have 512 bytes of RAM (technically 512 4-bit nibbles)
             (MapperType::MBC2, _) => 512,
+            // MBC6 cartridges always have 32KB of RAM
+            (MapperType::MBC6, _) => 32768,
             // MBC7 cartridges always have 256 bytes of RAM
             (MapperType::MBC7, _) => 256,
+            // TAMA5 cartridges always have 32 bytes of RAM
+            (MapperType::TAMA5, _) => 32,
             (_, true) => {
                 // Non-MBC2 cartridges specify RAM size through a header byte
-                let ram_size_code = rom[address::RAM_SIZE as usize];
+                let ram_size_code = header[address::RAM_SIZE as usize];
                 match ram_size_code {
                     0x00 => 0,
                     0x02 => 8192,   // 8 KB
@@ -192,12 +203,18 @@ impl Cartridge {
             _ => 0,
         };
 
+        // MBC6 save files contain the cartridge's flash memory after RAM
+        let expected_sav_size = match mapper_type {
+            MapperType::MBC6 => expected_ram_size + mapper::MBC6_FLASH_LEN,
+            _ => expected_ram_size,
+        };
+
         let ram = if let Some(loaded_ram) = &loaded_ram {
             if mapper_features.has_ram
                 && mapper_features.has_battery
-                && loaded_ram.len() == expected_ram_size
+                && loaded_ram.len() == expected_sav_size
             {
-                loaded_ram.clone()
+                loaded_ram[..expected_ram_size].to_vec()
             } else {
                 vec![0; expected_ram_size]
             }
@@ -217,7 +234,7 @@ impl Cartridge {
         let mapper = Mapper::new(
             mapper_type,
             mapper_features,
-            rtc,
+            rtc_bytes.as_deref(),
             rom.len() as u32,
             ram.len() as u32,
             loaded_ram.as_ref(),
@@ -259,6 +276,10 @@ impl Cartridge {
     /// This method will panic if the ROM address is invalid. ROM addresses must be in the range
     /// \[0x0000, 0x7FFF\].
     pub fn read_rom_address(&self, address: u16) -> u8 {
+        if let Some(value) = self.mapper.read_flash_address(address) {
+            return value;
+        }
+
         let mapped_address = self.mapper.map_rom_address(address);
         self.rom[mapped_address as usize]
     }
@@ -270,6 +291,12 @@ impl Cartridge {
     /// This method will panic if the ROM address is invalid. ROM addresses must be in the range
     /// \[0x0000, 0x7FFF\].
     pub fn write_rom_address(&mut self, address: u16, value: u8) {
+        if self.mapper.is_flash_address(address) {
+            if let Some(ram_battery) = &mut self.ram_battery {
+                ram_battery.mark_dirty();
+            }
+        }
+
         self.mapper.write_rom_address(address, value);
     }
 
@@ -299,6 +326,12 @@ impl Cartridge {
             }
             RamMapResult::MapperRegister => {
                 self.mapper.write_ram_addressed_register(address, value);
+                if let Some(image) = self.mapper.take_camera_image() {
+                    let image_start = mapper::POCKET_CAMERA_IMAGE_RAM_START;
+                    if let Some(ram) = self.ram.get_mut(image_start..image_start + image.len()) {
+                        ram.copy_from_slice(&image);
+                    }
+                }
                 if let Some(ram_battery) = &mut self.ram_battery {
                     ram_battery.mark_dirty();
                 }
@@ -312,23 +345,54 @@ impl Cartridge {
     /// be saved as well.
     pub fn persist_state(&mut self) -> Result<(), io::Error> {
         if let Some(ram_battery) = &mut self.ram_battery {
-            // Prefer to serialize EEPROM memory if the mapper has an EEPROM chip
-            let ram_to_persist = self.mapper.get_eeprom_memory().unwrap_or(&self.ram);
-            ram_battery.persist_state(ram_to_persist, self.mapper.get_clock())?;
+            // Prefer to serialize EEPROM memory if the mapper has an EEPROM chip, and save flash
+            // memory after RAM if the cartridge has flash
+            let ram_to_persist: Cow<'_, [u8]> =
+                match (self.mapper.get_eeprom_memory(), self.mapper.get_flash_memory()) {
+                    (Some(eeprom), _) => Cow::Borrowed(eeprom),
+                    (None, Some(flash)) => Cow::Owned([self.ram.as_slice(), flash].concat()),
+                    (None, None) => Cow::Borrowed(&self.ram),
+                };
+            ram_battery.persist_state(&ram_to_persist, self.mapper.serialize_rtc())?;
         }
 
         Ok(())
     }
 
+    /// Clear external RAM and any other battery-backed memory, and restart the real-time clock from
+    /// zero, as if the cartridge had been loaded without a save file. This does not modify the save
+    /// file.
+    pub fn reset_save_data(&mut self) {
+        self.ram.fill(0);
+        self.mapper.reset_save_data();
+    }
+
+    /// Signal to the mapper that the boot sequence has finished.
+    pub fn end_boot_sequence(&mut self) {
+        self.mapper.end_boot_sequence();
+    }
+
     /// Update the current state of the real-time clock based on the current time, if this
     /// cartridge has one.
     pub fn update_rtc(&mut self) {
         self.mapper.update_rtc();
     }
 
+    /// Mix audio produced by the cartridge hardware into the given interleaved stereo samples.
+    pub fn mix_audio(&mut self, samples: &mut [f32]) {
+        self.mapper.mix_audio(samples);
+    }
+
     /// Whether or not this cartridge supports CGB enhancements (or requires CGB)
     pub fn supports_cgb_mode(&self) -> bool {
-        self.rom[address::CGB_SUPPORT as usize] & 0x80 != 0
+        self.header()[address::CGB_SUPPORT as usize] & 0x80 != 0
+    }
+
+    /// The cartridge header, from the start of ROM through 0x014F. For MMM01 multicarts this is
+    /// the menu's header rather than the header at the start of ROM.
+    pub fn header(&self) -> &[u8] {
+        let header_offset = header_offset(&self.rom);
+        &self.rom[header_offset..header_offset + 0x0150]
     }
 
     pub fn move_unserializable_fields_from(&mut self, other: Self) {
@@ -370,44 +434,107 @@ pub struct AddressSpace {
     )]
     hram: [u8; 127],
     ie_register: u8,
+    // Set until the boot ROM unmaps itself
+    boot_rom: Option<Vec<u8>>,
 }
 
 impl AddressSpace {
+    pub fn new(mut cartridge: Cartridge, execution_mode: ExecutionMode) -> Self {
+        // There is no boot ROM to run, so the cartridge starts in its post-boot state
+        cartridge.end_boot_sequence();
+        Self::new_internal(cartridge, execution_mode, IoRegisters::new(execution_mode), None)
+    }
+
+    /// Create an address space with the given boot ROM mapped over the start of cartridge ROM,
+    /// and with hardware registers initialized to their power-on values.
+    ///
+    /// The boot ROM should be 256 bytes for DMG or 2304 bytes for CGB.
+    pub fn new_with_boot_rom(
+        cartridge: Cartridge,
+        execution_mode: ExecutionMode,
+        boot_rom: Vec<u8>,
+    ) -> Self {
+        Self::new_internal(
+            cartridge,
+            execution_mode,
+            IoRegisters::new_pre_boot(execution_mode),
+            Some(boot_rom),
+        )
+    }
+
     #[allow(clippy::large_stack_arrays)]
-    pub fn new(cartridge: Cartridge, execution_mode: ExecutionMode) -> Self {
+    fn new_internal(
+        cartridge: Cartridge,
+        execution_mode: ExecutionMode,
+        io_registers: IoRegisters,
+        boot_rom: Option<Vec<u8>>,
+    ) -> Self {
         Self {
             execution_mode,
             cartridge,
             vram: [0; 16384],
             working_ram: [0; 32768],
             oam: [0; 160],
-            io_registers: IoRegisters::new(execution_mode),
+            io_registers,
             hram: [0; 127],
             ie_register: 0,
+            boot_rom,
+        }
+    }
+
+    pub fn execution_mode(&self) -> ExecutionMode {
+        self.execution_mode
+    }
+
+    fn read_boot_rom(&self, address: u16) -> Option<u8> {
+        let boot_rom = self.boot_rom.as_ref()?;
+        match address {
+            ..=address::BOOT_ROM_END | address::CGB_BOOT_ROM_START..=address::CGB_BOOT_ROM_END => {
+                boot_rom.get(address as usize).copied()
+            }
+            _ => None,
         }
     }
 
+    fn write_boot_rom_disable(&mut self, value: u8) {
+        if value == 0 || self.boot_rom.is_none() {
+            return;
+        }
+
+        log::info!("Boot ROM unmapped");
+        self.boot_rom = None;
+        self.cartridge.end_boot_sequence();
+
+        // The CGB boot ROM writes 0x04 to KEY0 when running a cartridge without CGB support, which
+        // puts the hardware into DMG compatibility mode once the boot ROM is unmapped
+        if self.execution_mode == ExecutionMode::GameBoyColor
+            && self.io_registers.read_register(IoRegister::KEY0) & 0x0C == 0x04
+        {
+            log::info!("Switching to DMG compatibility mode");
+            self.execution_mode = ExecutionMode::GameBoy;
+            self.io_registers.enter_dmg_compatibility_mode();
+        }
+    }
+
+    // If an OAM DMA transfer is reading from the same memory bus that the CPU is trying to access,
+    // returns the address that the transfer is currently reading from
+    fn oam_dma_bus_conflict(&self, address: u16, ppu_state: &PpuState) -> Option<u16> {
+        let oam_dma_status = ppu_state.oam_dma_status()?;
+        let source_address = oam_dma_status.current_source_address();
+
+        let cpu_bus = MemoryBus::from_address(address, self.execution_mode)?;
+        (MemoryBus::from_address(source_address, self.execution_mode) == Some(cpu_bus))
+            .then_some(source_address)
+    }
+
     fn is_cpu_access_allowed(&self, address: u16, ppu_state: &PpuState) -> bool {
-        if let Some(oam_dma_status) = ppu_state.oam_dma_status() {
-            match self.execution_mode {
-                ExecutionMode::GameBoy => {
-                    // In DMG mode, non-HRAM access is not allowed while an OAM DMA transfer is active,
-                    // even if the PPU is disabled
-                    if !(address::HRAM_START..=address::HRAM_END).contains(&address) {
-                        return false;
-                    }
-                }
-                ExecutionMode::GameBoyColor => {
-                    // In CGB mode, the CPU is not allowed to access the same section of RAM that
-                    // the transfer is accessing
-                    let requested_address_range = AddressRange::from_address(address);
-                    if requested_address_range == AddressRange::Oam
-                        || requested_address_range
-                            == AddressRange::from_address(oam_dma_status.current_source_address())
-                    {
-                        return false;
-                    }
-                }
+        if ppu_state.oam_dma_status().is_some() {
+            // OAM is not accessible while an OAM DMA transfer is active, even if the PPU is
+            // disabled, and neither is the memory bus that the transfer is reading from
+            if AddressRange::from_address(address) == AddressRange::Oam
+                || self.oam_dma_bus_conflict(address, ppu_state).is_some()
+            {
+                return false;
             }
         }
 
@@ -427,7 +554,14 @@ impl AddressSpace {
 
     /// Read the value at the given address from the perspective of the CPU. Returns 0xFF if the
     /// CPU is not able to access the given address because of PPU state.
+    ///
+    /// If an OAM DMA transfer is using the same memory bus as the given address, the CPU instead
+    /// sees the byte that the transfer is currently reading.
     pub fn read_address_u8(&self, address: u16, ppu_state: &PpuState) -> u8 {
+        if let Some(source_address) = self.oam_dma_bus_conflict(address, ppu_state) {
+            return self.read_address_u8_no_access_check(source_address);
+        }
+
         if !self.is_cpu_access_allowed(address, ppu_state) {
             return 0xFF;
         }
@@ -463,9 +597,9 @@ impl AddressSpace {
 
     fn read_address_u8_no_access_check(&self, address: u16) -> u8 {
         match address {
-            address @ address::ROM_START..=address::ROM_END => {
-                self.cartridge.read_rom_address(address)
-            }
+            address @ address::ROM_START..=address::ROM_END => self
+                .read_boot_rom(address)
+                .unwrap_or_else(|| self.cartridge.read_rom_address(address)),
             address @ address::VRAM_START..=address::VRAM_END => {
                 self.vram[self.map_vram_address(address)]
             }
@@ -512,14 +646,6 @@ impl AddressSpace {
         }
     }
 
-    /// Read the value at the given address and the following address as a little-endian 16-bit
-    /// value.
-    pub fn read_address_u16(&self, address: u16, ppu_state: &PpuState) -> u16 {
-        let lsb = self.read_address_u8(address, ppu_state);
-        let msb = self.read_address_u8(address + 1, ppu_state);
-        u16::from_le_bytes([lsb, msb])
-    }
-
     /// Assign a value to the given address from the perspective of the CPU. The write is ignored
     /// if the CPU is not allowed to access the given address due to PPU state.
     pub fn write_address_u8(&mut self, address: u16, value: u8, ppu_state: &PpuState) {
@@ -553,7 +679,17 @@ impl AddressSpace {
                 self.oam[(address - address::OAM_START) as usize] = value;
             }
             _address @ address::UNUSABLE_START..=address::UNUSABLE_END => {}
+            address::BOOT_ROM_DISABLE => {
+                self.write_boot_rom_disable(value);
+            }
             address @ address::IO_REGISTERS_START..=address::IO_REGISTERS_END => {
+                // KEY0 can only be written by the boot ROM
+                if IoRegister::from_address(address) == Some(IoRegister::KEY0)
+                    && self.boot_rom.is_none()
+                {
+                    return;
+                }
+
                 self.io_registers.write_address(address, value);
             }
             address @ address::HRAM_START..=address::HRAM_END => {
@@ -565,12 +701,9 @@ impl AddressSpace {
         }
     }
 
-    /// Assign a 16-bit value to the given address and the following address, using
-    /// little-endian.
-    pub fn write_address_u16(&mut self, address: u16, value: u16, ppu_state: &PpuState) {
-        let [lsb, msb] = value.to_le_bytes();
-        self.write_address_u8(address, lsb, ppu_state);
-        self.write_address_u8(address + 1, msb, ppu_state);
+    /// The header of the loaded cartridge; see [`Cartridge::header`].
+    pub fn cartridge_header(&self) -> &[u8] {
+        self.cartridge.header()
     }
 
     pub fn get_io_registers(&self) -> &IoRegisters {
@@ -599,6 +732,12 @@ impl AddressSpace {
         }
     }
 
+    /// Direct mutable access to OAM, bypassing access checks related to PPU state. This should only
+    /// be used by the PPU.
+    pub fn get_oam_mut(&mut self) -> &mut [u8; 160] {
+        &mut self.oam
+    }
+
     /// Retrieve the current value of the IE register (interrupts enabled).
     pub fn get_ie_register(&self) -> u8 {
         self.ie_register
@@ -619,6 +758,14 @@ impl AddressSpace {
         self.cartridge.update_rtc();
     }
 
+    pub fn reset_cartridge_save_data(&mut self) {
+        self.cartridge.reset_save_data();
+    }
+
+    pub fn mix_cartridge_audio(&mut self, samples: &mut [f32]) {
+        self.cartridge.mix_audio(samples);
+    }
+
     pub fn move_unserializable_fields_from(&mut self, other: Self) {
         self.cartridge.move_unserializable_fields_from(other.cartridge);
     }
@@ -666,11 +813,116 @@ impl AddressRange {
     }
 }
 
+// The memory buses that are shared between the CPU and OAM DMA transfers. Addresses not on one of
+// these buses (OAM, I/O registers, HRAM) are internal to the SoC.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum MemoryBus {
+    External,
+    Vram,
+    // CGB-only; working RAM is on the external bus in DMG mode
+    WorkingRam,
+}
+
+impl MemoryBus {
+    fn from_address(address: u16, execution_mode: ExecutionMode) -> Option<Self> {
+        match AddressRange::from_address(address) {
+            AddressRange::Rom | AddressRange::CartridgeRam => Some(Self::External),
+            AddressRange::Vram => Some(Self::Vram),
+            AddressRange::WorkingRam => match execution_mode {
+                ExecutionMode::GameBoy => Some(Self::External),
+                ExecutionMode::GameBoyColor => Some(Self::WorkingRam),
+            },
+            AddressRange::Oam
+            | AddressRange::Unusable
+            | AddressRange::HardwareRegisters
+            | AddressRange::Hram
+            | AddressRange::IeRegister => None,
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::memory::ioregisters::IoRegister;
 
+    fn dmg_only_test_cartridge() -> Cartridge {
+        let mut rom = vec![0xAA; 0x8000];
+        rom[address::CGB_SUPPORT as usize] = 0x00;
+        rom[address::MAPPER as usize] = 0x00;
+        rom[address::RAM_SIZE as usize] = 0x00;
+        Cartridge::new(rom, None, ControllerStates::default()).unwrap()
+    }
+
+    #[test]
+    fn dmg_boot_rom() {
+        let mut address_space = AddressSpace::new_with_boot_rom(
+            dmg_only_test_cartridge(),
+            ExecutionMode::GameBoy,
+            vec![0x31; 0x100],
+        );
+        let ppu_state = PpuState::new(ExecutionMode::GameBoy);
+
+        assert_eq!(0x31, address_space.read_address_u8(0x0000, &ppu_state));
+        assert_eq!(0x31, address_space.read_address_u8(0x00FF, &ppu_state));
+        assert_eq!(0xAA, address_space.read_address_u8(0x0100, &ppu_state));
+        assert_eq!(0xAA, address_space.read_address_u8(0x0200, &ppu_state));
+
+        // Writing 0 should not unmap the boot ROM
+        address_space.write_address_u8(address::BOOT_ROM_DISABLE, 0x00, &ppu_state);
+        assert_eq!(0x31, address_space.read_address_u8(0x0000, &ppu_state));
+
+        address_space.write_address_u8(address::BOOT_ROM_DISABLE, 0x01, &ppu_state);
+        assert_eq!(0xAA, address_space.read_address_u8(0x0000, &ppu_state));
+        assert_eq!(ExecutionMode::GameBoy, address_space.execution_mode());
+    }
+
+    #[test]
+    fn cgb_boot_rom_compatibility_mode() {
+        let mut address_space = AddressSpace::new_with_boot_rom(
+            dmg_only_test_cartridge(),
+            ExecutionMode::GameBoyColor,
+            vec![0x31; 0x900],
+        );
+        let ppu_state = PpuState::new(ExecutionMode::GameBoyColor);
+
+        assert_eq!(0x31, address_space.read_address_u8(0x0000, &ppu_state));
+        assert_eq!(0xAA, address_space.read_address_u8(0x0150, &ppu_state));
+        assert_eq!(0x31, address_space.read_address_u8(0x0200, &ppu_state));
+        assert_eq!(0x31, address_space.read_address_u8(0x08FF, &ppu_state));
+        assert_eq!(0xAA, address_space.read_address_u8(0x0900, &ppu_state));
+
+        address_space.write_address_u8(0xFF4C, 0x04, &ppu_state);
+        address_space.write_address_u8(address::BOOT_ROM_DISABLE, 0x11, &ppu_state);
+
+        assert_eq!(0xAA, address_space.read_address_u8(0x0000, &ppu_state));
+        assert_eq!(ExecutionMode::GameBoy, address_space.execution_mode());
+        assert!(address_space.get_io_registers().dmg_compatibility_mode());
+
+        // CGB-only registers should no longer be accessible
+        assert_eq!(0xFF, address_space.read_address_u8(0xFF4F, &ppu_state));
+    }
+
+    #[test]
+    fn cgb_boot_rom_cgb_mode() {
+        let mut address_space = AddressSpace::new_with_boot_rom(
+            Cartridge::new_cgb_test(),
+            ExecutionMode::GameBoyColor,
+            vec![0x31; 0x900],
+        );
+        let ppu_state = PpuState::new(ExecutionMode::GameBoyColor);
+
+        address_space.write_address_u8(0xFF4C, 0x80, &ppu_state);
+        address_space.write_address_u8(address::BOOT_ROM_DISABLE, 0x11, &ppu_state);
+
+        assert_eq!(ExecutionMode::GameBoyColor, address_space.execution_mode());
+        assert!(!address_space.get_io_registers().dmg_compatibility_mode());
+
+        // KEY0 should not be writable after the boot ROM is unmapped
+        address_space.write_address_u8(0xFF4C, 0x04, &ppu_state);
+        assert_eq!(0x80, address_space.read_address_u8(0xFF4C, &ppu_state));
+    }
+
     #[test]
     fn cgb_vram_banks() {
         let mut address_space =
@@ -3,9 +3,16 @@
 //
 
 pub const ENTRY_POINT: u16 = 0x0100;
+pub const NINTENDO_LOGO_START: u16 = 0x0104;
+pub const NINTENDO_LOGO_END: u16 = 0x0133;
+pub const TITLE_START: u16 = 0x0134;
+pub const TITLE_END: u16 = 0x0143;
 pub const CGB_SUPPORT: u16 = 0x0143;
+pub const NEW_LICENSEE_CODE: u16 = 0x0144;
 pub const MAPPER: u16 = 0x0147;
 pub const RAM_SIZE: u16 = 0x0149;
+pub const OLD_LICENSEE_CODE: u16 = 0x014B;
+pub const GLOBAL_CHECKSUM: u16 = 0x014E;
 
 //
 // Address space boundaries
@@ -42,3 +49,16 @@ pub const HRAM_START: u16 = 0xFF80;
 pub const HRAM_END: u16 = 0xFFFE;
 
 pub const IE_REGISTER: u16 = 0xFFFF;
+
+//
+// Boot ROM
+//
+
+// The boot ROM is mapped over 0x0000-0x00FF, and the CGB boot ROM is additionally mapped over
+// 0x0200-0x08FF; the cartridge header in between is always visible
+pub const BOOT_ROM_END: u16 = 0x00FF;
+pub const CGB_BOOT_ROM_START: u16 = 0x0200;
+pub const CGB_BOOT_ROM_END: u16 = 0x08FF;
+
+// Writing a non-zero value to this register unmaps the boot ROM
+pub const BOOT_ROM_DISABLE: u16 = 0xFF50;
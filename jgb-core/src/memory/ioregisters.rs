@@ -2,7 +2,7 @@ mod lcdc;
 
 use crate::cpu::{ExecutionMode, InterruptType};
 use crate::memory::address;
-use crate::ppu::PpuMode;
+use crate::ppu::{CompatibilityPalettes, PpuMode};
 pub use lcdc::{Lcdc, SpriteMode, TileDataRange};
 use serde::{Deserialize, Serialize};
 
@@ -51,6 +51,7 @@ pub enum IoRegister {
     WY,
     WX,
     // CGB-only registers start here
+    KEY0,
     KEY1,
     VBK,
     HDMA1,
@@ -114,6 +115,7 @@ impl IoRegister {
             0xFF49 => Self::OBP1,
             0xFF4A => Self::WY,
             0xFF4B => Self::WX,
+            0xFF4C => Self::KEY0,
             0xFF4D => Self::KEY1,
             0xFF4F => Self::VBK,
             0xFF51 => Self::HDMA1,
@@ -179,6 +181,7 @@ impl IoRegister {
             Self::OBP1 => 0x49,
             Self::WY => 0x4A,
             Self::WX => 0x4B,
+            Self::KEY0 => 0x4C,
             Self::KEY1 => 0x4D,
             Self::VBK => 0x4F,
             Self::HDMA1 => 0x51,
@@ -251,7 +254,8 @@ impl IoRegister {
     pub fn is_cgb_only_register(self) -> bool {
         matches!(
             self,
-            Self::KEY1
+            Self::KEY0
+                | Self::KEY1
                 | Self::VBK
                 | Self::HDMA1
                 | Self::HDMA2
@@ -310,7 +314,7 @@ impl InterruptFlags<'_> {
     }
 }
 
-fn dirty_bit_for_register(io_register: IoRegister) -> Option<u16> {
+fn dirty_bit_for_register(io_register: IoRegister) -> Option<u32> {
     match io_register {
         IoRegister::NR10 => Some(0x0001),
         IoRegister::NR11 => Some(0x0002),
@@ -328,6 +332,12 @@ fn dirty_bit_for_register(io_register: IoRegister) -> Option<u16> {
         IoRegister::NR44 => Some(0x2000),
         IoRegister::DMA => Some(0x4000),
         IoRegister::HDMA5 => Some(0x8000),
+        IoRegister::DIV => Some(0x10000),
+        IoRegister::TIMA => Some(0x20000),
+        IoRegister::TMA => Some(0x40000),
+        IoRegister::TAC => Some(0x80000),
+        // Tracked so that movie playback can tell whether the game polled the joypad in a frame
+        IoRegister::JOYP => Some(0x100000),
         _ => None,
     }
 }
@@ -349,9 +359,10 @@ pub struct IoRegisters {
         deserialize_with = "crate::serialize::deserialize_array"
     )]
     cgb_obj_palette_ram: [u8; 64],
-    dirty_bits: u16,
+    dirty_bits: u32,
     execution_mode: ExecutionMode,
     current_ppu_mode: PpuMode,
+    dmg_compatibility_mode: bool,
 }
 
 impl IoRegisters {
@@ -389,9 +400,17 @@ impl IoRegisters {
             contents[IoRegister::OCPS.to_relative_address()] = 0xC1;
         }
 
-        // Don't boot with DMA transfer registers flagged as dirty
-        let dirty_bits = !dirty_bit_for_register(IoRegister::DMA).unwrap()
-            & !dirty_bit_for_register(IoRegister::HDMA5).unwrap();
+        // Don't boot with DMA transfer or timer registers flagged as dirty
+        let dirty_bits = [
+            IoRegister::DMA,
+            IoRegister::HDMA5,
+            IoRegister::DIV,
+            IoRegister::TIMA,
+            IoRegister::TMA,
+            IoRegister::TAC,
+        ]
+        .into_iter()
+        .fold(!0, |dirty_bits, register| dirty_bits & !dirty_bit_for_register(register).unwrap());
         Self {
             contents,
             cgb_bg_palette_ram: [0xFF; 64],
@@ -399,9 +418,65 @@ impl IoRegisters {
             dirty_bits,
             execution_mode,
             current_ppu_mode: PpuMode::VBlank,
+            dmg_compatibility_mode: false,
         }
     }
 
+    /// Create hardware registers with the values they have at power-on, before the boot ROM has
+    /// run. [`IoRegisters::new`] instead initializes registers to the values that the boot ROM
+    /// leaves behind.
+    pub fn new_pre_boot(execution_mode: ExecutionMode) -> Self {
+        let mut contents = [0; 0x80];
+
+        contents[IoRegister::JOYP.to_relative_address()] = 0xCF;
+
+        contents[IoRegister::TAC.to_relative_address()] = 0xF8;
+
+        contents[IoRegister::IF.to_relative_address()] = 0xE0;
+
+        contents[IoRegister::STAT.to_relative_address()] = 0x80;
+
+        contents[IoRegister::DMA.to_relative_address()] = 0xFF;
+
+        if matches!(execution_mode, ExecutionMode::GameBoyColor) {
+            contents[IoRegister::KEY1.to_relative_address()] = 0x7E;
+            contents[IoRegister::HDMA1.to_relative_address()] = 0xFF;
+            contents[IoRegister::HDMA2.to_relative_address()] = 0xFF;
+            contents[IoRegister::HDMA3.to_relative_address()] = 0xFF;
+            contents[IoRegister::HDMA4.to_relative_address()] = 0xFF;
+            contents[IoRegister::HDMA5.to_relative_address()] = 0xFF;
+        }
+
+        Self { contents, ..Self::new(execution_mode) }
+    }
+
+    /// Switch from CGB mode to DMG compatibility mode, where the CGB behaves like a DMG except that
+    /// the DMG palettes select colors from CGB palette RAM. Palette RAM can no longer be modified
+    /// by the CPU after this is called.
+    pub fn enter_dmg_compatibility_mode(&mut self) {
+        self.execution_mode = ExecutionMode::GameBoy;
+        self.dmg_compatibility_mode = true;
+    }
+
+    /// Load the given palettes into BG palette 0 and OBJ palettes 0-1, the same as the CGB boot ROM
+    /// does before entering DMG compatibility mode. This bypasses palette RAM locking.
+    pub fn load_compatibility_palettes(&mut self, palettes: &CompatibilityPalettes) {
+        fn write_palette(palette_ram: &mut [u8], palette: [u16; 4]) {
+            for (bytes, color) in palette_ram.chunks_exact_mut(2).zip(palette) {
+                bytes.copy_from_slice(&color.to_le_bytes());
+            }
+        }
+
+        write_palette(&mut self.cgb_bg_palette_ram[..8], palettes.bg);
+        write_palette(&mut self.cgb_obj_palette_ram[..8], palettes.obj0);
+        write_palette(&mut self.cgb_obj_palette_ram[8..16], palettes.obj1);
+    }
+
+    /// Whether the hardware is a CGB running in DMG compatibility mode.
+    pub fn dmg_compatibility_mode(&self) -> bool {
+        self.dmg_compatibility_mode
+    }
+
     /// Read the value from the hardware register at the given address. Returns 0xFF if the address
     /// is invalid or the register is not readable by the CPU.
     pub fn read_address(&self, address: u16) -> u8 {
@@ -455,6 +530,11 @@ impl IoRegisters {
         let byte = self.contents[register.to_relative_address()];
         match register {
             IoRegister::JOYP => byte | 0xC0,
+            IoRegister::SC => match self.execution_mode {
+                // Bit 1 (clock speed) is only readable in CGB mode
+                ExecutionMode::GameBoy => byte | 0x7E,
+                ExecutionMode::GameBoyColor => byte | 0x7C,
+            },
             IoRegister::STAT | IoRegister::NR10 => byte | 0x80,
             IoRegister::NR11 | IoRegister::NR21 => byte | 0x3F,
             IoRegister::NR30 => byte | 0x7F,
@@ -771,6 +851,7 @@ mod tests {
             dirty_bits: 0x00,
             execution_mode: ExecutionMode::GameBoy,
             current_ppu_mode: PpuMode::VBlank,
+            dmg_compatibility_mode: false,
         }
     }
 
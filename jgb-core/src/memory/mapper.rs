@@ -1,8 +1,18 @@
+mod huc3;
 mod mbc3;
+mod mbc6;
 mod mbc7;
+mod pocketcamera;
+mod tama5;
 
+use crate::camera::{BlankImageSource, CameraImageSource};
 use crate::memory::address;
+use crate::memory::mapper::huc3::{Huc3Clock, Huc3Rtc};
+use crate::memory::mapper::mbc6::Mbc6Flash;
 use crate::memory::mapper::mbc7::Mbc7Eeprom;
+use crate::memory::mapper::pocketcamera::PocketCamera;
+use crate::memory::mapper::tama5::{Tama5, Tama5Clock};
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
 use std::cell::RefCell;
 use std::fmt::Formatter;
@@ -11,16 +21,30 @@ use std::time::SystemTime;
 
 use crate::input::AccelerometerState;
 use crate::startup::ControllerStates;
-pub(crate) use mbc3::RealTimeClock;
+use mbc3::RealTimeClock;
+
+pub(crate) use mbc6::FLASH_LEN as MBC6_FLASH_LEN;
+pub(crate) use pocketcamera::IMAGE_RAM_START as POCKET_CAMERA_IMAGE_RAM_START;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub(crate) enum MapperType {
     None,
     MBC1,
+    MBC1M,
     MBC2,
     MBC3,
     MBC5,
+    MBC5Bootleg,
+    MBC6,
     MBC7,
+    MMM01,
+    HuC1,
+    HuC3,
+    TAMA5,
+    PocketCamera,
+    WisdomTree,
+    SachenMMC1,
+    SachenMMC2,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -51,6 +75,8 @@ pub(crate) enum Mapper {
         rom_bank_number: u8,
         ram_bank_number: u8,
         banking_mode_select: u8,
+        // MBC1M multicarts wire the ROM bank number register differently
+        multicart: bool,
     },
     MBC2 {
         rom_bank_bit_mask: u8,
@@ -63,6 +89,8 @@ pub(crate) enum Mapper {
         rom_bank_number: u8,
         ram_bank_number: u8,
         real_time_clock: Option<RealTimeClock>,
+        #[serde(skip)]
+        rtc_emulated_time: Rc<RefCell<Option<SystemTime>>>,
     },
     MBC5 {
         rom_bank_bit_mask: u16,
@@ -72,6 +100,22 @@ pub(crate) enum Mapper {
         ram_bank_number: u8,
         #[serde(skip)]
         rumble_motor_on: Rc<RefCell<bool>>,
+        // Bootleg MBC5 clones decode the whole 0x2000-0x3FFF range as the low ROM bank register
+        bootleg: bool,
+    },
+    MBC6 {
+        // ROM and flash are banked in 8KB units, RAM in 4KB units
+        rom_bank_bit_mask: u8,
+        ram_bank_bit_mask: u8,
+        ram_enable: u8,
+        // Each 8KB window (0x4000 and 0x6000) independently maps either a ROM bank or a flash bank
+        rom_bank_numbers: [u8; 2],
+        flash_selected: [bool; 2],
+        // Each 4KB RAM window (0xA000 and 0xB000) has its own RAM bank number
+        ram_bank_numbers: [u8; 2],
+        flash_enable: bool,
+        flash_write_enable: bool,
+        flash: Mbc6Flash,
     },
     MBC7 {
         eeprom: Mbc7Eeprom,
@@ -82,13 +126,87 @@ pub(crate) enum Mapper {
         live_accelerometer_state: Rc<RefCell<AccelerometerState>>,
         latched_accelerometer_state: Option<AccelerometerState>,
     },
+    MMM01 {
+        rom_bank_bit_mask: u16,
+        ram_bank_bit_mask: u8,
+        ram_enable: u8,
+        // The full ROM bank number is (rom_bank_high << 7) | (rom_bank_mid << 5) | rom_bank_low
+        rom_bank_low: u8,
+        rom_bank_mid: u8,
+        rom_bank_high: u8,
+        // The full RAM bank number is (ram_bank_high << 2) | ram_bank_low
+        ram_bank_low: u8,
+        ram_bank_high: u8,
+        // Bits of rom_bank_low/ram_bank_low that can no longer be written once mapped
+        rom_bank_lock_mask: u8,
+        ram_bank_lock_mask: u8,
+        banking_mode_select: u8,
+        banking_mode_locked: bool,
+        // Swap rom_bank_mid and ram_bank_low, for games that use MBC1-style large ROM banking
+        multiplex: bool,
+        // Whether the menu has locked in a game. Before this, the last 32KB of ROM are mapped
+        mapped: bool,
+    },
+    HuC1 {
+        rom_bank_bit_mask: u8,
+        ram_bank_bit_mask: u8,
+        rom_bank_number: u8,
+        ram_bank_number: u8,
+        // If true, the RAM address range maps to the IR port instead of RAM
+        ir_mode: bool,
+    },
+    HuC3 {
+        rom_bank_bit_mask: u8,
+        ram_bank_bit_mask: u8,
+        // Selects what the RAM address range maps to: RAM, RTC registers, or the IR port
+        ram_mode: u8,
+        rom_bank_number: u8,
+        ram_bank_number: u8,
+        rtc: Huc3Rtc,
+        #[serde(skip)]
+        rtc_emulated_time: Rc<RefCell<Option<SystemTime>>>,
+    },
+    TAMA5 {
+        rom_bank_bit_mask: u16,
+        rom_bank_number: u16,
+        tama5: Tama5,
+        #[serde(skip)]
+        rtc_emulated_time: Rc<RefCell<Option<SystemTime>>>,
+    },
+    PocketCamera {
+        rom_bank_bit_mask: u16,
+        ram_bank_bit_mask: u8,
+        ram_enable: u8,
+        rom_bank_number: u16,
+        // If bit 4 is set, the RAM address range maps to the camera registers instead of RAM
+        ram_bank_number: u8,
+        camera: PocketCamera,
+        #[serde(skip)]
+        image_source: Rc<RefCell<Option<Box<dyn CameraImageSource>>>>,
+    },
+    WisdomTree {
+        // Wisdom Tree banks the entire ROM address range in 32KB units
+        rom_bank_bit_mask: u8,
+        rom_bank_number: u8,
+    },
+    Sachen {
+        rom_bank_bit_mask: u8,
+        rom_bank_number: u8,
+        // The bits set in rom_bank_mask come from base_rom_bank instead of rom_bank_number
+        base_rom_bank: u8,
+        rom_bank_mask: u8,
+        // MMC2 stores its copy of the Nintendo logo with scrambled address lines
+        mmc2: bool,
+        // Set until the boot sequence ends; header reads are redirected while locked
+        locked: bool,
+    },
 }
 
 impl Mapper {
     pub(crate) fn new(
         mapper_type: MapperType,
         mapper_features: MapperFeatures,
-        rtc: Option<RealTimeClock>,
+        rtc_bytes: Option<&[u8]>,
         rom_size: u32,
         ram_size: u32,
         loaded_ram: Option<&Vec<u8>>,
@@ -102,13 +220,14 @@ impl Mapper {
 
         match mapper_type {
             MapperType::None => Self::None,
-            MapperType::MBC1 => Self::MBC1 {
+            MapperType::MBC1 | MapperType::MBC1M => Self::MBC1 {
                 rom_bank_bit_mask: rom_bank_bit_mask as u8,
                 ram_bank_bit_mask,
                 ram_enable: 0x00,
                 rom_bank_number: 0x00,
                 ram_bank_number: 0x00,
                 banking_mode_select: 0x00,
+                multicart: mapper_type == MapperType::MBC1M,
             },
             MapperType::MBC2 => Self::MBC2 {
                 rom_bank_bit_mask: rom_bank_bit_mask as u8,
@@ -116,12 +235,15 @@ impl Mapper {
                 rom_bank_number: 0x00,
             },
             MapperType::MBC3 => {
-                let real_time_clock = mapper_features.has_rtc.then(|| match rtc {
-                    Some(mut rtc) => {
-                        rtc.update(SystemTime::now());
-                        rtc
+                let now = rtc_now(&controller_states.rtc_emulated_time);
+                let real_time_clock = mapper_features.has_rtc.then(|| {
+                    match deserialize_rtc::<RealTimeClock>(rtc_bytes) {
+                        Some(mut rtc) => {
+                            rtc.update(now);
+                            rtc
+                        }
+                        None => RealTimeClock::new(now),
                     }
-                    None => RealTimeClock::new(SystemTime::now()),
                 });
                 Self::MBC3 {
                     rom_bank_bit_mask: rom_bank_bit_mask as u8,
@@ -129,9 +251,10 @@ impl Mapper {
                     rom_bank_number: 0x00,
                     ram_bank_number: 0x00,
                     real_time_clock,
+                    rtc_emulated_time: controller_states.rtc_emulated_time,
                 }
             }
-            MapperType::MBC5 => Self::MBC5 {
+            MapperType::MBC5 | MapperType::MBC5Bootleg => Self::MBC5 {
                 rom_bank_bit_mask,
                 ram_bank_bit_mask,
                 ram_enable: 0x00,
@@ -143,6 +266,29 @@ impl Mapper {
                     // Intentionally ignore shared reference
                     Rc::default()
                 },
+                bootleg: mapper_type == MapperType::MBC5Bootleg,
+            },
+            MapperType::MBC6 => Self::MBC6 {
+                rom_bank_bit_mask: if rom_size >= 1 << 13 {
+                    ((rom_size >> 13) - 1) as u8
+                } else {
+                    0
+                },
+                ram_bank_bit_mask: if ram_size >= 1 << 12 {
+                    ((ram_size >> 12) - 1) as u8
+                } else {
+                    0
+                },
+                ram_enable: 0x00,
+                rom_bank_numbers: [0x00; 2],
+                flash_selected: [false; 2],
+                ram_bank_numbers: [0x00; 2],
+                flash_enable: false,
+                flash_write_enable: false,
+                // Flash memory is saved after RAM in the .sav file
+                flash: Mbc6Flash::new(
+                    loaded_ram.and_then(|loaded_ram| loaded_ram.get(ram_size as usize..)),
+                ),
             },
             MapperType::MBC7 => Self::MBC7 {
                 eeprom: Mbc7Eeprom::new(loaded_ram),
@@ -152,6 +298,76 @@ impl Mapper {
                 live_accelerometer_state: controller_states.accelerometer_state,
                 latched_accelerometer_state: None,
             },
+            MapperType::MMM01 => Self::MMM01 {
+                rom_bank_bit_mask,
+                ram_bank_bit_mask,
+                ram_enable: 0x00,
+                rom_bank_low: 0x00,
+                rom_bank_mid: 0x00,
+                rom_bank_high: 0x00,
+                ram_bank_low: 0x00,
+                ram_bank_high: 0x00,
+                rom_bank_lock_mask: 0x00,
+                ram_bank_lock_mask: 0x00,
+                banking_mode_select: 0x00,
+                banking_mode_locked: false,
+                multiplex: false,
+                mapped: false,
+            },
+            MapperType::HuC1 => Self::HuC1 {
+                rom_bank_bit_mask: rom_bank_bit_mask as u8,
+                ram_bank_bit_mask,
+                rom_bank_number: 0x00,
+                ram_bank_number: 0x00,
+                ir_mode: false,
+            },
+            MapperType::HuC3 => Self::HuC3 {
+                rom_bank_bit_mask: rom_bank_bit_mask as u8,
+                ram_bank_bit_mask,
+                ram_mode: 0x00,
+                rom_bank_number: 0x00,
+                ram_bank_number: 0x00,
+                rtc: Huc3Rtc::new(
+                    deserialize_rtc::<Huc3Clock>(rtc_bytes),
+                    rtc_now(&controller_states.rtc_emulated_time),
+                ),
+                rtc_emulated_time: controller_states.rtc_emulated_time,
+            },
+            MapperType::TAMA5 => Self::TAMA5 {
+                rom_bank_bit_mask,
+                rom_bank_number: 0x00,
+                tama5: Tama5::new(
+                    loaded_ram,
+                    deserialize_rtc::<Tama5Clock>(rtc_bytes),
+                    rtc_now(&controller_states.rtc_emulated_time),
+                ),
+                rtc_emulated_time: controller_states.rtc_emulated_time,
+            },
+            MapperType::PocketCamera => Self::PocketCamera {
+                rom_bank_bit_mask,
+                ram_bank_bit_mask,
+                ram_enable: 0x00,
+                rom_bank_number: 0x01,
+                ram_bank_number: 0x00,
+                camera: PocketCamera::new(),
+                image_source: controller_states.camera_image_source,
+            },
+            MapperType::WisdomTree => Self::WisdomTree {
+                rom_bank_bit_mask: if rom_size >= 1 << 15 {
+                    ((rom_size >> 15) - 1) as u8
+                } else {
+                    0
+                },
+                rom_bank_number: 0x00,
+            },
+            MapperType::SachenMMC1 | MapperType::SachenMMC2 => Self::Sachen {
+                rom_bank_bit_mask: rom_bank_bit_mask as u8,
+                rom_bank_number: 0x01,
+                base_rom_bank: 0x00,
+                rom_bank_mask: 0x00,
+                mmc2: mapper_type == MapperType::SachenMMC2,
+                locked: true,
+            },
         }
     }
 
@@ -163,28 +379,33 @@ impl Mapper {
                 rom_bank_number,
                 ram_bank_number,
                 banking_mode_select,
+                multicart,
                 ..
             } => {
+                // The 0 -> 1 translation checks all 5 bits of the ROM bank number register even in
+                // MBC1M, where only the lowest 4 bits are connected
                 let rom_bank_number = if rom_bank_number == 0x00 { 0x01 } else { rom_bank_number };
 
+                // The 2-bit RAM bank number register supplies the high bits of the ROM bank number
+                let (rom_bank_number, high_bank_bits) = if multicart {
+                    (rom_bank_number & 0x0F, ram_bank_number << 4)
+                } else {
+                    (rom_bank_number, ram_bank_number << 5)
+                };
+
                 match address {
                     address @ 0x0000..=0x3FFF => {
                         if banking_mode_select == 0x00 {
                             u32::from(address)
                         } else {
-                            let bank_number = (ram_bank_number << 5) & rom_bank_bit_mask;
+                            let bank_number = high_bank_bits & rom_bank_bit_mask;
                             u32::from(address) + (u32::from(bank_number) << 14)
                         }
                     }
                     address @ 0x4000..=0x7FFF => {
-                        if banking_mode_select == 0x00 {
-                            let bank_number = rom_bank_number & rom_bank_bit_mask;
-                            u32::from(address - 0x4000) + (u32::from(bank_number) << 14)
-                        } else {
-                            let bank_number =
-                                (rom_bank_number | (ram_bank_number << 5)) & rom_bank_bit_mask;
-                            u32::from(address - 0x4000) + (u32::from(bank_number) << 14)
-                        }
+                        // The high bits apply here regardless of banking mode
+                        let bank_number = (rom_bank_number | high_bank_bits) & rom_bank_bit_mask;
+                        u32::from(address - 0x4000) + (u32::from(bank_number) << 14)
                     }
                     _ => panic!(
                         "mapper called for address outside of cartridge address range: {address:04X}"
@@ -192,7 +413,9 @@ impl Mapper {
                 }
             }
             &Self::MBC2 { rom_bank_bit_mask, rom_bank_number, .. }
-            | &Self::MBC3 { rom_bank_bit_mask, rom_bank_number, .. } => {
+            | &Self::MBC3 { rom_bank_bit_mask, rom_bank_number, .. }
+            | &Self::HuC1 { rom_bank_bit_mask, rom_bank_number, .. }
+            | &Self::HuC3 { rom_bank_bit_mask, rom_bank_number, .. } => {
                 let rom_bank_number = if rom_bank_number == 0x00 { 0x01 } else { rom_bank_number };
 
                 match address {
@@ -207,8 +430,10 @@ impl Mapper {
                 }
             }
             &Self::MBC5 { rom_bank_bit_mask, rom_bank_number, .. }
-            | &Self::MBC7 { rom_bank_bit_mask, rom_bank_number, .. } => {
-                // ROM bank 0 is actually bank 0 in MBC5 and MBC7
+            | &Self::MBC7 { rom_bank_bit_mask, rom_bank_number, .. }
+            | &Self::TAMA5 { rom_bank_bit_mask, rom_bank_number, .. }
+            | &Self::PocketCamera { rom_bank_bit_mask, rom_bank_number, .. } => {
+                // ROM bank 0 is actually bank 0 in MBC5, MBC7, TAMA5, and the Pocket Camera
 
                 match address {
                     address @ 0x0000..=0x3FFF => u32::from(address),
@@ -221,6 +446,107 @@ impl Mapper {
                     ),
                 }
             }
+            &Self::MBC6 { rom_bank_bit_mask, rom_bank_numbers, .. } => match address {
+                address @ 0x0000..=0x3FFF => u32::from(address),
+                address @ 0x4000..=0x7FFF => {
+                    let bank_number = rom_bank_numbers[mbc6_window(address)] & rom_bank_bit_mask;
+                    u32::from(address & 0x1FFF) + (u32::from(bank_number) << 13)
+                }
+                _ => panic!(
+                    "mapper called for address outside of cartridge address range: {address:04X}"
+                ),
+            },
+            &Self::WisdomTree { rom_bank_bit_mask, rom_bank_number } => match address {
+                address @ 0x0000..=0x7FFF => {
+                    let bank_number = rom_bank_number & rom_bank_bit_mask;
+                    u32::from(address) + (u32::from(bank_number) << 15)
+                }
+                _ => panic!(
+                    "mapper called for address outside of cartridge address range: {address:04X}"
+                ),
+            },
+            &Self::Sachen {
+                rom_bank_bit_mask,
+                rom_bank_number,
+                base_rom_bank,
+                rom_bank_mask,
+                mmc2,
+                locked,
+            } => {
+                let rom_bank_number = if rom_bank_number == 0x00 { 0x01 } else { rom_bank_number };
+                let base_bank_number = base_rom_bank & rom_bank_mask;
+
+                let (relative_address, bank_number) = match address {
+                    address @ 0x0100..=0x01FF if locked => {
+                        (sachen_locked_header_address(address, mmc2), base_bank_number)
+                    }
+                    address @ 0x0000..=0x3FFF => (address, base_bank_number),
+                    address @ 0x4000..=0x7FFF => {
+                        (address - 0x4000, base_bank_number | (rom_bank_number & !rom_bank_mask))
+                    }
+                    _ => panic!(
+                        "mapper called for address outside of cartridge address range: {address:04X}"
+                    ),
+                };
+
+                let bank_number = bank_number & rom_bank_bit_mask;
+                u32::from(relative_address) + (u32::from(bank_number) << 14)
+            }
+            &Self::MMM01 {
+                rom_bank_bit_mask,
+                rom_bank_low,
+                rom_bank_mid,
+                rom_bank_high,
+                ram_bank_low,
+                rom_bank_lock_mask,
+                banking_mode_select,
+                multiplex,
+                mapped,
+                ..
+            } => {
+                let bank_number = if mapped {
+                    let rom_bank_mid = if multiplex { ram_bank_low } else { rom_bank_mid };
+                    let locked_bits = (u16::from(rom_bank_high) << 7)
+                        | (u16::from(rom_bank_mid) << 5)
+                        | u16::from(rom_bank_low & rom_bank_lock_mask);
+
+                    match address {
+                        0x0000..=0x3FFF => {
+                            // With multiplexing, the mid bits only apply to the first bank in
+                            // banking mode 1, same as MBC1
+                            if multiplex && banking_mode_select == 0x00 {
+                                locked_bits & !(0x03 << 5)
+                            } else {
+                                locked_bits
+                            }
+                        }
+                        _ => {
+                            // The 0 -> 1 translation only considers the bits that the game can write
+                            let game_bits = match rom_bank_low & !rom_bank_lock_mask {
+                                0x00 => 0x01,
+                                game_bits => game_bits,
+                            };
+                            locked_bits | u16::from(game_bits)
+                        }
+                    }
+                } else {
+                    // Before mapping, the last 32KB of ROM is mapped regardless of the registers
+                    match address {
+                        0x0000..=0x3FFF => 0x01FE,
+                        _ => 0x01FF,
+                    }
+                };
+
+                match address {
+                    address @ 0x0000..=0x7FFF => {
+                        let bank_number = bank_number & rom_bank_bit_mask;
+                        u32::from(address & 0x3FFF) + (u32::from(bank_number) << 14)
+                    }
+                    _ => panic!(
+                        "mapper called for address outside of cartridge address range: {address:04X}"
+                    ),
+                }
+            }
         }
     }
 
@@ -228,7 +554,8 @@ impl Mapper {
     // cartridge registers
     pub(crate) fn write_rom_address(&mut self, address: u16, value: u8) {
         match self {
-            Self::None => {}
+            // TAMA5 registers are only accessible through the RAM address range
+            Self::None | Self::TAMA5 { .. } => {}
             Self::MBC1 {
                 ram_enable,
                 rom_bank_number,
@@ -285,11 +612,19 @@ impl Mapper {
                 _ => panic!("invalid ROM write address in MBC3 mapper: {address:04X}"),
             },
             Self::MBC5 {
-                ram_enable, rom_bank_number, ram_bank_number, rumble_motor_on, ..
+                ram_enable,
+                rom_bank_number,
+                ram_bank_number,
+                rumble_motor_on,
+                bootleg,
+                ..
             } => match address {
                 _address @ 0x0000..=0x1FFF => {
                     *ram_enable = value;
                 }
+                _address @ 0x2000..=0x3FFF if *bootleg => {
+                    *rom_bank_number = u16::from(value);
+                }
                 _address @ 0x2000..=0x2FFF => {
                     *rom_bank_number = (*rom_bank_number & 0xFF00) | u16::from(value);
                 }
@@ -322,6 +657,182 @@ impl Mapper {
                 _address @ 0x6000..=0x7FFF => {}
                 _ => panic!("invalid ROM write address in MBC7 mapper: {address:04X}"),
             },
+            Self::MMM01 {
+                ram_enable,
+                rom_bank_low,
+                rom_bank_mid,
+                rom_bank_high,
+                ram_bank_low,
+                ram_bank_high,
+                rom_bank_lock_mask,
+                ram_bank_lock_mask,
+                banking_mode_select,
+                banking_mode_locked,
+                multiplex,
+                mapped,
+                ..
+            } => {
+                // Most bits can only be written by the menu, before a game is mapped
+                let unmapped = !*mapped;
+                match address {
+                    _address @ 0x0000..=0x1FFF => {
+                        *ram_enable = value & 0x0F;
+                        if unmapped {
+                            *ram_bank_lock_mask = (value >> 4) & 0x03;
+                            *mapped = value & 0x40 != 0;
+                            if *mapped {
+                                log::debug!("MMM01 game mapped");
+                            }
+                        }
+                    }
+                    _address @ 0x2000..=0x3FFF => {
+                        let lock_mask = if unmapped { 0x00 } else { *rom_bank_lock_mask };
+                        *rom_bank_low = (*rom_bank_low & lock_mask) | (value & 0x1F & !lock_mask);
+                        if unmapped {
+                            *rom_bank_mid = (value >> 5) & 0x03;
+                        }
+                    }
+                    _address @ 0x4000..=0x5FFF => {
+                        let lock_mask = if unmapped { 0x00 } else { *ram_bank_lock_mask };
+                        *ram_bank_low = (*ram_bank_low & lock_mask) | (value & 0x03 & !lock_mask);
+                        if unmapped {
+                            *ram_bank_high = (value >> 2) & 0x03;
+                            *rom_bank_high = (value >> 4) & 0x03;
+                            *banking_mode_locked = value & 0x40 != 0;
+                        }
+                    }
+                    _address @ 0x6000..=0x7FFF => {
+                        if !*banking_mode_locked {
+                            *banking_mode_select = value & 0x01;
+                        }
+                        if unmapped {
+                            // Bits 2-5 lock bits 1-4 of the ROM bank number
+                            *rom_bank_lock_mask = (value & 0x3C) >> 1;
+                            *multiplex = value & 0x40 != 0;
+                        }
+                    }
+                    _ => panic!("invalid ROM write address in MMM01 mapper: {address:04X}"),
+                }
+            }
+            Self::MBC6 {
+                ram_enable,
+                rom_bank_numbers,
+                flash_selected,
+                ram_bank_numbers,
+                flash_enable,
+                flash_write_enable,
+                flash,
+                ..
+            } => match address {
+                _address @ 0x0000..=0x03FF => {
+                    *ram_enable = value;
+                }
+                _address @ 0x0400..=0x07FF => {
+                    ram_bank_numbers[0] = value;
+                }
+                _address @ 0x0800..=0x0BFF => {
+                    ram_bank_numbers[1] = value;
+                }
+                _address @ 0x0C00..=0x0FFF => {
+                    // Flash can only be enabled or disabled while flash writes are enabled
+                    if *flash_write_enable {
+                        *flash_enable = value & 0x01 != 0;
+                    }
+                }
+                _address @ 0x1000..=0x1FFF => {
+                    *flash_write_enable = value & 0x01 != 0;
+                }
+                _address @ 0x2000..=0x27FF => {
+                    rom_bank_numbers[0] = value & 0x7F;
+                }
+                _address @ 0x2800..=0x2FFF => {
+                    flash_selected[0] = value == 0x08;
+                }
+                _address @ 0x3000..=0x37FF => {
+                    rom_bank_numbers[1] = value & 0x7F;
+                }
+                _address @ 0x3800..=0x3FFF => {
+                    flash_selected[1] = value == 0x08;
+                }
+                address @ 0x4000..=0x7FFF => {
+                    // Writes to a window that maps flash are flash commands
+                    let window = mbc6_window(address);
+                    if flash_selected[window] && *flash_enable {
+                        let flash_address = mbc6_flash_address(rom_bank_numbers[window], address);
+                        flash.handle_write(flash_address, value, *flash_write_enable);
+                    }
+                }
+                _ => panic!("invalid ROM write address in MBC6 mapper: {address:04X}"),
+            },
+            Self::PocketCamera { ram_enable, rom_bank_number, ram_bank_number, .. } => {
+                match address {
+                    _address @ 0x0000..=0x1FFF => {
+                        *ram_enable = value;
+                    }
+                    _address @ 0x2000..=0x3FFF => {
+                        *rom_bank_number = u16::from(value & 0x3F);
+                    }
+                    _address @ 0x4000..=0x5FFF => {
+                        *ram_bank_number = value & 0x1F;
+                    }
+                    _address @ 0x6000..=0x7FFF => {}
+                    _ => panic!("invalid ROM write address in Pocket Camera mapper: {address:04X}"),
+                }
+            }
+            Self::WisdomTree { rom_bank_number, .. } => match address {
+                // The bank number comes from the low byte of the address rather than the value
+                _address @ 0x0000..=0x3FFF => {
+                    *rom_bank_number = address as u8;
+                }
+                _address @ 0x4000..=0x7FFF => {}
+                _ => panic!("invalid ROM write address in Wisdom Tree mapper: {address:04X}"),
+            },
+            Self::Sachen { rom_bank_number, base_rom_bank, rom_bank_mask, .. } => match address {
+                // The base bank and mask registers can only be written while the current ROM bank
+                // number has bits 4 and 5 set
+                _address @ 0x0000..=0x1FFF => {
+                    if *rom_bank_number & 0x30 == 0x30 {
+                        *base_rom_bank = value;
+                    }
+                }
+                _address @ 0x2000..=0x3FFF => {
+                    *rom_bank_number = value;
+                }
+                _address @ 0x4000..=0x5FFF => {
+                    if *rom_bank_number & 0x30 == 0x30 {
+                        *rom_bank_mask = value;
+                    }
+                }
+                _address @ 0x6000..=0x7FFF => {}
+                _ => panic!("invalid ROM write address in Sachen mapper: {address:04X}"),
+            },
+            Self::HuC1 { rom_bank_number, ram_bank_number, ir_mode, .. } => match address {
+                _address @ 0x0000..=0x1FFF => {
+                    // HuC-1 has no RAM enable; any value other than 0x0E selects RAM
+                    *ir_mode = value == 0x0E;
+                }
+                _address @ 0x2000..=0x3FFF => {
+                    *rom_bank_number = value & 0x3F;
+                }
+                _address @ 0x4000..=0x5FFF => {
+                    *ram_bank_number = value & 0x03;
+                }
+                _address @ 0x6000..=0x7FFF => {}
+                _ => panic!("invalid ROM write address in HuC-1 mapper: {address:04X}"),
+            },
+            Self::HuC3 { ram_mode, rom_bank_number, ram_bank_number, .. } => match address {
+                _address @ 0x0000..=0x1FFF => {
+                    *ram_mode = value & 0x0F;
+                }
+                _address @ 0x2000..=0x3FFF => {
+                    *rom_bank_number = value & 0x7F;
+                }
+                _address @ 0x4000..=0x5FFF => {
+                    *ram_bank_number = value & 0x0F;
+                }
+                _address @ 0x6000..=0x7FFF => {}
+                _ => panic!("invalid ROM write address in HuC-3 mapper: {address:04X}"),
+            },
         }
     }
 
@@ -386,10 +897,113 @@ impl Mapper {
                     RamMapResult::None
                 }
             }
+            &Self::MBC6 { ram_bank_bit_mask, ram_enable, ram_bank_numbers, .. } => {
+                if ram_enable & 0x0A == 0x0A {
+                    let window = usize::from(relative_address >= 0x1000);
+                    let bank_number = ram_bank_numbers[window] & ram_bank_bit_mask;
+                    RamMapResult::RamAddress(
+                        u32::from(relative_address & 0x0FFF) + (u32::from(bank_number) << 12),
+                    )
+                } else {
+                    RamMapResult::None
+                }
+            }
             &Self::MBC7 { ram_status, .. } => match ram_status {
                 Mbc7RamStatus::Enabled => RamMapResult::MapperRegister,
                 _ => RamMapResult::None,
             },
+            &Self::MMM01 {
+                ram_bank_bit_mask,
+                ram_enable,
+                rom_bank_mid,
+                ram_bank_low,
+                ram_bank_high,
+                banking_mode_select,
+                multiplex,
+                ..
+            } => {
+                if ram_enable == 0x0A {
+                    let ram_bank_low = match (multiplex, banking_mode_select) {
+                        (false, _) => ram_bank_low,
+                        // Multiplexed RAM banking behaves like MBC1, where the low bits only apply
+                        // in banking mode 1
+                        (true, 0x00) => 0x00,
+                        (true, _) => rom_bank_mid,
+                    };
+                    let bank_number = ((ram_bank_high << 2) | ram_bank_low) & ram_bank_bit_mask;
+                    RamMapResult::RamAddress(
+                        u32::from(relative_address) + (u32::from(bank_number) << 13),
+                    )
+                } else {
+                    RamMapResult::None
+                }
+            }
+            &Self::HuC1 { ram_bank_bit_mask, ram_bank_number, ir_mode, .. } => {
+                if ir_mode {
+                    RamMapResult::MapperRegister
+                } else {
+                    let bank_number = ram_bank_number & ram_bank_bit_mask;
+                    RamMapResult::RamAddress(
+                        u32::from(relative_address) + (u32::from(bank_number) << 13),
+                    )
+                }
+            }
+            &Self::HuC3 { ram_bank_bit_mask, ram_mode, ram_bank_number, .. } => match ram_mode {
+                // 0x00 is supposed to be read-only RAM, but games only write to RAM in mode 0x0A
+                0x00 | 0x0A => {
+                    let bank_number = ram_bank_number & ram_bank_bit_mask;
+                    RamMapResult::RamAddress(
+                        u32::from(relative_address) + (u32::from(bank_number) << 13),
+                    )
+                }
+                // 0x0B-0x0D are RTC registers and 0x0E is the IR port
+                0x0B..=0x0E => RamMapResult::MapperRegister,
+                _ => RamMapResult::None,
+            },
+            // TAMA5 RAM is only accessible through the register window
+            Self::TAMA5 { .. } => RamMapResult::MapperRegister,
+            &Self::PocketCamera { ram_bank_bit_mask, ram_enable, ram_bank_number, .. } => {
+                if ram_bank_number & 0x10 != 0 {
+                    RamMapResult::MapperRegister
+                } else if ram_enable & 0x0A == 0x0A {
+                    let bank_number = ram_bank_number & ram_bank_bit_mask;
+                    RamMapResult::RamAddress(
+                        u32::from(relative_address) + (u32::from(bank_number) << 13),
+                    )
+                } else {
+                    RamMapResult::None
+                }
+            }
+            Self::WisdomTree { .. } | Self::Sachen { .. } => RamMapResult::None,
+        }
+    }
+
+    /// Returns true if the given ROM address is currently mapped to flash memory rather than ROM.
+    /// Only MBC6 cartridges have flash memory.
+    pub(crate) fn is_flash_address(&self, address: u16) -> bool {
+        match self {
+            Self::MBC6 { flash_selected, .. } => {
+                (0x4000..=0x7FFF).contains(&address) && flash_selected[mbc6_window(address)]
+            }
+            _ => false,
+        }
+    }
+
+    /// Read from flash memory at the given ROM address, if that address is currently mapped to
+    /// flash memory.
+    pub(crate) fn read_flash_address(&self, address: u16) -> Option<u8> {
+        match self {
+            Self::MBC6 { rom_bank_numbers, flash_enable, flash, .. }
+                if self.is_flash_address(address) =>
+            {
+                if *flash_enable {
+                    let window = mbc6_window(address);
+                    Some(flash.handle_read(mbc6_flash_address(rom_bank_numbers[window], address)))
+                } else {
+                    Some(0xFF)
+                }
+            }
+            _ => None,
         }
     }
 
@@ -433,6 +1047,17 @@ impl Mapper {
                     _ => None,
                 }
             }
+            // IR port; bit 0 is set when light is detected, and there is never anything sending
+            Self::HuC1 { .. } => Some(0xC0),
+            Self::HuC3 { ram_mode, rtc, .. } => match ram_mode {
+                0x0C => Some(rtc.read_response()),
+                // Commands execute instantly, so the RTC is always ready
+                0x0D => Some(0xFF),
+                0x0E => Some(0xC0),
+                _ => None,
+            },
+            Self::TAMA5 { tama5, .. } => Some(tama5.handle_read(address)),
+            Self::PocketCamera { camera, .. } => Some(camera.handle_read(address)),
             _ => None,
         }
     }
@@ -461,32 +1086,121 @@ impl Mapper {
                 }
                 _ => {}
             },
+            Self::HuC3 { ram_mode, rtc, rtc_emulated_time, .. } => match ram_mode {
+                0x0B => rtc.write_command(value),
+                0x0D => rtc.write_semaphore(value, rtc_now(rtc_emulated_time)),
+                _ => {}
+            },
+            Self::TAMA5 { rom_bank_number, tama5, rtc_emulated_time, .. } => {
+                tama5.handle_write(address, value, rtc_now(rtc_emulated_time));
+                *rom_bank_number = tama5.rom_bank_number();
+            }
+            Self::PocketCamera { camera, image_source, .. } => {
+                let mut image_source = image_source.borrow_mut();
+                match image_source.as_deref_mut() {
+                    Some(image_source) => camera.handle_write(address, value, image_source),
+                    None => camera.handle_write(address, value, &mut BlankImageSource),
+                }
+            }
             _ => {}
         }
     }
 
     pub(crate) fn update_rtc(&mut self) {
-        let Self::MBC3 { real_time_clock: Some(real_time_clock), .. } = self else {
-            return;
-        };
-        real_time_clock.update(SystemTime::now());
+        match self {
+            Self::MBC3 { real_time_clock: Some(real_time_clock), rtc_emulated_time, .. } => {
+                real_time_clock.update(rtc_now(rtc_emulated_time));
+            }
+            Self::HuC3 { rtc, rtc_emulated_time, .. } => {
+                rtc.update(rtc_now(rtc_emulated_time));
+            }
+            Self::TAMA5 { tama5, rtc_emulated_time, .. } => {
+                tama5.update_clock(rtc_now(rtc_emulated_time));
+            }
+            _ => {}
+        }
     }
 
-    pub(crate) fn get_clock(&self) -> Option<&RealTimeClock> {
+    /// Reset battery-backed memory and real-time clocks that are part of the mapper to the state of
+    /// a cartridge without a save file. Clocks restart from zero at the current RTC time.
+    pub(crate) fn reset_save_data(&mut self) {
         match self {
-            Self::MBC3 { real_time_clock, .. } => real_time_clock.as_ref(),
-            _ => None,
+            Self::MBC3 { real_time_clock: Some(real_time_clock), rtc_emulated_time, .. } => {
+                *real_time_clock = RealTimeClock::new(rtc_now(rtc_emulated_time));
+            }
+            Self::MBC6 { flash, .. } => {
+                *flash = Mbc6Flash::new(None);
+            }
+            Self::MBC7 { eeprom, .. } => {
+                *eeprom = Mbc7Eeprom::new(None);
+            }
+            Self::HuC3 { rtc, rtc_emulated_time, .. } => {
+                *rtc = Huc3Rtc::new(None, rtc_now(rtc_emulated_time));
+            }
+            Self::TAMA5 { tama5, rtc_emulated_time, .. } => {
+                *tama5 = Tama5::new(None, None, rtc_now(rtc_emulated_time));
+            }
+            _ => {}
         }
     }
 
-    /// Get a reference to the mapper chip's raw EEPROM, if any (only MBC7 mapper has such a chip)
+    /// Serialize the real-time clock state for persisting to disk, if the mapper has a clock
+    pub(crate) fn serialize_rtc(&self) -> Option<Vec<u8>> {
+        let rtc_bytes = match self {
+            Self::MBC3 { real_time_clock: Some(real_time_clock), .. } => {
+                bincode::serialize(real_time_clock)
+            }
+            Self::HuC3 { rtc, .. } => bincode::serialize(rtc.clock()),
+            Self::TAMA5 { tama5, .. } => bincode::serialize(tama5.clock()),
+            _ => return None,
+        };
+        Some(rtc_bytes.expect("RTC value-to-bytes serialization should never fail"))
+    }
+
+    /// Mix any audio that the cartridge itself produces into the given audio samples. Only the
+    /// HuC-3 mapper has a speaker.
+    pub(crate) fn mix_audio(&mut self, samples: &mut [f32]) {
+        if let Self::HuC3 { rtc, .. } = self {
+            rtc.mix_tone(samples);
+        }
+    }
+
+    /// Get a reference to the mapper chip's raw EEPROM, if any (only MBC7 and TAMA5 mappers have
+    /// such a chip)
     pub(crate) fn get_eeprom_memory(&self) -> Option<&[u8]> {
         match self {
             Self::MBC7 { eeprom, .. } => Some(eeprom.raw_memory()),
+            Self::TAMA5 { tama5, .. } => Some(tama5.eeprom().raw_memory()),
+            _ => None,
+        }
+    }
+
+    /// Get a reference to the cartridge's raw flash memory, if any (only MBC6 cartridges have
+    /// flash memory)
+    pub(crate) fn get_flash_memory(&self) -> Option<&[u8]> {
+        match self {
+            Self::MBC6 { flash, .. } => Some(flash.raw_memory()),
+            _ => None,
+        }
+    }
+
+    /// Take the image produced by the most recent Pocket Camera capture, if there is one that has
+    /// not been copied into cartridge RAM yet.
+    pub(crate) fn take_camera_image(&mut self) -> Option<Vec<u8>> {
+        match self {
+            Self::PocketCamera { camera, .. } => camera.take_captured_image(),
             _ => None,
         }
     }
 
+    /// Signal that the boot sequence has finished, either because the boot ROM unmapped itself or
+    /// because there is no boot ROM. Sachen mappers use this to stop redirecting header reads.
+    pub(crate) fn end_boot_sequence(&mut self) {
+        if let Self::Sachen { locked, .. } = self {
+            *locked = false;
+        }
+    }
+
     pub(crate) fn move_unserializable_fields_from(&mut self, other: Self) {
         match (self, other) {
             (
@@ -501,6 +1215,26 @@ impl Mapper {
             ) => {
                 *live_accelerometer_state = other_live_accelerometer_state;
             }
+            (
+                Self::MBC3 { rtc_emulated_time, .. },
+                Self::MBC3 { rtc_emulated_time: other_rtc_emulated_time, .. },
+            )
+            | (
+                Self::HuC3 { rtc_emulated_time, .. },
+                Self::HuC3 { rtc_emulated_time: other_rtc_emulated_time, .. },
+            )
+            | (
+                Self::TAMA5 { rtc_emulated_time, .. },
+                Self::TAMA5 { rtc_emulated_time: other_rtc_emulated_time, .. },
+            ) => {
+                *rtc_emulated_time = other_rtc_emulated_time;
+            }
+            (
+                Self::PocketCamera { image_source, .. },
+                Self::PocketCamera { image_source: other_image_source, .. },
+            ) => {
+                *image_source = other_image_source;
+            }
             _ => {}
         }
     }
@@ -524,6 +1258,118 @@ impl std::fmt::Display for MapperFeatures {
     }
 }
 
+// MBC1M multicarts are 1MB and contain 4 256KB games, each of which starts with a standard
+// cartridge header. They use the same mapper bytes as regular MBC1 cartridges.
+const MBC1M_ROM_LEN: usize = 1 << 20;
+const MBC1M_GAME_LEN: usize = 1 << 18;
+
+/// Determine whether an MBC1 cartridge is actually an MBC1M multicart by checking whether the
+/// second game in the ROM has a copy of the Nintendo logo.
+pub(crate) fn is_mbc1_multicart(rom: &[u8]) -> bool {
+    if rom.len() != MBC1M_ROM_LEN {
+        return false;
+    }
+
+    let logo_range = address::NINTENDO_LOGO_START as usize..=address::NINTENDO_LOGO_END as usize;
+    let second_game_logo_range =
+        MBC1M_GAME_LEN + logo_range.start()..=MBC1M_GAME_LEN + logo_range.end();
+    rom[logo_range] == rom[second_game_logo_range]
+}
+
+// The header for the MMM01 menu is in the last 32KB of ROM, which is what is mapped at power-on
+const MMM01_MENU_LEN: usize = 1 << 15;
+
+/// If the ROM looks like an MMM01 multicart, return the offset of the menu's cartridge header.
+/// The header at the start of the ROM belongs to the first game in this case.
+pub(crate) fn mmm01_header_offset(rom: &[u8]) -> Option<usize> {
+    let offset = rom.len().checked_sub(MMM01_MENU_LEN).filter(|&offset| offset != 0)?;
+    matches!(rom[offset + address::MAPPER as usize], 0x0B..=0x0D).then_some(offset)
+}
+
+// Returns 0 for the 8KB window at 0x4000 and 1 for the 8KB window at 0x6000
+fn mbc6_window(address: u16) -> usize {
+    usize::from(address >= 0x6000)
+}
+
+fn mbc6_flash_address(bank_number: u8, address: u16) -> u32 {
+    (u32::from(bank_number) << 13) | u32::from(address & 0x1FFF)
+}
+
+const NINTENDO_LOGO: [u8; 48] = [
+    0xCE, 0xED, 0x66, 0x66, 0xCC, 0x0D, 0x00, 0x0B, 0x03, 0x73, 0x00, 0x83, 0x00, 0x0C, 0x00, 0x0D,
+    0x00, 0x08, 0x11, 0x1F, 0x88, 0x89, 0x00, 0x0E, 0xDC, 0xCC, 0x6E, 0xE6, 0xDD, 0xDD, 0xD9, 0x99,
+    0xBB, 0xBB, 0x67, 0x63, 0x6E, 0x0E, 0xEC, 0xCC, 0xDD, 0xDC, 0x99, 0x9F, 0xBB, 0xB9, 0x33, 0x3E,
+];
+
+// The largest ROM that MBC1 and MBC3 can address. Larger ROMs that claim to use one of these
+// mappers are bootlegs that actually use an MBC5-like mapper
+const MBC1_MBC3_MAX_ROM_LEN: usize = 1 << 21;
+
+// Sachen mappers force A7 high for header reads while locked so that the boot ROM sees the
+// Nintendo logo stored at 0x0184 rather than the Sachen logo at 0x0104. MMC2 additionally swaps
+// address lines A0/A6 and A1/A4.
+fn sachen_locked_header_address(address: u16, mmc2: bool) -> u16 {
+    let address = address | 0x0080;
+    if !mmc2 {
+        return address;
+    }
+
+    let bit = |n: u16| (address >> n) & 0x01;
+    (address & !0x0053) | (bit(0) << 6) | bit(6) | (bit(1) << 4) | (bit(4) << 1)
+}
+
+fn has_sachen_logo(rom: &[u8], mmc2: bool) -> bool {
+    (address::NINTENDO_LOGO_START..=address::NINTENDO_LOGO_END).zip(NINTENDO_LOGO).all(
+        |(address, logo_byte)| {
+            rom.get(sachen_locked_header_address(address, mmc2) as usize) == Some(&logo_byte)
+        },
+    )
+}
+
+/// Detect cartridges that use unlicensed mappers. These cartridges either have invalid mapper
+/// bytes in the header or claim to use a licensed mapper that they do not actually use, so
+/// detection is based on other header contents and the ROM size.
+pub(crate) fn detect_unlicensed_mapper(rom: &[u8]) -> Option<(MapperType, MapperFeatures)> {
+    let no_features =
+        MapperFeatures { has_ram: false, has_battery: false, has_rtc: false, has_rumble: false };
+
+    let logo_range = address::NINTENDO_LOGO_START as usize..=address::NINTENDO_LOGO_END as usize;
+    if rom[logo_range] != NINTENDO_LOGO {
+        // Sachen cartridges store the Nintendo logo in a location that is only visible while the
+        // mapper is locked
+        if has_sachen_logo(rom, false) {
+            return Some((MapperType::SachenMMC1, no_features));
+        }
+        if has_sachen_logo(rom, true) {
+            return Some((MapperType::SachenMMC2, no_features));
+        }
+    }
+
+    let mapper_byte = rom[address::MAPPER as usize];
+    match mapper_byte {
+        // Wisdom Tree cartridges claim to have no mapper (or use the invalid byte 0xC0), but no
+        // cartridge without a mapper can be larger than 32KB
+        0x00 | 0xC0 if rom.len() > 1 << 15 => Some((MapperType::WisdomTree, no_features)),
+        0x01..=0x03 | 0x0F..=0x13 if rom.len() > MBC1_MBC3_MAX_ROM_LEN => {
+            let (_, features) = parse_byte(mapper_byte)?;
+            Some((MapperType::MBC5Bootleg, MapperFeatures { has_rtc: false, ..features }))
+        }
+        _ => None,
+    }
+}
+
+// The current time for cartridge real-time clocks: the emulated time if one is set (while
+// recording or playing back a movie), otherwise the system time
+fn rtc_now(emulated_time: &RefCell<Option<SystemTime>>) -> SystemTime {
+    emulated_time.borrow().unwrap_or_else(SystemTime::now)
+}
+
+fn deserialize_rtc<T: DeserializeOwned>(rtc_bytes: Option<&[u8]>) -> Option<T> {
+    bincode::deserialize(rtc_bytes?)
+        .map_err(|err| log::warn!("error deserializing previous RTC, resetting: {err}"))
+        .ok()
+}
+
 pub(crate) fn parse_byte(mapper_byte: u8) -> Option<(MapperType, MapperFeatures)> {
     let (mapper_type, has_ram, has_battery) = match mapper_byte {
         0x00 => (MapperType::None, false, false),
@@ -532,6 +1378,9 @@ pub(crate) fn parse_byte(mapper_byte: u8) -> Option<(MapperType, MapperFeatures)
         0x03 => (MapperType::MBC1, true, true),
         0x05 => (MapperType::MBC2, true, false),
         0x06 => (MapperType::MBC2, true, true),
+        0x0B => (MapperType::MMM01, false, false),
+        0x0C => (MapperType::MMM01, true, false),
+        0x0D => (MapperType::MMM01, true, true),
         0x0F => (MapperType::MBC3, false, true),
         // 0x10 is w/ RTC, 0x13 is w/o RTC
         0x10 | 0x13 => (MapperType::MBC3, true, true),
@@ -543,11 +1392,16 @@ pub(crate) fn parse_byte(mapper_byte: u8) -> Option<(MapperType, MapperFeatures)
         0x1A | 0x1D => (MapperType::MBC5, true, false),
         // 0x1B is w/o rumble, 0x1E is w/ rumble
         0x1B | 0x1E => (MapperType::MBC5, true, true),
+        0x20 => (MapperType::MBC6, true, true),
         0x22 => (MapperType::MBC7, true, true),
+        0xFC => (MapperType::PocketCamera, true, true),
+        0xFD => (MapperType::TAMA5, true, true),
+        0xFE => (MapperType::HuC3, true, true),
+        0xFF => (MapperType::HuC1, true, true),
         _ => return None,
     };
 
-    let has_rtc = mapper_byte == 0x0F || mapper_byte == 0x10;
+    let has_rtc = [0x0F, 0x10, 0xFD, 0xFE].contains(&mapper_byte);
 
     let has_rumble = [0x1C, 0x1D, 0x1E].contains(&mapper_byte);
 
@@ -647,6 +1501,81 @@ mod tests {
         assert_eq!(0x104000, mapper.map_rom_address(0x4000));
         assert_eq!(0x105234, mapper.map_rom_address(0x5234));
         assert_eq!(0x107FFF, mapper.map_rom_address(0x7FFF));
+
+        // RAM bank number should still apply to 0x4000-0x7FFF in banking mode 0
+        mapper.write_rom_address(0x6000, 0x00);
+        mapper.write_rom_address(0x2000, 0x05);
+
+        assert_eq!(0x0000, mapper.map_rom_address(0x0000));
+        assert_eq!(0x3FFF, mapper.map_rom_address(0x3FFF));
+        assert_eq!(0x114000, mapper.map_rom_address(0x4000));
+        assert_eq!(0x117FFF, mapper.map_rom_address(0x7FFF));
+    }
+
+    #[test]
+    fn mbc1m_mapper_rom() {
+        // 1MB ROM
+        let mut mapper = Mapper::new(
+            MapperType::MBC1M,
+            mapper_features(),
+            None,
+            1 << 20,
+            0,
+            None,
+            ControllerStates::default(),
+        );
+
+        assert_eq!(0x0000, mapper.map_rom_address(0x0000));
+        assert_eq!(0x4000, mapper.map_rom_address(0x4000));
+
+        // Only the lowest 4 bits of the ROM bank number are connected
+        mapper.write_rom_address(0x2000, 0x15);
+
+        assert_eq!(0x0000, mapper.map_rom_address(0x0000));
+        assert_eq!(0x14000, mapper.map_rom_address(0x4000));
+        assert_eq!(0x17FFF, mapper.map_rom_address(0x7FFF));
+
+        // 0x10 is not treated as 0x00, so this maps to bank 0 rather than bank 1
+        mapper.write_rom_address(0x2000, 0x10);
+
+        assert_eq!(0x0000, mapper.map_rom_address(0x4000));
+
+        // Set banking select mode and RAM bank number to select the third game
+        mapper.write_rom_address(0x6000, 0x01);
+        mapper.write_rom_address(0x4000, 0x02);
+        mapper.write_rom_address(0x2000, 0x03);
+
+        assert_eq!(0x80000, mapper.map_rom_address(0x0000));
+        assert_eq!(0x83FFF, mapper.map_rom_address(0x3FFF));
+        assert_eq!(0x8C000, mapper.map_rom_address(0x4000));
+        assert_eq!(0x8FFFF, mapper.map_rom_address(0x7FFF));
+
+        // Banking mode 0 should only affect 0x0000-0x3FFF
+        mapper.write_rom_address(0x6000, 0x00);
+
+        assert_eq!(0x0000, mapper.map_rom_address(0x0000));
+        assert_eq!(0x3FFF, mapper.map_rom_address(0x3FFF));
+        assert_eq!(0x8C000, mapper.map_rom_address(0x4000));
+        assert_eq!(0x8FFFF, mapper.map_rom_address(0x7FFF));
+    }
+
+    #[test]
+    fn mbc1m_detection() {
+        let logo_start = address::NINTENDO_LOGO_START as usize;
+        let logo_end = address::NINTENDO_LOGO_END as usize;
+
+        let mut rom = vec![0; 1 << 20];
+        for (i, byte) in rom[logo_start..=logo_end].iter_mut().enumerate() {
+            *byte = i as u8;
+        }
+        assert!(!is_mbc1_multicart(&rom));
+
+        rom.copy_within(logo_start..=logo_end, (1 << 18) + logo_start);
+        assert!(is_mbc1_multicart(&rom));
+
+        // Regular MBC1 cartridges larger than 1MB are never multicarts
+        rom.resize(1 << 21, 0);
+        assert!(!is_mbc1_multicart(&rom));
     }
 
     #[test]
@@ -669,4 +1598,448 @@ mod tests {
         assert_eq!(RamMapResult::RamAddress(0x1000), mapper.map_ram_address(0xB000));
         assert_eq!(RamMapResult::RamAddress(0x1234), mapper.map_ram_address(0xB234));
     }
+
+    #[test]
+    fn mmm01_mapper_rom() {
+        // 1MB ROM
+        let mut mapper = Mapper::new(
+            MapperType::MMM01,
+            mapper_features(),
+            None,
+            1 << 20,
+            0,
+            None,
+            ControllerStates::default(),
+        );
+
+        // Last 32KB should be mapped at power-on, regardless of the ROM bank number
+        assert_eq!(0xF8000, mapper.map_rom_address(0x0000));
+        assert_eq!(0xFBFFF, mapper.map_rom_address(0x3FFF));
+        assert_eq!(0xFC000, mapper.map_rom_address(0x4000));
+        assert_eq!(0xFFFFF, mapper.map_rom_address(0x7FFF));
+
+        mapper.write_rom_address(0x2000, 0x05);
+        assert_eq!(0xF8000, mapper.map_rom_address(0x0000));
+        assert_eq!(0xFC000, mapper.map_rom_address(0x4000));
+
+        // Select a 128KB game starting at bank 0x28, locking ROM bank number bits 3-4
+        mapper.write_rom_address(0x2000, 0x28);
+        mapper.write_rom_address(0x6000, 0x30);
+        mapper.write_rom_address(0x0000, 0x40);
+
+        assert_eq!(0xA0000, mapper.map_rom_address(0x0000));
+        assert_eq!(0xA3FFF, mapper.map_rom_address(0x3FFF));
+        // Bank 0 within the game should be treated as bank 1
+        assert_eq!(0xA4000, mapper.map_rom_address(0x4000));
+        assert_eq!(0xA7FFF, mapper.map_rom_address(0x7FFF));
+
+        mapper.write_rom_address(0x2000, 0x03);
+        assert_eq!(0xA0000, mapper.map_rom_address(0x0000));
+        assert_eq!(0xAC000, mapper.map_rom_address(0x4000));
+
+        // The game should not be able to modify locked bits or unmap itself
+        mapper.write_rom_address(0x2000, 0x1F);
+        assert_eq!(0xBC000, mapper.map_rom_address(0x4000));
+        mapper.write_rom_address(0x2000, 0x67);
+        mapper.write_rom_address(0x0000, 0x00);
+        assert_eq!(0xA0000, mapper.map_rom_address(0x0000));
+        assert_eq!(0xBC000, mapper.map_rom_address(0x4000));
+    }
+
+    #[test]
+    fn mmm01_mapper_ram() {
+        // 1MB ROM, 32KB RAM
+        let mut mapper = Mapper::new(
+            MapperType::MMM01,
+            mapper_features(),
+            None,
+            1 << 20,
+            32768,
+            None,
+            ControllerStates::default(),
+        );
+
+        assert_eq!(RamMapResult::None, mapper.map_ram_address(0xA000));
+
+        // Select RAM bank 2 and lock RAM bank number bit 1, then map and enable RAM
+        mapper.write_rom_address(0x4000, 0x02);
+        mapper.write_rom_address(0x0000, 0x6A);
+
+        assert_eq!(RamMapResult::RamAddress(0x4000), mapper.map_ram_address(0xA000));
+        assert_eq!(RamMapResult::RamAddress(0x5234), mapper.map_ram_address(0xB234));
+
+        // Only bit 0 should be writable now
+        mapper.write_rom_address(0x4000, 0x01);
+        assert_eq!(RamMapResult::RamAddress(0x6000), mapper.map_ram_address(0xA000));
+
+        mapper.write_rom_address(0x0000, 0x00);
+        assert_eq!(RamMapResult::None, mapper.map_ram_address(0xA000));
+    }
+
+    #[test]
+    fn mmm01_serialization() {
+        let mut mapper = Mapper::new(
+            MapperType::MMM01,
+            mapper_features(),
+            None,
+            1 << 20,
+            0,
+            None,
+            ControllerStates::default(),
+        );
+
+        mapper.write_rom_address(0x2000, 0x28);
+        mapper.write_rom_address(0x6000, 0x30);
+        mapper.write_rom_address(0x0000, 0x40);
+        mapper.write_rom_address(0x2000, 0x03);
+
+        let serialized = bincode::serialize(&mapper).unwrap();
+        let deserialized: Mapper = bincode::deserialize(&serialized).unwrap();
+
+        assert_eq!(0xA0000, deserialized.map_rom_address(0x0000));
+        assert_eq!(0xAC000, deserialized.map_rom_address(0x4000));
+    }
+
+    #[test]
+    fn mmm01_header_detection() {
+        let mut rom = vec![0; 1 << 20];
+        rom[address::MAPPER as usize] = 0x01;
+        assert_eq!(None, mmm01_header_offset(&rom));
+
+        rom[0xF8000 + address::MAPPER as usize] = 0x0D;
+        assert_eq!(Some(0xF8000), mmm01_header_offset(&rom));
+
+        // A 32KB ROM only has one header
+        let mut rom = vec![0; 1 << 15];
+        rom[address::MAPPER as usize] = 0x0B;
+        assert_eq!(None, mmm01_header_offset(&rom));
+    }
+
+    #[test]
+    fn huc1_mapper() {
+        // 1MB ROM, 32KB RAM
+        let mut mapper = Mapper::new(
+            MapperType::HuC1,
+            mapper_features(),
+            None,
+            1 << 20,
+            32768,
+            None,
+            ControllerStates::default(),
+        );
+
+        mapper.write_rom_address(0x2000, 0x25);
+        assert_eq!(0x0000, mapper.map_rom_address(0x0000));
+        assert_eq!(0x94000, mapper.map_rom_address(0x4000));
+
+        // RAM is accessible without enabling it first
+        mapper.write_rom_address(0x4000, 0x02);
+        assert_eq!(RamMapResult::RamAddress(0x5234), mapper.map_ram_address(0xB234));
+
+        // IR mode
+        mapper.write_rom_address(0x0000, 0x0E);
+        assert_eq!(RamMapResult::MapperRegister, mapper.map_ram_address(0xA000));
+        assert_eq!(Some(0xC0), mapper.read_ram_addressed_register(0xA000));
+
+        mapper.write_rom_address(0x0000, 0x0A);
+        assert_eq!(RamMapResult::RamAddress(0x4000), mapper.map_ram_address(0xA000));
+    }
+
+    #[test]
+    fn huc3_mapper() {
+        // 2MB ROM, 32KB RAM
+        let mut mapper = Mapper::new(
+            MapperType::HuC3,
+            mapper_features(),
+            None,
+            1 << 21,
+            32768,
+            None,
+            ControllerStates::default(),
+        );
+
+        mapper.write_rom_address(0x2000, 0x45);
+        assert_eq!(0x114000, mapper.map_rom_address(0x4000));
+
+        mapper.write_rom_address(0x4000, 0x03);
+        assert_eq!(RamMapResult::RamAddress(0x6000), mapper.map_ram_address(0xA000));
+        mapper.write_rom_address(0x0000, 0x0A);
+        assert_eq!(RamMapResult::RamAddress(0x6000), mapper.map_ram_address(0xA000));
+
+        // Request the RTC status through the command/semaphore/response registers
+        mapper.write_rom_address(0x0000, 0x0B);
+        mapper.write_ram_addressed_register(0xA000, 0x62);
+        mapper.write_rom_address(0x0000, 0x0D);
+        assert_eq!(Some(0xFF), mapper.read_ram_addressed_register(0xA000));
+        mapper.write_ram_addressed_register(0xA000, 0xFE);
+        mapper.write_rom_address(0x0000, 0x0C);
+        assert_eq!(Some(0xE1), mapper.read_ram_addressed_register(0xA000));
+
+        // RTC state should be persisted
+        assert!(mapper.serialize_rtc().is_some());
+    }
+
+    #[test]
+    fn tama5_mapper() {
+        // 512KB ROM
+        let mut mapper = Mapper::new(
+            MapperType::TAMA5,
+            mapper_features(),
+            None,
+            1 << 19,
+            32,
+            None,
+            ControllerStates::default(),
+        );
+
+        assert_eq!(0x0000, mapper.map_rom_address(0x4000));
+
+        // Select ROM bank 0x12 through the register window
+        assert_eq!(RamMapResult::MapperRegister, mapper.map_ram_address(0xA000));
+        mapper.write_ram_addressed_register(0xA001, 0x00);
+        mapper.write_ram_addressed_register(0xA000, 0x02);
+        mapper.write_ram_addressed_register(0xA001, 0x01);
+        mapper.write_ram_addressed_register(0xA000, 0x01);
+
+        assert_eq!(0x0000, mapper.map_rom_address(0x0000));
+        assert_eq!(0x48000, mapper.map_rom_address(0x4000));
+
+        assert_eq!(Some(&[0; 32][..]), mapper.get_eeprom_memory());
+        assert!(mapper.serialize_rtc().is_some());
+    }
+
+    #[test]
+    fn mbc6_mapper() {
+        let mut mapper = Mapper::new(
+            MapperType::MBC6,
+            mapper_features(),
+            None,
+            1 << 20,
+            1 << 15,
+            None,
+            ControllerStates::default(),
+        );
+
+        // The two 8KB ROM windows are banked independently
+        mapper.write_rom_address(0x2000, 0x05);
+        mapper.write_rom_address(0x3000, 0x7E);
+        assert_eq!(0x0000, mapper.map_rom_address(0x0000));
+        assert_eq!(0x3FFF, mapper.map_rom_address(0x3FFF));
+        assert_eq!(0x0A000, mapper.map_rom_address(0x4000));
+        assert_eq!(0x0BFFF, mapper.map_rom_address(0x5FFF));
+        assert_eq!(0xFC000, mapper.map_rom_address(0x6000));
+        assert_eq!(0xFDFFF, mapper.map_rom_address(0x7FFF));
+
+        // The two 4KB RAM windows are banked independently
+        assert_eq!(RamMapResult::None, mapper.map_ram_address(0xA000));
+        mapper.write_rom_address(0x0000, 0x0A);
+        mapper.write_rom_address(0x0400, 0x03);
+        mapper.write_rom_address(0x0800, 0x06);
+        assert_eq!(RamMapResult::RamAddress(0x3000), mapper.map_ram_address(0xA000));
+        assert_eq!(RamMapResult::RamAddress(0x6FFF), mapper.map_ram_address(0xBFFF));
+
+        // Map flash into the second window; reads return 0xFF until flash is enabled
+        mapper.write_rom_address(0x3800, 0x08);
+        assert!(!mapper.is_flash_address(0x4000));
+        assert!(mapper.is_flash_address(0x6000));
+        assert_eq!(None, mapper.read_flash_address(0x4000));
+        assert_eq!(Some(0xFF), mapper.read_flash_address(0x6000));
+
+        // Flash enable cannot be changed while flash writes are disabled
+        mapper.write_rom_address(0x0C00, 0x01);
+        mapper.write_rom_address(0x1000, 0x01);
+        mapper.write_rom_address(0x3000, 0x02);
+        mapper.write_rom_address(0x6000, 0x12);
+        assert!(mapper.get_flash_memory().unwrap().iter().all(|&byte| byte == 0xFF));
+
+        mapper.write_rom_address(0x0C00, 0x01);
+
+        // Program a byte using the unlock addresses 2:5555 and 1:4AAA
+        mapper.write_rom_address(0x2000, 0x01);
+        mapper.write_rom_address(0x2800, 0x08);
+        mapper.write_rom_address(0x7555, 0xAA);
+        mapper.write_rom_address(0x4AAA, 0x55);
+        mapper.write_rom_address(0x7555, 0xA0);
+        mapper.write_rom_address(0x6123, 0x34);
+
+        assert_eq!(Some(0x34), mapper.read_flash_address(0x6123));
+        assert_eq!(0x34, mapper.get_flash_memory().unwrap()[0x4123]);
+    }
+
+    #[test]
+    fn pocket_camera_mapper() {
+        let mut mapper = Mapper::new(
+            MapperType::PocketCamera,
+            mapper_features(),
+            None,
+            1 << 20,
+            1 << 17,
+            None,
+            ControllerStates::default(),
+        );
+
+        assert_eq!(0x4000, mapper.map_rom_address(0x4000));
+        mapper.write_rom_address(0x2000, 0x00);
+        assert_eq!(0x0000, mapper.map_rom_address(0x4000));
+        mapper.write_rom_address(0x2000, 0x3F);
+        assert_eq!(0xFC000, mapper.map_rom_address(0x4000));
+
+        assert_eq!(RamMapResult::None, mapper.map_ram_address(0xA000));
+        mapper.write_rom_address(0x0000, 0x0A);
+        mapper.write_rom_address(0x4000, 0x0F);
+        assert_eq!(RamMapResult::RamAddress(0x1E000), mapper.map_ram_address(0xA000));
+
+        // Bit 4 of the RAM bank number maps the camera registers
+        mapper.write_rom_address(0x4000, 0x10);
+        assert_eq!(RamMapResult::MapperRegister, mapper.map_ram_address(0xA000));
+        assert_eq!(Some(0x00), mapper.read_ram_addressed_register(0xA000));
+
+        // Without an image source, capturing should produce a blank image
+        assert_eq!(None, mapper.take_camera_image());
+        mapper.write_ram_addressed_register(0xA000, 0x01);
+        assert_eq!(Some(vec![0; 0x0E00]), mapper.take_camera_image());
+        assert_eq!(None, mapper.take_camera_image());
+    }
+
+    #[test]
+    fn wisdom_tree_mapper() {
+        let mut mapper = Mapper::new(
+            MapperType::WisdomTree,
+            mapper_features(),
+            None,
+            1 << 18,
+            0,
+            None,
+            ControllerStates::default(),
+        );
+
+        assert_eq!(0x0000, mapper.map_rom_address(0x0000));
+        assert_eq!(0x7FFF, mapper.map_rom_address(0x7FFF));
+
+        // The bank number is taken from the address, and the value is ignored
+        mapper.write_rom_address(0x0005, 0x00);
+        assert_eq!(0x28000, mapper.map_rom_address(0x0000));
+        assert_eq!(0x2FFFF, mapper.map_rom_address(0x7FFF));
+
+        mapper.write_rom_address(0x4002, 0x07);
+        assert_eq!(0x28000, mapper.map_rom_address(0x0000));
+
+        // ROM bank number should be masked to the ROM size
+        mapper.write_rom_address(0x000A, 0x00);
+        assert_eq!(0x10000, mapper.map_rom_address(0x0000));
+
+        assert_eq!(RamMapResult::None, mapper.map_ram_address(0xA000));
+    }
+
+    #[test]
+    fn sachen_mapper() {
+        let mut mapper = Mapper::new(
+            MapperType::SachenMMC1,
+            mapper_features(),
+            None,
+            1 << 20,
+            0,
+            None,
+            ControllerStates::default(),
+        );
+
+        // Header reads are redirected until the boot sequence ends
+        assert_eq!(0x0184, mapper.map_rom_address(0x0104));
+        assert_eq!(0x0000, mapper.map_rom_address(0x0000));
+        mapper.end_boot_sequence();
+        assert_eq!(0x0104, mapper.map_rom_address(0x0104));
+
+        assert_eq!(0x4000, mapper.map_rom_address(0x4000));
+        mapper.write_rom_address(0x2000, 0x05);
+        assert_eq!(0x14000, mapper.map_rom_address(0x4000));
+
+        // Base bank and mask can't be written unless bits 4-5 of the ROM bank number are set
+        mapper.write_rom_address(0x0000, 0x20);
+        mapper.write_rom_address(0x4000, 0x30);
+        assert_eq!(0x0000, mapper.map_rom_address(0x0000));
+
+        mapper.write_rom_address(0x2000, 0x31);
+        mapper.write_rom_address(0x0000, 0x20);
+        mapper.write_rom_address(0x4000, 0x30);
+        assert_eq!(0x80000, mapper.map_rom_address(0x0000));
+        assert_eq!(0x84000, mapper.map_rom_address(0x4000));
+
+        mapper.write_rom_address(0x2000, 0x07);
+        assert_eq!(0x9C000, mapper.map_rom_address(0x4000));
+
+        // MMC2 also scrambles address lines while locked
+        let mapper = Mapper::new(
+            MapperType::SachenMMC2,
+            mapper_features(),
+            None,
+            1 << 20,
+            0,
+            None,
+            ControllerStates::default(),
+        );
+        assert_eq!(0x01C0, mapper.map_rom_address(0x0101));
+        assert_eq!(0x0182, mapper.map_rom_address(0x0110));
+    }
+
+    #[test]
+    fn unlicensed_mapper_detection() {
+        let logo_start = address::NINTENDO_LOGO_START as usize;
+
+        let mut rom = vec![0; 1 << 16];
+        rom[logo_start..logo_start + NINTENDO_LOGO.len()].copy_from_slice(&NINTENDO_LOGO);
+        assert_eq!(
+            Some(MapperType::WisdomTree),
+            detect_unlicensed_mapper(&rom).map(|(mapper_type, _)| mapper_type)
+        );
+
+        // Licensed cartridges without a mapper are never larger than 32KB
+        rom.truncate(1 << 15);
+        assert_eq!(None, detect_unlicensed_mapper(&rom));
+
+        rom[address::MAPPER as usize] = 0x13;
+        assert_eq!(None, detect_unlicensed_mapper(&rom));
+        rom.resize(1 << 22, 0);
+        assert_eq!(
+            Some(MapperType::MBC5Bootleg),
+            detect_unlicensed_mapper(&rom).map(|(mapper_type, _)| mapper_type)
+        );
+
+        // Sachen cartridges have the Nintendo logo at 0x0184, scrambled in the case of MMC2
+        let mut rom = vec![0; 1 << 16];
+        for (address, logo_byte) in (0x0104..).zip(NINTENDO_LOGO) {
+            rom[usize::from(sachen_locked_header_address(address, false))] = logo_byte;
+        }
+        assert_eq!(
+            Some(MapperType::SachenMMC1),
+            detect_unlicensed_mapper(&rom).map(|(mapper_type, _)| mapper_type)
+        );
+
+        let mut rom = vec![0; 1 << 16];
+        for (address, logo_byte) in (0x0104..).zip(NINTENDO_LOGO) {
+            rom[usize::from(sachen_locked_header_address(address, true))] = logo_byte;
+        }
+        assert_eq!(
+            Some(MapperType::SachenMMC2),
+            detect_unlicensed_mapper(&rom).map(|(mapper_type, _)| mapper_type)
+        );
+    }
+
+    #[test]
+    fn mbc5_bootleg_mapper() {
+        let mut mapper = Mapper::new(
+            MapperType::MBC5Bootleg,
+            mapper_features(),
+            None,
+            1 << 22,
+            0,
+            None,
+            ControllerStates::default(),
+        );
+
+        // Games written for MBC1/MBC3 may write the ROM bank number anywhere in 0x2000-0x3FFF
+        mapper.write_rom_address(0x2000, 0x12);
+        assert_eq!(0x48000, mapper.map_rom_address(0x4000));
+        mapper.write_rom_address(0x3FFF, 0xC4);
+        assert_eq!(0x310000, mapper.map_rom_address(0x4000));
+    }
 }
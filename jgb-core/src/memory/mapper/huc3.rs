@@ -0,0 +1,302 @@
+use crate::apu::OUTPUT_FREQUENCY;
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, SystemTime};
+
+const MINUTES_PER_DAY: u16 = 24 * 60;
+// The day counter is 12 bits
+const DAYS_MASK: u16 = 0x0FFF;
+
+const NANOS_PER_MINUTE: u128 = 60 * 1_000_000_000;
+
+// Locations in RTC memory that are used to transfer the current time. Each location holds one
+// nibble, least significant nibble first
+const TIME_TRANSFER_MINUTES: usize = 0x00;
+const TIME_TRANSFER_DAYS: usize = 0x03;
+
+// Locations in RTC memory that configure the tone generator
+const TONE_ENABLE: usize = 0x26;
+const TONE_SELECT: usize = 0x27;
+
+// The actual waveforms of the cartridge speaker tones are not documented, so each tone is
+// approximated as a half-second square wave beep with a tone-dependent pitch
+const TONE_BASE_FREQUENCY: u64 = 1000;
+const TONE_DURATION_SAMPLES: u64 = OUTPUT_FREQUENCY / 2;
+const TONE_AMPLITUDE: f32 = 0.15;
+
+/// The persistent state of the HuC-3 real-time clock, which is saved to the .rtc file. This
+/// includes the RTC chip's general-purpose memory because games store alarm settings there.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) struct Huc3Clock {
+    last_update: SystemTime,
+    // Time elapsed since the current minute started
+    nanos: u64,
+    minutes: u16,
+    days: u16,
+    #[serde(
+        serialize_with = "crate::serialize::serialize_array",
+        deserialize_with = "crate::serialize::deserialize_array"
+    )]
+    memory: [u8; 256],
+}
+
+impl Huc3Clock {
+    pub(crate) fn new(now: SystemTime) -> Self {
+        Self { last_update: now, nanos: 0, minutes: 0, days: 0, memory: [0; 256] }
+    }
+
+    pub(crate) fn update(&mut self, now: SystemTime) {
+        let since = now.duration_since(self.last_update).unwrap_or_else(|err| {
+            log::error!(
+                "Time has gone backwards: last_update={:?}, now={now:?}: {err}",
+                self.last_update
+            );
+            Duration::from_secs(0)
+        });
+
+        self.last_update = now;
+
+        let nanos = u128::from(self.nanos) + since.as_nanos();
+        self.nanos = (nanos % NANOS_PER_MINUTE) as u64;
+
+        let minutes = u128::from(self.minutes) + nanos / NANOS_PER_MINUTE;
+        self.minutes = (minutes % u128::from(MINUTES_PER_DAY)) as u16;
+
+        let days = u128::from(self.days) + minutes / u128::from(MINUTES_PER_DAY);
+        self.days = (days & u128::from(DAYS_MASK)) as u16;
+    }
+
+    fn write_nibbles(&mut self, start: usize, value: u16) {
+        for (i, nibble) in self.memory[start..start + 3].iter_mut().enumerate() {
+            *nibble = ((value >> (4 * i)) & 0x0F) as u8;
+        }
+    }
+
+    fn read_nibbles(&self, start: usize) -> u16 {
+        self.memory[start..start + 3]
+            .iter()
+            .enumerate()
+            .map(|(i, &nibble)| u16::from(nibble) << (4 * i))
+            .sum()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+struct Tone {
+    frequency: u64,
+    samples_remaining: u64,
+    phase: u64,
+}
+
+/// The HuC-3 RTC chip's command interface, plus the cartridge speaker that it controls.
+///
+/// Commands are written as a single byte with the command in the high nibble and the argument in
+/// the low nibble, and they are executed when the game writes to the semaphore register.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) struct Huc3Rtc {
+    clock: Huc3Clock,
+    address: u8,
+    command: u8,
+    response: u8,
+    tone: Option<Tone>,
+}
+
+impl Huc3Rtc {
+    pub(crate) fn new(clock: Option<Huc3Clock>, now: SystemTime) -> Self {
+        let clock = match clock {
+            Some(mut clock) => {
+                clock.update(now);
+                clock
+            }
+            None => Huc3Clock::new(now),
+        };
+
+        Self { clock, address: 0, command: 0, response: 0, tone: None }
+    }
+
+    pub(crate) fn clock(&self) -> &Huc3Clock {
+        &self.clock
+    }
+
+    pub(crate) fn update(&mut self, now: SystemTime) {
+        self.clock.update(now);
+    }
+
+    pub(crate) fn write_command(&mut self, value: u8) {
+        self.command = value;
+    }
+
+    pub(crate) fn read_response(&self) -> u8 {
+        0x80 | (self.command & 0x70) | self.response
+    }
+
+    pub(crate) fn write_semaphore(&mut self, value: u8, now: SystemTime) {
+        if value & 0x01 == 0 {
+            self.execute_command(now);
+        }
+    }
+
+    fn execute_command(&mut self, now: SystemTime) {
+        let argument = self.command & 0x0F;
+        match self.command >> 4 {
+            // Read from memory and increment address
+            0x1 => {
+                self.response = self.clock.memory[usize::from(self.address)];
+                self.address = self.address.wrapping_add(1);
+            }
+            // Write to memory and increment address
+            0x3 => {
+                self.clock.memory[usize::from(self.address)] = argument;
+                self.address = self.address.wrapping_add(1);
+            }
+            0x4 => {
+                self.address = (self.address & 0xF0) | argument;
+            }
+            0x5 => {
+                self.address = (self.address & 0x0F) | (argument << 4);
+            }
+            0x6 => self.execute_extended_command(argument, now),
+            _ => {
+                log::debug!("Ignoring unknown HuC-3 RTC command {:02X}", self.command);
+            }
+        }
+    }
+
+    fn execute_extended_command(&mut self, argument: u8, now: SystemTime) {
+        match argument {
+            // Copy current time to memory
+            0x0 => {
+                self.clock.update(now);
+                self.clock.write_nibbles(TIME_TRANSFER_MINUTES, self.clock.minutes);
+                self.clock.write_nibbles(TIME_TRANSFER_DAYS, self.clock.days);
+            }
+            // Set current time from memory
+            0x1 => {
+                self.clock.update(now);
+                self.clock.minutes =
+                    self.clock.read_nibbles(TIME_TRANSFER_MINUTES) % MINUTES_PER_DAY;
+                self.clock.days = self.clock.read_nibbles(TIME_TRANSFER_DAYS) & DAYS_MASK;
+                self.clock.nanos = 0;
+            }
+            // Status check; games expect this to return 1
+            0x2 => {
+                self.response = 0x1;
+            }
+            // Play a tone on the cartridge speaker
+            0xE => {
+                if self.clock.memory[TONE_ENABLE] == 0x1 {
+                    let tone = u64::from(self.clock.memory[TONE_SELECT] & 0x07);
+                    log::debug!("Playing HuC-3 tone {tone}");
+                    self.tone = Some(Tone {
+                        frequency: TONE_BASE_FREQUENCY + 250 * tone,
+                        samples_remaining: TONE_DURATION_SAMPLES,
+                        phase: 0,
+                    });
+                }
+            }
+            _ => {
+                log::debug!("Ignoring unknown HuC-3 RTC extended command {argument:X}");
+            }
+        }
+    }
+
+    /// Mix the cartridge speaker output into the given interleaved stereo samples.
+    pub(crate) fn mix_tone(&mut self, samples: &mut [f32]) {
+        let Some(tone) = &mut self.tone else { return };
+
+        for frame in samples.chunks_exact_mut(2) {
+            if tone.samples_remaining == 0 {
+                self.tone = None;
+                return;
+            }
+
+            let high = (tone.phase * tone.frequency * 2 / OUTPUT_FREQUENCY) & 1 == 0;
+            let sample = if high { TONE_AMPLITUDE } else { -TONE_AMPLITUDE };
+            for channel in frame {
+                *channel = (*channel + sample).clamp(-1.0, 1.0);
+            }
+
+            tone.phase = (tone.phase + 1) % OUTPUT_FREQUENCY;
+            tone.samples_remaining -= 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn execute(rtc: &mut Huc3Rtc, command: u8) -> u8 {
+        rtc.write_command(command);
+        rtc.write_semaphore(0xFE, SystemTime::UNIX_EPOCH);
+        rtc.read_response() & 0x0F
+    }
+
+    #[test]
+    fn clock_update() {
+        let start = SystemTime::UNIX_EPOCH;
+        let mut clock = Huc3Clock::new(start);
+
+        clock.update(start + Duration::from_secs(59));
+        assert_eq!((0, 0), (clock.minutes, clock.days));
+
+        clock.update(start + Duration::from_secs(61));
+        assert_eq!((1, 0), (clock.minutes, clock.days));
+
+        clock.update(start + Duration::from_secs(2 * 86400 + 3 * 60 + 1));
+        assert_eq!((3, 2), (clock.minutes, clock.days));
+
+        // Day counter should wrap at 4096
+        clock.update(start + Duration::from_secs(4097 * 86400 + 3 * 60 + 1));
+        assert_eq!((3, 1), (clock.minutes, clock.days));
+    }
+
+    #[test]
+    fn time_transfer() {
+        let mut rtc = Huc3Rtc::new(None, SystemTime::UNIX_EPOCH);
+
+        // Write 0x123 minutes and 0x456 days to the transfer area, then set the time from memory
+        execute(&mut rtc, 0x40);
+        execute(&mut rtc, 0x50);
+        for nibble in [0x3, 0x2, 0x1, 0x6, 0x5, 0x4] {
+            execute(&mut rtc, 0x30 | nibble);
+        }
+        execute(&mut rtc, 0x61);
+        assert_eq!((0x123, 0x456), (rtc.clock.minutes, rtc.clock.days));
+
+        // Clear memory, then copy the time back into memory and read it out
+        execute(&mut rtc, 0x40);
+        for _ in 0..6 {
+            execute(&mut rtc, 0x30);
+        }
+        execute(&mut rtc, 0x60);
+
+        execute(&mut rtc, 0x40);
+        let nibbles: Vec<_> = (0..6).map(|_| execute(&mut rtc, 0x10)).collect();
+        assert_eq!(vec![0x3, 0x2, 0x1, 0x6, 0x5, 0x4], nibbles);
+
+        assert_eq!(0x1, execute(&mut rtc, 0x62));
+    }
+
+    #[test]
+    fn tone() {
+        let mut rtc = Huc3Rtc::new(None, SystemTime::UNIX_EPOCH);
+
+        // Tone should not play unless enabled
+        execute(&mut rtc, 0x6E);
+        assert_eq!(None, rtc.tone);
+
+        execute(&mut rtc, 0x46);
+        execute(&mut rtc, 0x52);
+        execute(&mut rtc, 0x31);
+        execute(&mut rtc, 0x6E);
+
+        let mut samples = vec![0.0; 64];
+        rtc.mix_tone(&mut samples);
+        assert!(samples.iter().all(|&sample| (sample.abs() - TONE_AMPLITUDE).abs() < f32::EPSILON));
+
+        let mut samples = vec![0.0; 2 * TONE_DURATION_SAMPLES as usize];
+        rtc.mix_tone(&mut samples);
+        assert_eq!(None, rtc.tone);
+        assert!(samples.last().unwrap().abs() < f32::EPSILON);
+    }
+}
@@ -0,0 +1,176 @@
+use serde::{Deserialize, Serialize};
+
+/// MBC6 cartridges have 1MB of flash memory in addition to ROM and RAM.
+pub(crate) const FLASH_LEN: usize = 1 << 20;
+
+// Flash commands are recognized based on the low 15 bits of the flash address
+const COMMAND_ADDRESS_MASK: u32 = 0x7FFF;
+const UNLOCK_ADDRESS_1: u32 = 0x5555;
+const UNLOCK_ADDRESS_2: u32 = 0x2AAA;
+
+const SECTOR_LEN: usize = 1 << 17;
+
+const MANUFACTURER_ID: u8 = 0xC2;
+const DEVICE_ID: u8 = 0x81;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+enum FlashState {
+    Read,
+    // Received the first unlock cycle (AA)
+    Unlocked1,
+    // Received the second unlock cycle (55), next write is the command
+    Unlocked2,
+    // Reads return the manufacturer/device ID instead of memory contents
+    Autoselect,
+    // Next write programs a byte
+    Program,
+    // Received erase setup (80), waiting for another pair of unlock cycles
+    EraseSetup,
+    EraseUnlocked1,
+    EraseUnlocked2,
+}
+
+// Emulation of the MBC6 cartridge's Macronix MX29F008 flash chip
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct Mbc6Flash {
+    memory: Vec<u8>,
+    state: FlashState,
+}
+
+impl Mbc6Flash {
+    pub(crate) fn new(loaded_flash: Option<&[u8]>) -> Self {
+        let memory = match loaded_flash {
+            Some(loaded_flash) if loaded_flash.len() == FLASH_LEN => loaded_flash.to_vec(),
+            // Flash memory is all 1s when erased
+            _ => vec![0xFF; FLASH_LEN],
+        };
+
+        Self { memory, state: FlashState::Read }
+    }
+
+    pub(crate) fn handle_read(&self, flash_address: u32) -> u8 {
+        match self.state {
+            FlashState::Autoselect => {
+                if flash_address & 0x01 == 0 {
+                    MANUFACTURER_ID
+                } else {
+                    DEVICE_ID
+                }
+            }
+            // Program and erase operations complete instantly, so there is never any status to
+            // report
+            _ => self.memory[flash_address as usize % FLASH_LEN],
+        }
+    }
+
+    pub(crate) fn handle_write(&mut self, flash_address: u32, value: u8, write_enabled: bool) {
+        let command_address = flash_address & COMMAND_ADDRESS_MASK;
+
+        // Reset can be issued at any time except as the data for a program command
+        if value == 0xF0 && self.state != FlashState::Program {
+            self.state = FlashState::Read;
+            return;
+        }
+
+        self.state = match (self.state, command_address, value) {
+            (FlashState::Read | FlashState::Autoselect, UNLOCK_ADDRESS_1, 0xAA) => {
+                FlashState::Unlocked1
+            }
+            (FlashState::Unlocked1, UNLOCK_ADDRESS_2, 0x55) => FlashState::Unlocked2,
+            (FlashState::Unlocked2, UNLOCK_ADDRESS_1, 0x90) => FlashState::Autoselect,
+            (FlashState::Unlocked2, UNLOCK_ADDRESS_1, 0xA0) => FlashState::Program,
+            (FlashState::Unlocked2, UNLOCK_ADDRESS_1, 0x80) => FlashState::EraseSetup,
+            (FlashState::Program, _, _) => {
+                if write_enabled {
+                    // Programming can only change bits from 1 to 0
+                    self.memory[flash_address as usize % FLASH_LEN] &= value;
+                }
+                FlashState::Read
+            }
+            (FlashState::EraseSetup, UNLOCK_ADDRESS_1, 0xAA) => FlashState::EraseUnlocked1,
+            (FlashState::EraseUnlocked1, UNLOCK_ADDRESS_2, 0x55) => FlashState::EraseUnlocked2,
+            (FlashState::EraseUnlocked2, UNLOCK_ADDRESS_1, 0x10) => {
+                if write_enabled {
+                    log::debug!("Erasing MBC6 flash chip");
+                    self.memory.fill(0xFF);
+                }
+                FlashState::Read
+            }
+            (FlashState::EraseUnlocked2, _, 0x30) => {
+                if write_enabled {
+                    let sector_start = flash_address as usize % FLASH_LEN / SECTOR_LEN * SECTOR_LEN;
+                    log::debug!("Erasing MBC6 flash sector at {sector_start:05X}");
+                    self.memory[sector_start..sector_start + SECTOR_LEN].fill(0xFF);
+                }
+                FlashState::Read
+            }
+            (state, _, _) => {
+                log::debug!(
+                    "Unexpected MBC6 flash write in state {state:?}: {flash_address:05X} {value:02X}"
+                );
+                FlashState::Read
+            }
+        };
+    }
+
+    pub(crate) fn raw_memory(&self) -> &[u8] {
+        &self.memory
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn command(flash: &mut Mbc6Flash, command: u8) {
+        flash.handle_write(0x5555, 0xAA, true);
+        flash.handle_write(0x2AAA, 0x55, true);
+        flash.handle_write(0x5555, command, true);
+    }
+
+    #[test]
+    fn autoselect() {
+        let mut flash = Mbc6Flash::new(None);
+
+        command(&mut flash, 0x90);
+        assert_eq!(MANUFACTURER_ID, flash.handle_read(0x0000));
+        assert_eq!(DEVICE_ID, flash.handle_read(0x0001));
+
+        flash.handle_write(0x0000, 0xF0, true);
+        assert_eq!(0xFF, flash.handle_read(0x0000));
+    }
+
+    #[test]
+    fn program_and_erase() {
+        let mut flash = Mbc6Flash::new(None);
+
+        command(&mut flash, 0xA0);
+        flash.handle_write(0x23456, 0x5A, true);
+        assert_eq!(0x5A, flash.handle_read(0x23456));
+
+        // Programming cannot set bits
+        command(&mut flash, 0xA0);
+        flash.handle_write(0x23456, 0xA5, true);
+        assert_eq!(0x00, flash.handle_read(0x23456));
+
+        // Writes should be ignored if not enabled
+        command(&mut flash, 0xA0);
+        flash.handle_write(0x40000, 0x12, false);
+        assert_eq!(0xFF, flash.handle_read(0x40000));
+
+        command(&mut flash, 0xA0);
+        flash.handle_write(0x40000, 0x12, true);
+
+        // Sector erase should only erase the sector containing the address
+        command(&mut flash, 0x80);
+        flash.handle_write(0x5555, 0xAA, true);
+        flash.handle_write(0x2AAA, 0x55, true);
+        flash.handle_write(0x20000, 0x30, true);
+        assert_eq!(0xFF, flash.handle_read(0x23456));
+        assert_eq!(0x12, flash.handle_read(0x40000));
+
+        command(&mut flash, 0x80);
+        command(&mut flash, 0x10);
+        assert_eq!(0xFF, flash.handle_read(0x40000));
+    }
+}
@@ -0,0 +1,218 @@
+use crate::camera::{CAMERA_HEIGHT, CAMERA_WIDTH, CameraImageSource};
+use serde::{Deserialize, Serialize};
+
+/// The captured image is written to cartridge RAM bank 0 starting at this offset.
+pub(crate) const IMAGE_RAM_START: usize = 0x0100;
+// 16x14 tiles at 16 bytes per tile
+const IMAGE_LEN: usize = CAMERA_WIDTH * CAMERA_HEIGHT / 4;
+
+const REGISTERS_LEN: usize = 0x36;
+
+const CAPTURE_REGISTER: usize = 0x00;
+const EDGE_MODE_REGISTER: usize = 0x01;
+const EXPOSURE_HIGH_REGISTER: usize = 0x02;
+const EXPOSURE_LOW_REGISTER: usize = 0x03;
+const EDGE_RATIO_REGISTER: usize = 0x04;
+// 4x4 matrix of 3 thresholds each, used to dither sensor output down to 2bpp
+const DITHER_MATRIX_START: usize = 0x06;
+
+// An exposure setting that maps full source brightness to roughly full sensor output
+const REFERENCE_EXPOSURE: u32 = 0x0300;
+
+// Edge enhancement ratios in quarters: 50%, 75%, 100%, 125%, 200%, 300%, 400%, 500%
+const EDGE_RATIOS: [i32; 8] = [2, 3, 4, 5, 8, 12, 16, 20];
+
+/// The Pocket Camera's sensor registers plus its Mitsubishi M64282FP image sensor.
+///
+/// Captures complete instantly: writing 1 to bit 0 of the capture register takes an image from the
+/// image source, runs it through the sensor's exposure and edge enhancement stages, dithers it
+/// using the dither matrix registers, and produces tile data for the mapper to copy into RAM.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct PocketCamera {
+    #[serde(
+        serialize_with = "crate::serialize::serialize_array",
+        deserialize_with = "crate::serialize::deserialize_array"
+    )]
+    registers: [u8; REGISTERS_LEN],
+    captured_image: Option<Vec<u8>>,
+}
+
+impl PocketCamera {
+    pub(crate) fn new() -> Self {
+        Self { registers: [0; REGISTERS_LEN], captured_image: None }
+    }
+
+    pub(crate) fn handle_read(&self, address: u16) -> u8 {
+        // Only the capture register is readable. Since captures complete instantly, the busy bit
+        // is never set
+        match usize::from(address & 0x007F) {
+            CAPTURE_REGISTER => self.registers[CAPTURE_REGISTER] & 0x06,
+            _ => 0x00,
+        }
+    }
+
+    pub(crate) fn handle_write(
+        &mut self,
+        address: u16,
+        value: u8,
+        image_source: &mut dyn CameraImageSource,
+    ) {
+        let register = usize::from(address & 0x007F);
+        if register >= REGISTERS_LEN {
+            return;
+        }
+
+        self.registers[register] = value;
+
+        if register == CAPTURE_REGISTER && value & 0x01 != 0 {
+            self.capture(image_source);
+        }
+    }
+
+    /// Take the most recently captured image, if it has not already been taken.
+    pub(crate) fn take_captured_image(&mut self) -> Option<Vec<u8>> {
+        self.captured_image.take()
+    }
+
+    fn capture(&mut self, image_source: &mut dyn CameraImageSource) {
+        let pixels = image_source.capture();
+        assert_eq!(CAMERA_WIDTH * CAMERA_HEIGHT, pixels.len(), "invalid camera image size");
+
+        let exposure = u32::from(u16::from_be_bytes([
+            self.registers[EXPOSURE_HIGH_REGISTER],
+            self.registers[EXPOSURE_LOW_REGISTER],
+        ]));
+        let exposed: Vec<i32> = pixels
+            .iter()
+            .map(|&pixel| (u32::from(pixel) * exposure / REFERENCE_EXPOSURE).min(0xFF) as i32)
+            .collect();
+
+        let enhanced = self.enhance_edges(&exposed);
+
+        let mut tiles = vec![0; IMAGE_LEN];
+        for y in 0..CAMERA_HEIGHT {
+            for x in 0..CAMERA_WIDTH {
+                let color = self.dither(enhanced[y * CAMERA_WIDTH + x], x, y);
+
+                let tile_addr = ((y / 8) * (CAMERA_WIDTH / 8) + x / 8) * 16 + (y % 8) * 2;
+                let bit = 7 - (x % 8);
+                tiles[tile_addr] |= (color & 0x01) << bit;
+                tiles[tile_addr + 1] |= (color >> 1) << bit;
+            }
+        }
+
+        self.captured_image = Some(tiles);
+        self.registers[CAPTURE_REGISTER] &= !0x01;
+    }
+
+    fn enhance_edges(&self, pixels: &[i32]) -> Vec<i32> {
+        // 0 = none, 1 = horizontal, 2 = vertical, 3 = both
+        let mode = (self.registers[EDGE_MODE_REGISTER] >> 5) & 0x03;
+        if mode == 0 {
+            return pixels.to_vec();
+        }
+
+        let ratio = EDGE_RATIOS[usize::from((self.registers[EDGE_RATIO_REGISTER] >> 4) & 0x07)];
+        let get = |x: usize, y: usize| pixels[y * CAMERA_WIDTH + x];
+
+        let mut enhanced = Vec::with_capacity(pixels.len());
+        for y in 0..CAMERA_HEIGHT {
+            for x in 0..CAMERA_WIDTH {
+                let pixel = get(x, y);
+
+                let mut neighbors = Vec::with_capacity(4);
+                if mode & 0x01 != 0 {
+                    neighbors.push(get(x.saturating_sub(1), y));
+                    neighbors.push(get((x + 1).min(CAMERA_WIDTH - 1), y));
+                }
+                if mode & 0x02 != 0 {
+                    neighbors.push(get(x, y.saturating_sub(1)));
+                    neighbors.push(get(x, (y + 1).min(CAMERA_HEIGHT - 1)));
+                }
+
+                let edge = neighbors.len() as i32 * pixel - neighbors.iter().sum::<i32>();
+                enhanced.push((pixel + edge * ratio / 4).clamp(0, 0xFF));
+            }
+        }
+        enhanced
+    }
+
+    fn dither(&self, value: i32, x: usize, y: usize) -> u8 {
+        let matrix_addr = DITHER_MATRIX_START + ((y % 4) * 4 + (x % 4)) * 3;
+        let thresholds = &self.registers[matrix_addr..matrix_addr + 3];
+
+        // Lower sensor output means less light, which is a darker color
+        match thresholds.iter().position(|&threshold| value < i32::from(threshold)) {
+            Some(0) => 3,
+            Some(1) => 2,
+            Some(_) => 1,
+            None => 0,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::camera::StillImageSource;
+
+    fn set_thresholds(camera: &mut PocketCamera, thresholds: [u8; 3]) {
+        for i in 0..16 {
+            for (j, &threshold) in thresholds.iter().enumerate() {
+                camera.registers[DITHER_MATRIX_START + 3 * i + j] = threshold;
+            }
+        }
+    }
+
+    fn capture(camera: &mut PocketCamera, pixels: Vec<u8>) -> Vec<u8> {
+        let mut source = StillImageSource::new(pixels);
+        camera.handle_write(0xA000, 0x03, &mut source);
+        camera.take_captured_image().unwrap()
+    }
+
+    #[test]
+    fn exposure_and_dithering() {
+        let mut camera = PocketCamera::new();
+        set_thresholds(&mut camera, [0x40, 0x80, 0xC0]);
+        camera.registers[EXPOSURE_HIGH_REGISTER] = 0x03;
+
+        // Left half at 0x90 brightness, right half black
+        let pixels: Vec<u8> = (0..CAMERA_WIDTH * CAMERA_HEIGHT)
+            .map(|i| if i % CAMERA_WIDTH < CAMERA_WIDTH / 2 { 0x90 } else { 0x00 })
+            .collect();
+
+        let tiles = capture(&mut camera, pixels.clone());
+        assert_eq!(IMAGE_LEN, tiles.len());
+        // Color 1 in the left half, color 3 in the right half
+        assert_eq!([0xFF, 0x00], tiles[0..2]);
+        assert_eq!([0xFF, 0xFF], tiles[8 * 16..8 * 16 + 2]);
+
+        // Capture should complete immediately and only happen once
+        assert_eq!(0x02, camera.handle_read(0xA000));
+        assert_eq!(None, camera.take_captured_image());
+
+        // Doubling exposure should make the left half white
+        camera.registers[EXPOSURE_HIGH_REGISTER] = 0x06;
+        let tiles = capture(&mut camera, pixels);
+        assert_eq!([0x00, 0x00], tiles[0..2]);
+        assert_eq!([0xFF, 0xFF], tiles[8 * 16..8 * 16 + 2]);
+    }
+
+    #[test]
+    fn edge_enhancement() {
+        let mut camera = PocketCamera::new();
+        camera.registers[EDGE_MODE_REGISTER] = 0x20;
+        camera.registers[EDGE_RATIO_REGISTER] = 0x20;
+
+        let pixels: Vec<i32> = (0..CAMERA_WIDTH * CAMERA_HEIGHT)
+            .map(|i| if i % CAMERA_WIDTH < CAMERA_WIDTH / 2 { 0x60 } else { 0x40 })
+            .collect();
+        let enhanced = camera.enhance_edges(&pixels);
+
+        // Horizontal edge enhancement should exaggerate the boundary in the middle of each row
+        assert_eq!(0x60, enhanced[CAMERA_WIDTH / 2 - 2]);
+        assert_eq!(0x80, enhanced[CAMERA_WIDTH / 2 - 1]);
+        assert_eq!(0x20, enhanced[CAMERA_WIDTH / 2]);
+        assert_eq!(0x40, enhanced[CAMERA_WIDTH / 2 + 1]);
+    }
+}
@@ -0,0 +1,441 @@
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, SystemTime};
+
+// Registers in the TAMA5 register window. The game selects a register by writing its index to
+// 0xA001, then reads or writes the low nibble of the register through 0xA000.
+const ROM_BANK_LOW: u8 = 0x0;
+const ROM_BANK_HIGH: u8 = 0x1;
+const WRITE_DATA_LOW: u8 = 0x4;
+const WRITE_DATA_HIGH: u8 = 0x5;
+const ADDRESS_HIGH: u8 = 0x6;
+// Writing this register executes the operation selected by ADDRESS_HIGH
+const ADDRESS_LOW: u8 = 0x7;
+const READY: u8 = 0xA;
+const READ_DATA_LOW: u8 = 0xC;
+const READ_DATA_HIGH: u8 = 0xD;
+
+// Operations, selected by bits 1-3 of the ADDRESS_HIGH register
+const OP_MEMORY_WRITE: u8 = 0x0;
+const OP_MEMORY_READ: u8 = 0x1;
+const OP_RTC_COMMAND: u8 = 0x2;
+const OP_RTC_REGISTER: u8 = 0x4;
+
+// RTC commands, selected by the address
+const RTC_COMMAND_WRITE_MINUTES: u8 = 0x04;
+const RTC_COMMAND_WRITE_HOURS: u8 = 0x05;
+
+// The first 13 RTC registers hold the current time/date as one decimal digit per register:
+// seconds, minutes, hours, day of week, day, month, year
+const RTC_TIME_REGISTERS: u8 = 0x0D;
+
+const SECONDS_PER_DAY: i64 = 86400;
+
+// The clock starts at 2000-01-01 00:00:00 if there is no saved clock state
+const DEFAULT_TIME: i64 = 946_684_800;
+
+// Emulation of the 32 bytes of battery-backed memory in the TAMA5 cartridge's TAMA6 chip
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct Tama5Eeprom {
+    memory: [u8; 32],
+}
+
+impl Tama5Eeprom {
+    pub(crate) fn new(loaded_ram: Option<&Vec<u8>>) -> Self {
+        let mut memory = [0; 32];
+
+        match loaded_ram {
+            Some(loaded_ram) if loaded_ram.len() == memory.len() => {
+                memory.copy_from_slice(loaded_ram);
+            }
+            _ => {}
+        }
+
+        Self { memory }
+    }
+
+    pub(crate) fn raw_memory(&self) -> &[u8; 32] {
+        &self.memory
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct DateTime {
+    year: i64,
+    month: i64,
+    day: i64,
+    hours: i64,
+    minutes: i64,
+    seconds: i64,
+}
+
+// Convert a Unix day number to a (year, month, day) date in the proleptic Gregorian calendar
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+// Inverse of civil_from_days
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year.rem_euclid(400);
+    let mp = if month > 2 { month - 3 } else { month + 9 };
+    let day_of_year = (153 * mp + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+impl DateTime {
+    fn from_timestamp(timestamp: i64) -> Self {
+        let (year, month, day) = civil_from_days(timestamp.div_euclid(SECONDS_PER_DAY));
+        let time_of_day = timestamp.rem_euclid(SECONDS_PER_DAY);
+        Self {
+            year,
+            month,
+            day,
+            hours: time_of_day / 3600,
+            minutes: time_of_day / 60 % 60,
+            seconds: time_of_day % 60,
+        }
+    }
+
+    fn to_timestamp(self) -> i64 {
+        days_from_civil(self.year, self.month, self.day) * SECONDS_PER_DAY
+            + self.hours * 3600
+            + self.minutes * 60
+            + self.seconds
+    }
+
+    fn day_of_week(self) -> i64 {
+        // 1970-01-01 was a Thursday, with Sunday as day 0
+        (days_from_civil(self.year, self.month, self.day) + 4).rem_euclid(7)
+    }
+
+    fn read_register(self, register: u8) -> u8 {
+        let digit = match register {
+            0x0 => self.seconds % 10,
+            0x1 => self.seconds / 10,
+            0x2 => self.minutes % 10,
+            0x3 => self.minutes / 10,
+            0x4 => self.hours % 10,
+            0x5 => self.hours / 10,
+            0x6 => self.day_of_week(),
+            0x7 => self.day % 10,
+            0x8 => self.day / 10,
+            0x9 => self.month % 10,
+            0xA => self.month / 10,
+            0xB => self.year % 10,
+            0xC => self.year / 10 % 10,
+            _ => 0,
+        };
+        digit as u8
+    }
+
+    fn write_register(&mut self, register: u8, digit: u8) {
+        let digit = i64::from(digit);
+        let replace_ones = |value: i64| value - value % 10 + digit;
+        let replace_tens = |value: i64| value % 10 + 10 * digit;
+        match register {
+            0x0 => self.seconds = replace_ones(self.seconds),
+            0x1 => self.seconds = replace_tens(self.seconds),
+            0x2 => self.minutes = replace_ones(self.minutes),
+            0x3 => self.minutes = replace_tens(self.minutes),
+            0x4 => self.hours = replace_ones(self.hours),
+            0x5 => self.hours = replace_tens(self.hours),
+            0x7 => self.day = replace_ones(self.day),
+            0x8 => self.day = replace_tens(self.day),
+            0x9 => self.month = replace_ones(self.month),
+            0xA => self.month = replace_tens(self.month),
+            0xB => self.year = self.year - self.year % 10 + digit,
+            0xC => self.year = self.year - self.year % 100 + 10 * digit + self.year % 10,
+            // Day of week (0x6) is always derived from the date
+            _ => {}
+        }
+
+        // Keep values in range so that the timestamp conversion cannot do anything strange
+        self.seconds = self.seconds.min(59);
+        self.minutes = self.minutes.min(59);
+        self.hours = self.hours.min(23);
+        self.month = self.month.clamp(1, 12);
+        self.day = self.day.clamp(1, 31);
+    }
+}
+
+fn from_bcd(value: u8) -> i64 {
+    i64::from(10 * (value >> 4) + (value & 0x0F))
+}
+
+/// The state of the TAMA5 real-time clock, which is saved to the .rtc file.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) struct Tama5Clock {
+    last_update: SystemTime,
+    nanos: u32,
+    // Seconds since the Unix epoch in the emulated clock's time
+    timestamp: i64,
+}
+
+impl Tama5Clock {
+    pub(crate) fn new(now: SystemTime) -> Self {
+        Self { last_update: now, nanos: 0, timestamp: DEFAULT_TIME }
+    }
+
+    pub(crate) fn update(&mut self, now: SystemTime) {
+        let since = now.duration_since(self.last_update).unwrap_or_else(|err| {
+            log::error!(
+                "Time has gone backwards: last_update={:?}, now={now:?}: {err}",
+                self.last_update
+            );
+            Duration::from_secs(0)
+        });
+
+        self.last_update = now;
+
+        let nanos = u128::from(self.nanos) + since.as_nanos();
+        self.nanos = (nanos % 1_000_000_000) as u32;
+        self.timestamp += (nanos / 1_000_000_000) as i64;
+    }
+
+    fn date_time(&self) -> DateTime {
+        DateTime::from_timestamp(self.timestamp)
+    }
+
+    fn set_date_time(&mut self, date_time: DateTime) {
+        self.timestamp = date_time.to_timestamp();
+        self.nanos = 0;
+    }
+}
+
+/// The TAMA5 register window, the memory it gives access to, and the real-time clock.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct Tama5 {
+    registers: [u8; 16],
+    selected_register: u8,
+    read_data: u8,
+    eeprom: Tama5Eeprom,
+    clock: Tama5Clock,
+}
+
+impl Tama5 {
+    pub(crate) fn new(
+        loaded_ram: Option<&Vec<u8>>,
+        clock: Option<Tama5Clock>,
+        now: SystemTime,
+    ) -> Self {
+        let clock = match clock {
+            Some(mut clock) => {
+                clock.update(now);
+                clock
+            }
+            None => Tama5Clock::new(now),
+        };
+
+        Self {
+            registers: [0; 16],
+            selected_register: 0,
+            read_data: 0,
+            eeprom: Tama5Eeprom::new(loaded_ram),
+            clock,
+        }
+    }
+
+    pub(crate) fn rom_bank_number(&self) -> u16 {
+        (u16::from(self.registers[ROM_BANK_HIGH as usize] & 0x01) << 4)
+            | u16::from(self.registers[ROM_BANK_LOW as usize])
+    }
+
+    pub(crate) fn eeprom(&self) -> &Tama5Eeprom {
+        &self.eeprom
+    }
+
+    pub(crate) fn clock(&self) -> &Tama5Clock {
+        &self.clock
+    }
+
+    pub(crate) fn update_clock(&mut self, now: SystemTime) {
+        self.clock.update(now);
+    }
+
+    pub(crate) fn handle_read(&self, address: u16) -> u8 {
+        if address & 0x0001 != 0 {
+            return 0xFF;
+        }
+
+        let value = match self.selected_register {
+            READY => 0x1,
+            READ_DATA_LOW => self.read_data & 0x0F,
+            READ_DATA_HIGH => self.read_data >> 4,
+            _ => 0x0,
+        };
+        0xF0 | value
+    }
+
+    pub(crate) fn handle_write(&mut self, address: u16, value: u8, now: SystemTime) {
+        if address & 0x0001 != 0 {
+            self.selected_register = value & 0x0F;
+            return;
+        }
+
+        let value = value & 0x0F;
+        self.registers[self.selected_register as usize] = value;
+
+        if self.selected_register == ADDRESS_LOW {
+            self.execute_operation(now);
+        }
+    }
+
+    fn execute_operation(&mut self, now: SystemTime) {
+        let address_high = self.registers[ADDRESS_HIGH as usize];
+        let address = ((address_high & 0x01) << 4) | self.registers[ADDRESS_LOW as usize];
+        let data = self.registers[WRITE_DATA_LOW as usize]
+            | (self.registers[WRITE_DATA_HIGH as usize] << 4);
+
+        match address_high >> 1 {
+            OP_MEMORY_WRITE => {
+                self.eeprom.memory[address as usize] = data;
+            }
+            OP_MEMORY_READ => {
+                self.read_data = self.eeprom.memory[address as usize];
+            }
+            OP_RTC_COMMAND => {
+                self.clock.update(now);
+                let mut date_time = self.clock.date_time();
+                match address {
+                    RTC_COMMAND_WRITE_MINUTES => date_time.minutes = from_bcd(data).min(59),
+                    RTC_COMMAND_WRITE_HOURS => date_time.hours = from_bcd(data).min(23),
+                    _ => {
+                        log::debug!("Ignoring TAMA5 RTC command {address:02X}");
+                        return;
+                    }
+                }
+                self.clock.set_date_time(date_time);
+            }
+            OP_RTC_REGISTER => {
+                // The RTC register index is in the low data nibble, and the low address bits
+                // select between reading (0) and writing (2) the register
+                let register = self.registers[WRITE_DATA_LOW as usize];
+                if register >= RTC_TIME_REGISTERS {
+                    return;
+                }
+
+                self.clock.update(now);
+                let mut date_time = self.clock.date_time();
+                match self.registers[ADDRESS_LOW as usize] {
+                    0x0 => {
+                        self.read_data = date_time.read_register(register);
+                    }
+                    0x2 => {
+                        let digit = self.registers[WRITE_DATA_HIGH as usize];
+                        date_time.write_register(register, digit);
+                        self.clock.set_date_time(date_time);
+                    }
+                    _ => {}
+                }
+            }
+            op => {
+                log::debug!("Ignoring unknown TAMA5 operation {op:X}");
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write_register(tama5: &mut Tama5, register: u8, value: u8) {
+        tama5.handle_write(0xA001, register, SystemTime::UNIX_EPOCH);
+        tama5.handle_write(0xA000, value, SystemTime::UNIX_EPOCH);
+    }
+
+    fn read_register(tama5: &mut Tama5, register: u8) -> u8 {
+        tama5.handle_write(0xA001, register, SystemTime::UNIX_EPOCH);
+        tama5.handle_read(0xA000)
+    }
+
+    #[test]
+    fn memory_read_write() {
+        let mut tama5 = Tama5::new(None, None, SystemTime::UNIX_EPOCH);
+
+        assert_eq!(0xF1, read_register(&mut tama5, READY));
+
+        // Write 0xA5 to address 0x13
+        write_register(&mut tama5, WRITE_DATA_LOW, 0x5);
+        write_register(&mut tama5, WRITE_DATA_HIGH, 0xA);
+        write_register(&mut tama5, ADDRESS_HIGH, (OP_MEMORY_WRITE << 1) | 0x1);
+        write_register(&mut tama5, ADDRESS_LOW, 0x3);
+        assert_eq!(0xA5, tama5.eeprom().raw_memory()[0x13]);
+
+        write_register(&mut tama5, ADDRESS_HIGH, (OP_MEMORY_READ << 1) | 0x1);
+        write_register(&mut tama5, ADDRESS_LOW, 0x3);
+        assert_eq!(0xF5, read_register(&mut tama5, READ_DATA_LOW));
+        assert_eq!(0xFA, read_register(&mut tama5, READ_DATA_HIGH));
+    }
+
+    #[test]
+    fn rom_bank() {
+        let mut tama5 = Tama5::new(None, None, SystemTime::UNIX_EPOCH);
+
+        write_register(&mut tama5, ROM_BANK_LOW, 0x7);
+        write_register(&mut tama5, ROM_BANK_HIGH, 0x1);
+        assert_eq!(0x17, tama5.rom_bank_number());
+    }
+
+    #[test]
+    fn date_conversion() {
+        let date_time = DateTime::from_timestamp(DEFAULT_TIME);
+        assert_eq!(
+            DateTime { year: 2000, month: 1, day: 1, hours: 0, minutes: 0, seconds: 0 },
+            date_time
+        );
+        // 2000-01-01 was a Saturday
+        assert_eq!(6, date_time.day_of_week());
+
+        // Leap day
+        let date_time = DateTime::from_timestamp(DEFAULT_TIME + 59 * SECONDS_PER_DAY + 3661);
+        assert_eq!(
+            DateTime { year: 2000, month: 2, day: 29, hours: 1, minutes: 1, seconds: 1 },
+            date_time
+        );
+        assert_eq!(DEFAULT_TIME + 59 * SECONDS_PER_DAY + 3661, date_time.to_timestamp());
+    }
+
+    #[test]
+    fn rtc() {
+        let mut tama5 = Tama5::new(None, None, SystemTime::UNIX_EPOCH);
+
+        // Set hours to 13 through the hours command
+        write_register(&mut tama5, WRITE_DATA_LOW, 0x3);
+        write_register(&mut tama5, WRITE_DATA_HIGH, 0x1);
+        write_register(&mut tama5, ADDRESS_HIGH, OP_RTC_COMMAND << 1);
+        write_register(&mut tama5, ADDRESS_LOW, RTC_COMMAND_WRITE_HOURS);
+
+        let read_rtc_register = |tama5: &mut Tama5, register: u8| {
+            write_register(tama5, WRITE_DATA_LOW, register);
+            write_register(tama5, ADDRESS_HIGH, OP_RTC_REGISTER << 1);
+            write_register(tama5, ADDRESS_LOW, 0x0);
+            read_register(tama5, READ_DATA_LOW) & 0x0F
+        };
+
+        assert_eq!(0x3, read_rtc_register(&mut tama5, 0x4));
+        assert_eq!(0x1, read_rtc_register(&mut tama5, 0x5));
+
+        // Set the month to 7 through the RTC registers
+        write_register(&mut tama5, WRITE_DATA_LOW, 0x9);
+        write_register(&mut tama5, WRITE_DATA_HIGH, 0x7);
+        write_register(&mut tama5, ADDRESS_HIGH, OP_RTC_REGISTER << 1);
+        write_register(&mut tama5, ADDRESS_LOW, 0x2);
+
+        assert_eq!(0x7, read_rtc_register(&mut tama5, 0x9));
+        assert_eq!(0x0, read_rtc_register(&mut tama5, 0xA));
+        assert_eq!(0x3, read_rtc_register(&mut tama5, 0x4));
+    }
+}
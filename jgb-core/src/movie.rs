@@ -0,0 +1,649 @@
+mod bk2;
+mod vbm;
+
+use crate::config::CpuAccuracy;
+use crate::input::{AccelerometerState, Button, JoypadState};
+use crate::memory::ioregisters::IoRegister;
+use crate::serialize::{self, SaveStateError};
+use crate::startup::EmulationState;
+use serde::{Deserialize, Serialize};
+use std::ffi::OsStr;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+use std::{fs, io};
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum MovieError {
+    #[error("error serializing/deserializing movie: {source}")]
+    Serialization {
+        #[from]
+        source: bincode::Error,
+    },
+    #[error("error reading/writing movie: {source}")]
+    FileSystem {
+        #[from]
+        source: io::Error,
+    },
+    #[error("error reading BK2 archive: {source}")]
+    Bk2Archive {
+        #[from]
+        source: zip::result::ZipError,
+    },
+    #[error("movie file is invalid or truncated")]
+    InvalidFormat,
+    #[error(
+        "movie format version {version} is not supported; this version of jgb can play version {}",
+        MOVIE_FORMAT_VERSION
+    )]
+    IncompatibleVersion { version: u32 },
+    #[error("{reason}")]
+    UnsupportedMovie { reason: String },
+    #[error("error saving/loading the movie's starting state: {source}")]
+    StartState {
+        #[from]
+        source: SaveStateError,
+    },
+}
+
+// Native movie files are laid out as:
+//   the magic bytes "JGBM"
+//   format version (u32, little-endian)
+//   the bincode-serialized MovieData
+const MOVIE_MAGIC: [u8; 4] = *b"JGBM";
+const MOVIE_FORMAT_VERSION: u32 = 2;
+
+// Bit N of a frame's button byte is set if BUTTON_BITS[N] is pressed. This is the same order that
+// the buttons appear in the JOYP register, actions first, which is also the order used by VBM
+// movies
+const BUTTON_BITS: [Button; 8] = [
+    Button::A,
+    Button::B,
+    Button::Select,
+    Button::Start,
+    Button::Right,
+    Button::Left,
+    Button::Up,
+    Button::Down,
+];
+
+// The emulated real-time clock advances by the number of 4MHz clock cycles that each frame took
+const CLOCK_SPEED: u128 = 4 * 1024 * 1024;
+
+/// Where a movie's recording started.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MovieStart {
+    PowerOn,
+    SaveState,
+}
+
+/// Whether the emulator is currently recording or playing back a movie.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MovieStatus {
+    None,
+    Recording,
+    Playing,
+    /// Every frame of the movie has been played back. Input comes from the frontend again, but
+    /// real-time clocks continue to use the emulated time.
+    PlaybackFinished,
+}
+
+impl MovieStatus {
+    /// Whether a movie is being recorded or played back, during which anything that would break
+    /// determinism (such as loading a save state or rewinding) should be disallowed.
+    #[must_use]
+    pub fn is_active(self) -> bool {
+        matches!(self, Self::Recording | Self::Playing)
+    }
+}
+
+/// Emulator state that is not included in save states but still affects emulation, which a movie
+/// needs to restore in order to play back deterministically.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) struct UnsavedEmulatorState {
+    pub(crate) leftover_cpu_cycles: u32,
+    pub(crate) compatibility_palette_frames_remaining: u32,
+    // Instruction-level and cycle-level accuracy produce different timings
+    pub(crate) cpu_accuracy: CpuAccuracy,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+struct MovieFrame {
+    buttons: u8,
+    accelerometer: AccelerometerState,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct MovieData {
+    start: MovieStart,
+    // The emulated time at the start of the movie, which real-time clocks count forward from
+    start_time: SystemTime,
+    // The starting state in save state file format. This is included even when recording from
+    // power-on so that cartridge RAM and real-time clock contents match during playback
+    start_state: Vec<u8>,
+    start_unsaved_state: UnsavedEmulatorState,
+    frames: Vec<MovieFrame>,
+}
+
+// Input parsed from another emulator's movie format. Imported movies always start from power-on
+// with no save data
+#[derive(Debug, Clone)]
+struct ImportedMovie {
+    // The title and global checksum of the ROM that the movie was recorded with, if known
+    rom_identity: Option<(String, u16)>,
+    // What the cartridge real-time clock reads at power-on
+    rtc_initial_time: Duration,
+    frames: Vec<MovieFrame>,
+    // Frames containing input that jgb cannot replay, such as resets or motion sensor input
+    unsupported_frames: Vec<usize>,
+}
+
+impl ImportedMovie {
+    fn new(rom_identity: Option<(String, u16)>) -> Self {
+        Self {
+            rom_identity,
+            rtc_initial_time: Duration::ZERO,
+            frames: Vec::new(),
+            unsupported_frames: Vec::new(),
+        }
+    }
+
+    // Replace the cartridge's save data with that of a cartridge without a save file, and begin
+    // playing back from the current state. The emulated time starts from a fixed point so that
+    // real-time clocks behave the same on every playback
+    fn start_playback(
+        self,
+        state: &mut EmulationState,
+        unsaved_state: UnsavedEmulatorState,
+    ) -> Result<MovieData, MovieError> {
+        if let Some((movie_title, movie_checksum)) = &self.rom_identity {
+            let (rom_title, rom_checksum) =
+                serialize::rom_identity(state.address_space.cartridge_header());
+            // VBM movies only store the first 12 bytes of the title
+            if !rom_title.starts_with(movie_title.trim_end()) || rom_checksum != *movie_checksum {
+                log::warn!(
+                    "Movie was recorded with a different ROM ('{movie_title}', checksum {movie_checksum:04X}); current ROM is '{rom_title}', checksum {rom_checksum:04X}"
+                );
+            }
+        }
+
+        *state.controller_states.rtc_emulated_time.borrow_mut() = Some(SystemTime::UNIX_EPOCH);
+        state.address_space.reset_cartridge_save_data();
+
+        let start_time = SystemTime::UNIX_EPOCH + self.rtc_initial_time;
+        let mut data =
+            start_from_current_state(MovieStart::PowerOn, state, unsaved_state, start_time)?;
+        data.frames = self.frames;
+        Ok(data)
+    }
+
+    fn push_frame(&mut self, buttons: u8, unsupported: bool) {
+        if unsupported {
+            self.unsupported_frames.push(self.frames.len());
+        }
+        self.frames.push(MovieFrame { buttons, accelerometer: AccelerometerState::default() });
+    }
+}
+
+#[derive(Debug, Clone)]
+enum MovieMode {
+    Recording { path: PathBuf, written: bool },
+    Playback,
+}
+
+/// A movie that is being recorded or played back. Recorded movies are kept in memory and only
+/// written to disk when recording stops or the movie is dropped.
+#[derive(Debug)]
+pub(crate) struct Movie {
+    mode: MovieMode,
+    data: MovieData,
+    next_frame: usize,
+    // The movie frame whose input is being played back in the current emulated frame, if any
+    current_frame: Option<usize>,
+    elapsed_cycles: u64,
+    // Frames that contain input which is ignored during playback, in ascending order
+    unsupported_frames: Vec<usize>,
+    desync_frames: Vec<usize>,
+}
+
+impl Movie {
+    fn new(mode: MovieMode, data: MovieData, unsupported_frames: Vec<usize>) -> Self {
+        Self {
+            mode,
+            data,
+            next_frame: 0,
+            current_frame: None,
+            elapsed_cycles: 0,
+            unsupported_frames,
+            desync_frames: vec![],
+        }
+    }
+
+    /// Start recording from the current state. Real-time clocks switch to an emulated time that
+    /// starts at the current system time.
+    pub(crate) fn start_recording(
+        path: &Path,
+        start: MovieStart,
+        state: &mut EmulationState,
+        unsaved_state: UnsavedEmulatorState,
+    ) -> Result<Self, MovieError> {
+        let data = start_from_current_state(start, state, unsaved_state, SystemTime::now())?;
+
+        log::info!("Recording movie to '{}'", path.display());
+
+        Ok(Self::new(MovieMode::Recording { path: path.into(), written: false }, data, vec![]))
+    }
+
+    /// Load the movie at the given path for playback. Returns the unsaved emulator state to restore
+    /// along with the movie.
+    ///
+    /// Native movies replace the current state with the movie's starting state. BizHawk (`.bk2`)
+    /// and VisualBoyAdvance (`.vbm`) movies are imported based on the file extension, and they
+    /// start from the current state, which should be the power-on state, with cartridge RAM and
+    /// real-time clocks reset as if there were no save file.
+    pub(crate) fn start_playback(
+        path: &Path,
+        state: &mut EmulationState,
+        unsaved_state: UnsavedEmulatorState,
+    ) -> Result<(Self, UnsavedEmulatorState), MovieError> {
+        let bytes = fs::read(path)?;
+
+        let extension = path.extension().and_then(OsStr::to_str).map(str::to_ascii_lowercase);
+        let imported_movie = match extension.as_deref() {
+            Some("bk2") => Some(bk2::parse(&bytes)?),
+            Some("vbm") => Some(vbm::parse(&bytes)?),
+            _ => None,
+        };
+
+        let (data, unsupported_frames) = match imported_movie {
+            Some(mut imported_movie) => {
+                let unsupported_frames = std::mem::take(&mut imported_movie.unsupported_frames);
+                (imported_movie.start_playback(state, unsaved_state)?, unsupported_frames)
+            }
+            None => {
+                let data = read_movie_data(&bytes)?;
+                serialize::load_state_from_bytes(&data.start_state, state)?;
+                *state.controller_states.rtc_emulated_time.borrow_mut() = Some(data.start_time);
+                (data, vec![])
+            }
+        };
+
+        log::info!(
+            "Playing back movie from '{}' ({} frames, starting from {:?})",
+            path.display(),
+            data.frames.len(),
+            data.start
+        );
+
+        let unsaved_state = data.start_unsaved_state;
+        Ok((Self::new(MovieMode::Playback, data, unsupported_frames), unsaved_state))
+    }
+
+    pub(crate) fn status(&self) -> MovieStatus {
+        match self.mode {
+            MovieMode::Recording { .. } => MovieStatus::Recording,
+            MovieMode::Playback if self.next_frame < self.data.frames.len() => MovieStatus::Playing,
+            MovieMode::Playback => MovieStatus::PlaybackFinished,
+        }
+    }
+
+    pub(crate) fn is_playback(&self) -> bool {
+        matches!(self.mode, MovieMode::Playback)
+    }
+
+    pub(crate) fn desync_frames(&self) -> &[usize] {
+        &self.desync_frames
+    }
+
+    /// Determine the input for the next frame. When recording, the given input and the current
+    /// accelerometer state are recorded and the input is returned unchanged. When playing back,
+    /// the input and accelerometer state are replaced with the movie's next frame.
+    pub(crate) fn next_input(&mut self, input: JoypadState, state: &EmulationState) -> JoypadState {
+        let accelerometer_state = &state.controller_states.accelerometer_state;
+        match self.mode {
+            MovieMode::Recording { .. } => {
+                self.data.frames.push(MovieFrame {
+                    buttons: buttons_to_byte(&input),
+                    accelerometer: *accelerometer_state.borrow(),
+                });
+                input
+            }
+            MovieMode::Playback => match self.data.frames.get(self.next_frame) {
+                Some(frame) => {
+                    self.current_frame = Some(self.next_frame);
+                    self.next_frame += 1;
+                    *accelerometer_state.borrow_mut() = frame.accelerometer;
+                    byte_to_buttons(frame.buttons)
+                }
+                None => input,
+            },
+        }
+    }
+
+    /// Advance the emulated time by the given number of 4MHz clock cycles, and check whether
+    /// playback may have desynced during the frame.
+    ///
+    /// Movies do not record anything to verify playback against, so a frame is reported as a
+    /// possible desync if the game did not poll the joypad during a frame where the movie's input
+    /// changed, since the recording emulator must have been at a different point in the game. A
+    /// frame is also reported if it contains input that cannot be replayed.
+    pub(crate) fn end_frame(&mut self, frame_cycles: u64, state: &mut EmulationState) {
+        let io_registers = state.address_space.get_io_registers_mut();
+        let joypad_polled = io_registers.get_dirty_bit(IoRegister::JOYP);
+        io_registers.clear_dirty_bit(IoRegister::JOYP);
+
+        if let Some(frame) = self.current_frame.take() {
+            let input_changed =
+                frame > 0 && self.data.frames[frame].buttons != self.data.frames[frame - 1].buttons;
+            if input_changed && !joypad_polled {
+                log::warn!(
+                    "Possible movie desync at frame {frame}: input changed but the game did not poll the joypad"
+                );
+                self.desync_frames.push(frame);
+            } else if self.unsupported_frames.binary_search(&frame).is_ok() {
+                log::warn!(
+                    "Possible movie desync at frame {frame}: frame contains unsupported input"
+                );
+                self.desync_frames.push(frame);
+            }
+        }
+
+        self.elapsed_cycles += frame_cycles;
+
+        let elapsed_nanos = u128::from(self.elapsed_cycles) * 1_000_000_000 / CLOCK_SPEED;
+        let elapsed = Duration::from_nanos(elapsed_nanos as u64);
+        *state.controller_states.rtc_emulated_time.borrow_mut() =
+            Some(self.data.start_time + elapsed);
+    }
+
+    /// Stop recording or playing back. Recorded movies are written to disk, and real-time clocks
+    /// switch back to the system time.
+    pub(crate) fn stop(mut self, state: &EmulationState) -> Result<(), MovieError> {
+        *state.controller_states.rtc_emulated_time.borrow_mut() = None;
+
+        self.write_recording()
+    }
+
+    // Write the recorded movie to disk unless it has already been written (or failed to write)
+    fn write_recording(&mut self) -> Result<(), MovieError> {
+        let MovieMode::Recording { path, written } = &mut self.mode else {
+            return Ok(());
+        };
+        if *written {
+            return Ok(());
+        }
+        *written = true;
+
+        fs::write(&*path, self.data.to_bytes()?)?;
+
+        log::info!(
+            "Successfully wrote movie with {} frames to '{}'",
+            self.data.frames.len(),
+            path.display()
+        );
+
+        Ok(())
+    }
+}
+
+// Don't lose a recording if the emulator exits without stopping the movie, e.g. due to an error
+impl Drop for Movie {
+    fn drop(&mut self) {
+        if let Err(err) = self.write_recording() {
+            log::error!("error writing movie: {err}");
+        }
+    }
+}
+
+// Begin a movie from the current state, switching real-time clocks to an emulated time that starts
+// at the given time
+fn start_from_current_state(
+    start: MovieStart,
+    state: &mut EmulationState,
+    unsaved_state: UnsavedEmulatorState,
+    start_time: SystemTime,
+) -> Result<MovieData, MovieError> {
+    *state.controller_states.rtc_emulated_time.borrow_mut() = Some(start_time);
+    state.address_space.update_rtc();
+
+    Ok(MovieData {
+        start,
+        start_time,
+        start_state: serialize::save_state_to_bytes(state)?,
+        start_unsaved_state: unsaved_state,
+        frames: Vec::new(),
+    })
+}
+
+impl MovieData {
+    fn to_bytes(&self) -> Result<Vec<u8>, MovieError> {
+        let mut bytes = MOVIE_MAGIC.to_vec();
+        bytes.extend(MOVIE_FORMAT_VERSION.to_le_bytes());
+        bincode::serialize_into(&mut bytes, self)?;
+        Ok(bytes)
+    }
+}
+
+fn read_movie_data(bytes: &[u8]) -> Result<MovieData, MovieError> {
+    let Some(bytes) = bytes.strip_prefix(&MOVIE_MAGIC) else {
+        return Err(MovieError::InvalidFormat);
+    };
+
+    let Some((version, bytes)) = bytes.split_first_chunk::<4>() else {
+        return Err(MovieError::InvalidFormat);
+    };
+    let version = u32::from_le_bytes(*version);
+    if version != MOVIE_FORMAT_VERSION {
+        return Err(MovieError::IncompatibleVersion { version });
+    }
+
+    Ok(bincode::deserialize(bytes)?)
+}
+
+fn button_bit(button: Button) -> u8 {
+    let bit = BUTTON_BITS.iter().position(|&b| b == button).expect("every button has a bit");
+    1 << bit
+}
+
+fn buttons_to_byte(joypad_state: &JoypadState) -> u8 {
+    BUTTON_BITS
+        .into_iter()
+        .filter(|&button| joypad_state.is_pressed(button))
+        .fold(0, |byte, button| byte | button_bit(button))
+}
+
+fn byte_to_buttons(byte: u8) -> JoypadState {
+    let mut joypad_state = JoypadState::new();
+    for (bit, &button) in BUTTON_BITS.iter().enumerate() {
+        joypad_state.set_button(button, byte & (1 << bit) != 0);
+    }
+    joypad_state
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn new_test_state() -> EmulationState {
+        // MBC3+TIMER+BATTERY
+        EmulationState::new_test_state(0x0F, b"TEST")
+    }
+
+    fn test_movie_path() -> PathBuf {
+        std::env::temp_dir().join(format!("jgb-movie-test-{}.jgbm", std::process::id()))
+    }
+
+    #[test]
+    fn button_bits() {
+        for byte in 0..=u8::MAX {
+            assert_eq!(byte, buttons_to_byte(&byte_to_buttons(byte)));
+        }
+
+        let mut joypad_state = JoypadState::new();
+        joypad_state.set_button(Button::Start, true);
+        joypad_state.set_button(Button::Up, true);
+        assert_eq!(0x48, buttons_to_byte(&joypad_state));
+    }
+
+    #[test]
+    fn record_and_play_back() {
+        let path = test_movie_path();
+        let unsaved_state = UnsavedEmulatorState {
+            leftover_cpu_cycles: 2,
+            compatibility_palette_frames_remaining: 0,
+            cpu_accuracy: CpuAccuracy::Cycle,
+        };
+
+        let mut state = new_test_state();
+        state.cpu_registers.pc = 0x1234;
+        let mut movie =
+            Movie::start_recording(&path, MovieStart::PowerOn, &mut state, unsaved_state).unwrap();
+        assert_eq!(MovieStatus::Recording, movie.status());
+        let start_time = state.controller_states.rtc_emulated_time.borrow().unwrap();
+
+        let mut inputs = Vec::new();
+        for i in 0..10_u8 {
+            let input = byte_to_buttons(i.wrapping_mul(37));
+            state.controller_states.accelerometer_state.borrow_mut().x = u16::from(i);
+            assert_eq!(buttons_to_byte(&input), buttons_to_byte(&movie.next_input(input, &state)));
+            movie.end_frame(70224, &mut state);
+            inputs.push(i.wrapping_mul(37));
+        }
+
+        // 10 frames of 70224 cycles each
+        let end_time = start_time + Duration::from_nanos(167_427_062);
+        assert_eq!(Some(end_time), *state.controller_states.rtc_emulated_time.borrow());
+
+        movie.stop(&state).unwrap();
+        assert_eq!(None, *state.controller_states.rtc_emulated_time.borrow());
+
+        let mut state = new_test_state();
+        let (mut movie, playback_unsaved_state) =
+            Movie::start_playback(&path, &mut state, UnsavedEmulatorState::default()).unwrap();
+        assert_eq!(unsaved_state, playback_unsaved_state);
+        assert_eq!(0x1234, state.cpu_registers.pc);
+        assert_eq!(Some(start_time), *state.controller_states.rtc_emulated_time.borrow());
+
+        for (i, &buttons) in inputs.iter().enumerate() {
+            assert_eq!(MovieStatus::Playing, movie.status());
+            let input = movie.next_input(JoypadState::new(), &state);
+            assert_eq!(buttons, buttons_to_byte(&input));
+            assert_eq!(i as u16, state.controller_states.accelerometer_state.borrow().x);
+            movie.end_frame(70224, &mut state);
+        }
+        assert_eq!(MovieStatus::PlaybackFinished, movie.status());
+        assert_eq!(Some(end_time), *state.controller_states.rtc_emulated_time.borrow());
+
+        // Input comes from the frontend after playback finishes
+        let input = byte_to_buttons(0x01);
+        assert_eq!(0x01, buttons_to_byte(&movie.next_input(input, &state)));
+
+        let _ = fs::remove_file(&path);
+    }
+
+    #[test]
+    fn recording_written_on_drop() {
+        let path = test_movie_path().with_extension("drop.jgbm");
+
+        let mut state = new_test_state();
+        let mut movie = Movie::start_recording(
+            &path,
+            MovieStart::PowerOn,
+            &mut state,
+            UnsavedEmulatorState::default(),
+        )
+        .unwrap();
+        for _ in 0..3 {
+            movie.next_input(JoypadState::new(), &state);
+            movie.end_frame(70224, &mut state);
+        }
+        drop(movie);
+
+        let (movie, _) =
+            Movie::start_playback(&path, &mut new_test_state(), UnsavedEmulatorState::default())
+                .unwrap();
+        assert_eq!(3, movie.data.frames.len());
+
+        let _ = fs::remove_file(&path);
+    }
+
+    #[test]
+    fn imported_movie_resets_save_data() {
+        // MBC3+TIMER+RAM+BATTERY
+        let mut state = EmulationState::new_test_state(0x10, b"TEST");
+        let write = |state: &mut EmulationState, address, value| {
+            state.address_space.write_address_u8(address, value, &state.ppu_state);
+        };
+        let read_rtc_register = |state: &mut EmulationState, register| {
+            write(state, 0x4000, register);
+            state.address_space.read_address_u8(0xA000, &state.ppu_state)
+        };
+
+        // Enable RAM and write to it as if a save file had been loaded, and advance the RTC
+        write(&mut state, 0x0000, 0x0A);
+        write(&mut state, 0x4000, 0x00);
+        write(&mut state, 0xA000, 0x55);
+        *state.controller_states.rtc_emulated_time.borrow_mut() =
+            Some(SystemTime::now() + Duration::from_secs(300_007));
+        state.address_space.update_rtc();
+
+        let mut imported_movie = ImportedMovie::new(None);
+        imported_movie.rtc_initial_time = Duration::from_secs(90);
+        imported_movie.push_frame(0x00, false);
+        let data =
+            imported_movie.start_playback(&mut state, UnsavedEmulatorState::default()).unwrap();
+
+        let start_time = SystemTime::UNIX_EPOCH + Duration::from_secs(90);
+        assert_eq!(start_time, data.start_time);
+        assert_eq!(Some(start_time), *state.controller_states.rtc_emulated_time.borrow());
+
+        write(&mut state, 0x4000, 0x00);
+        assert_eq!(0x00, state.address_space.read_address_u8(0xA000, &state.ppu_state));
+
+        // Latch the RTC and check that it reads the initial time
+        write(&mut state, 0x6000, 0x00);
+        write(&mut state, 0x6000, 0x01);
+        assert_eq!(30, read_rtc_register(&mut state, 0x08));
+        assert_eq!(1, read_rtc_register(&mut state, 0x09));
+        assert_eq!(0, read_rtc_register(&mut state, 0x0B));
+    }
+
+    #[test]
+    fn desync_detection() {
+        let mut state = new_test_state();
+        let mut data = start_from_current_state(
+            MovieStart::PowerOn,
+            &mut state,
+            UnsavedEmulatorState::default(),
+            SystemTime::now(),
+        )
+        .unwrap();
+        data.frames = [0x00, 0x01, 0x01, 0x02, 0x02]
+            .map(|buttons| MovieFrame { buttons, accelerometer: AccelerometerState::default() })
+            .to_vec();
+        let mut movie = Movie::new(MovieMode::Playback, data, vec![4]);
+
+        // The game polls the joypad by selecting buttons or directions through JOYP
+        for polled in [true, true, false, false, true, false] {
+            movie.next_input(JoypadState::new(), &state);
+            if polled {
+                state.address_space.get_io_registers_mut().write_register(IoRegister::JOYP, 0x20);
+            }
+            movie.end_frame(70224, &mut state);
+        }
+
+        // Frame 3 changed input without a poll and frame 4 contains unsupported input. The frame
+        // after the end of the movie is not checked
+        assert_eq!(&[3, 4], movie.desync_frames());
+    }
+
+    #[test]
+    fn movie_format() {
+        assert!(matches!(read_movie_data(b"JGBS\x01\x00\x00\x00"), Err(MovieError::InvalidFormat)));
+        assert!(matches!(read_movie_data(b"JGBM\x01"), Err(MovieError::InvalidFormat)));
+        assert!(matches!(
+            read_movie_data(b"JGBM\x01\x00\x00\x00"),
+            Err(MovieError::IncompatibleVersion { version: 1 })
+        ));
+    }
+}
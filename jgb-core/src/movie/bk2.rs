@@ -0,0 +1,282 @@
+use crate::input::Button;
+use crate::movie::{self, ImportedMovie, MovieError};
+use std::io::{Cursor, Read};
+use std::time::Duration;
+use zip::ZipArchive;
+use zip::result::ZipError;
+
+const HEADER_FILE: &str = "Header.txt";
+const INPUT_LOG_FILE: &str = "Input Log.txt";
+const SYNC_SETTINGS_FILE: &str = "SyncSettings.json";
+
+const SUPPORTED_PLATFORMS: [&str; 2] = ["GB", "GBC"];
+
+// A single input from the input log key
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum LogInput {
+    Button(Button),
+    Power,
+    Other(String),
+}
+
+impl LogInput {
+    fn from_name(name: &str) -> Self {
+        // Cores that support multiple controllers prefix inputs with the player number
+        let name = name.strip_prefix("P1 ").unwrap_or(name);
+        match name {
+            "Up" => Self::Button(Button::Up),
+            "Down" => Self::Button(Button::Down),
+            "Left" => Self::Button(Button::Left),
+            "Right" => Self::Button(Button::Right),
+            "A" => Self::Button(Button::A),
+            "B" => Self::Button(Button::B),
+            "Start" => Self::Button(Button::Start),
+            "Select" => Self::Button(Button::Select),
+            "Power" | "Reset" => Self::Power,
+            _ => Self::Other(name.into()),
+        }
+    }
+}
+
+/// Parse a BizHawk movie, which is a zip archive containing a text header and a text input log.
+/// Only Game Boy and Game Boy Color movies that start from power-on are supported.
+pub(crate) fn parse(bytes: &[u8]) -> Result<ImportedMovie, MovieError> {
+    let mut archive = ZipArchive::new(Cursor::new(bytes))?;
+
+    let header = read_archive_file(&mut archive, HEADER_FILE)?.ok_or(MovieError::InvalidFormat)?;
+    check_header(&header)?;
+
+    let input_log =
+        read_archive_file(&mut archive, INPUT_LOG_FILE)?.ok_or(MovieError::InvalidFormat)?;
+    let mut movie = parse_input_log(&input_log)?;
+
+    if let Some(sync_settings) = read_archive_file(&mut archive, SYNC_SETTINGS_FILE)? {
+        movie.rtc_initial_time = rtc_initial_time(&sync_settings)?;
+    }
+
+    Ok(movie)
+}
+
+// Returns None if the archive does not contain the file
+fn read_archive_file(
+    archive: &mut ZipArchive<Cursor<&[u8]>>,
+    name: &str,
+) -> Result<Option<String>, MovieError> {
+    let mut file = match archive.by_name(name) {
+        Ok(file) => file,
+        Err(ZipError::FileNotFound) => return Ok(None),
+        Err(err) => return Err(err.into()),
+    };
+
+    let mut contents = String::new();
+    file.read_to_string(&mut contents)?;
+    Ok(Some(contents))
+}
+
+// BizHawk's Game Boy cores have an "RTCInitialTime" sync setting, which is the number of seconds
+// that the real-time clock reads at power-on. Sync settings are JSON, but this is the only value
+// that matters here, so look for it directly instead of parsing the whole file
+fn rtc_initial_time(sync_settings: &str) -> Result<Duration, MovieError> {
+    let Some((_, value)) = sync_settings.split_once("\"RTCInitialTime\"") else {
+        return Ok(Duration::ZERO);
+    };
+
+    let value = value.trim_start().strip_prefix(':').ok_or(MovieError::InvalidFormat)?.trim_start();
+    let value_len = value.find(|c: char| !c.is_ascii_digit()).unwrap_or(value.len());
+    let seconds = value[..value_len].parse().map_err(|_| MovieError::InvalidFormat)?;
+    Ok(Duration::from_secs(seconds))
+}
+
+// Header lines are a key and a value separated by a space
+fn check_header(header: &str) -> Result<(), MovieError> {
+    let mut platform = None;
+    for (key, value) in header.lines().filter_map(|line| line.trim_end().split_once(' ')) {
+        match key {
+            "Platform" => platform = Some(value),
+            "StartsFromSavestate" if value == "True" => {
+                return Err(MovieError::UnsupportedMovie {
+                    reason: "BK2 movies that start from a BizHawk save state are not supported"
+                        .into(),
+                });
+            }
+            "StartsFromSaveRam" if value == "True" => {
+                return Err(MovieError::UnsupportedMovie {
+                    reason: "BK2 movies that start with embedded save RAM are not supported".into(),
+                });
+            }
+            "GameName" | "Core" => log::info!("BK2 movie {key}: {value}"),
+            _ => {}
+        }
+    }
+
+    match platform {
+        Some(platform) if SUPPORTED_PLATFORMS.contains(&platform) => Ok(()),
+        Some(platform) => Err(MovieError::UnsupportedMovie {
+            reason: format!("BK2 movies for platform '{platform}' are not supported"),
+        }),
+        None => Err(MovieError::InvalidFormat),
+    }
+}
+
+// The input log contains a key line followed by one line per frame, e.g.:
+//   LogKey:#Up|Down|Left|Right|Start|Select|B|A|Power|
+//   |U...S..A.|
+// '#' starts a group of inputs, and each frame line has one '|'-separated field per group.
+// Buttons take up one character that is '.' if the button is not pressed, and analog inputs are a
+// number followed by a comma
+fn parse_input_log(input_log: &str) -> Result<ImportedMovie, MovieError> {
+    let mut log_key: Option<Vec<Vec<LogInput>>> = None;
+    let mut movie = ImportedMovie::new(None);
+
+    for line in input_log.lines().map(str::trim_end) {
+        if let Some(key) = line.strip_prefix("LogKey:") {
+            log_key = Some(
+                key.split('#')
+                    .filter(|group| !group.is_empty())
+                    .map(|group| {
+                        group
+                            .split('|')
+                            .filter(|name| !name.is_empty())
+                            .map(LogInput::from_name)
+                            .collect()
+                    })
+                    .collect(),
+            );
+        } else if line.starts_with('|') {
+            let Some(log_key) = &log_key else {
+                return Err(MovieError::InvalidFormat);
+            };
+            let (buttons, unsupported) = parse_frame(line, log_key)?;
+            movie.push_frame(buttons, unsupported);
+        }
+    }
+
+    Ok(movie)
+}
+
+// Returns the frame's button byte and whether the frame uses any inputs other than buttons
+fn parse_frame(line: &str, log_key: &[Vec<LogInput>]) -> Result<(u8, bool), MovieError> {
+    let fields: Vec<_> = line.trim_matches('|').split('|').collect();
+    if fields.len() != log_key.len() {
+        return Err(MovieError::InvalidFormat);
+    }
+
+    let mut buttons = 0;
+    let mut unsupported = false;
+    for (field, group) in fields.into_iter().zip(log_key) {
+        let mut remaining = field;
+        for input in group {
+            let is_analog = remaining
+                .starts_with(|c: char| c.is_ascii_digit() || c == ' ' || c == '-')
+                && remaining.contains(',');
+            let active = if is_analog {
+                let (value, rest) = remaining.split_once(',').ok_or(MovieError::InvalidFormat)?;
+                remaining = rest;
+                value.trim().parse::<i32>().map_err(|_| MovieError::InvalidFormat)? != 0
+            } else {
+                let mut chars = remaining.chars();
+                let c = chars.next().ok_or(MovieError::InvalidFormat)?;
+                remaining = chars.as_str();
+                c != '.'
+            };
+
+            if !active {
+                continue;
+            }
+
+            match input {
+                LogInput::Button(button) => buttons |= movie::button_bit(*button),
+                LogInput::Power | LogInput::Other(_) => unsupported = true,
+            }
+        }
+    }
+
+    Ok((buttons, unsupported))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use zip::ZipWriter;
+    use zip::write::SimpleFileOptions;
+
+    fn bk2_bytes(header: &str, input_log: &str) -> Vec<u8> {
+        bk2_bytes_with_files(&[(HEADER_FILE, header), (INPUT_LOG_FILE, input_log)])
+    }
+
+    fn bk2_bytes_with_files(files: &[(&str, &str)]) -> Vec<u8> {
+        let mut writer = ZipWriter::new(Cursor::new(Vec::new()));
+        let options =
+            SimpleFileOptions::default().compression_method(zip::CompressionMethod::Stored);
+        for (name, contents) in files {
+            writer.start_file(*name, options).unwrap();
+            writer.write_all(contents.as_bytes()).unwrap();
+        }
+        writer.finish().unwrap().into_inner()
+    }
+
+    fn frame_buttons(movie: &ImportedMovie) -> Vec<u8> {
+        movie.frames.iter().map(|frame| frame.buttons).collect()
+    }
+
+    #[test]
+    fn parse_gambatte_movie() {
+        let header = "MovieVersion BizHawk v2.0.0\nPlatform GB\nGameName Test\nCore Gambatte\n";
+        let input_log = "[Input]\r\nLogKey:#Up|Down|Left|Right|Start|Select|B|A|Power|\r\n\
+                         |.........|\r\n|U...S..A.|\r\n|........P|\r\n[/Input]\r\n";
+
+        let movie = parse(&bk2_bytes(header, input_log)).unwrap();
+        assert_eq!(vec![0x00, 0x49, 0x00], frame_buttons(&movie));
+        assert_eq!(vec![2], movie.unsupported_frames);
+    }
+
+    #[test]
+    fn parse_grouped_movie() {
+        // Inputs can be split into groups, and analog inputs are numbers followed by commas
+        let header = "Platform GBC\n";
+        let input_log = "LogKey:#P1 Tilt X|P1 Tilt Y|#P1 Up|P1 Down|P1 Left|P1 Right|P1 Start|P1 Select|P1 B|P1 A|P1 Power|\n\
+                         |    0,    0,|...R...A.|\n|   12,  -40,|.........|\n";
+
+        let movie = parse(&bk2_bytes(header, input_log)).unwrap();
+        assert_eq!(vec![0x11, 0x00], frame_buttons(&movie));
+        assert_eq!(vec![1], movie.unsupported_frames);
+    }
+
+    #[test]
+    fn parse_rtc_initial_time() {
+        let header = "Platform GB\n";
+        let input_log = "LogKey:#Up|Down|Left|Right|Start|Select|B|A|Power|\n|.........|\n";
+        let sync_settings = r#"{"o":{"$type":"GambatteSyncSettings","EnableBIOS":false,"RTCInitialTime": 5000,"RTCDivisorOffset":0}}"#;
+
+        let movie = parse(&bk2_bytes_with_files(&[
+            (HEADER_FILE, header),
+            (INPUT_LOG_FILE, input_log),
+            (SYNC_SETTINGS_FILE, sync_settings),
+        ]))
+        .unwrap();
+        assert_eq!(Duration::from_secs(5000), movie.rtc_initial_time);
+
+        // The RTC starts at 0 if the setting is missing
+        let movie = parse(&bk2_bytes(header, input_log)).unwrap();
+        assert_eq!(Duration::ZERO, movie.rtc_initial_time);
+    }
+
+    #[test]
+    fn unsupported_movies() {
+        let input_log = "LogKey:#Up|Down|Left|Right|Start|Select|B|A|Power|\n";
+        assert!(matches!(
+            parse(&bk2_bytes("Platform NES\n", input_log)),
+            Err(MovieError::UnsupportedMovie { .. })
+        ));
+        assert!(matches!(
+            parse(&bk2_bytes("Platform GB\nStartsFromSavestate True\n", input_log)),
+            Err(MovieError::UnsupportedMovie { .. })
+        ));
+        assert!(matches!(
+            parse(&bk2_bytes("Platform GB\n", "|....|\n")),
+            Err(MovieError::InvalidFormat)
+        ));
+        assert!(matches!(parse(b"not a zip file"), Err(MovieError::Bk2Archive { .. })));
+    }
+}
@@ -0,0 +1,171 @@
+use crate::movie::{ImportedMovie, MovieError};
+
+const SIGNATURE: [u8; 4] = *b"VBM\x1A";
+const HEADER_LEN: usize = 0x40;
+
+// Header field offsets
+const VERSION: usize = 0x04;
+const FRAME_COUNT: usize = 0x0C;
+const START_FLAGS: usize = 0x14;
+const CONTROLLER_FLAGS: usize = 0x15;
+const SYSTEM_FLAGS: usize = 0x16;
+const ROM_TITLE: usize = 0x24;
+const ROM_CHECKSUM: usize = 0x32;
+const CONTROLLER_DATA_OFFSET: usize = 0x3C;
+
+// Start flags
+const START_FROM_SNAPSHOT: u8 = 0x01;
+const START_FROM_SRAM: u8 = 0x02;
+
+// System flags
+const SYSTEM_GBA: u8 = 0x01;
+
+// The low byte of each frame's controller data uses the same button order as jgb's native movies.
+// Bits 10 and 11 are resets and bits 12-15 are motion sensor directions
+const RESET_BITS: u16 = 0x0C00;
+const MOTION_BITS: u16 = 0xF000;
+
+/// Parse a VisualBoyAdvance movie. Only movies that start from power-on without embedded SRAM
+/// are supported, and only the first controller's input is used.
+pub(crate) fn parse(bytes: &[u8]) -> Result<ImportedMovie, MovieError> {
+    if bytes.len() < HEADER_LEN || bytes[..4] != SIGNATURE {
+        return Err(MovieError::InvalidFormat);
+    }
+
+    let version = read_u32(bytes, VERSION);
+    if version != 1 {
+        return Err(MovieError::IncompatibleVersion { version });
+    }
+
+    let start_flags = bytes[START_FLAGS];
+    if start_flags & START_FROM_SNAPSHOT != 0 {
+        return Err(unsupported("VBM movies that start from a VisualBoyAdvance save state"));
+    }
+    if start_flags & START_FROM_SRAM != 0 {
+        return Err(unsupported("VBM movies that start with embedded SRAM"));
+    }
+
+    if bytes[SYSTEM_FLAGS] & SYSTEM_GBA != 0 {
+        return Err(unsupported("Game Boy Advance movies"));
+    }
+
+    let controller_flags = bytes[CONTROLLER_FLAGS] & 0x0F;
+    if controller_flags & 0x01 == 0 {
+        return Err(unsupported("VBM movies that do not use controller 1"));
+    }
+    // Every controller in use gets 2 bytes per frame
+    let frame_len = 2 * controller_flags.count_ones() as usize;
+
+    let frame_count = read_u32(bytes, FRAME_COUNT) as usize;
+    let data_offset = read_u32(bytes, CONTROLLER_DATA_OFFSET) as usize;
+    let Some(controller_data) = frame_count
+        .checked_mul(frame_len)
+        .and_then(|data_len| bytes.get(data_offset..data_offset.checked_add(data_len)?))
+    else {
+        return Err(MovieError::InvalidFormat);
+    };
+
+    let rom_title: String = bytes[ROM_TITLE..ROM_TITLE + 12]
+        .iter()
+        .take_while(|&&byte| byte != 0)
+        .map(|&byte| char::from(byte))
+        .collect();
+    // VBA writes the checksum as the little-endian u16 rom[0x14E] | (rom[0x14F] << 8), so the
+    // bytes are in the same order as in the ROM header, where the checksum is big-endian
+    let rom_checksum = u16::from_be_bytes([bytes[ROM_CHECKSUM], bytes[ROM_CHECKSUM + 1]]);
+
+    let mut movie = ImportedMovie::new(Some((rom_title, rom_checksum)));
+    for frame_data in controller_data.chunks_exact(frame_len) {
+        let input = u16::from_le_bytes([frame_data[0], frame_data[1]]);
+        movie.push_frame(input as u8, input & (RESET_BITS | MOTION_BITS) != 0);
+    }
+
+    Ok(movie)
+}
+
+fn unsupported(what: &str) -> MovieError {
+    MovieError::UnsupportedMovie { reason: format!("{what} are not supported") }
+}
+
+fn read_u32(bytes: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes([bytes[offset], bytes[offset + 1], bytes[offset + 2], bytes[offset + 3]])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::memory::address;
+    use crate::serialize;
+
+    // The header of a ROM titled TEST with global checksum 0x1234
+    fn rom_header() -> Vec<u8> {
+        let mut header = vec![0; 0x0150];
+        header[address::TITLE_START as usize..][..4].copy_from_slice(b"TEST");
+        header[address::GLOBAL_CHECKSUM as usize] = 0x12;
+        header[address::GLOBAL_CHECKSUM as usize + 1] = 0x34;
+        header
+    }
+
+    fn vbm_bytes(start_flags: u8, controller_flags: u8, frames: &[u16]) -> Vec<u8> {
+        let mut bytes = vec![0; HEADER_LEN + 192];
+        bytes[..4].copy_from_slice(&SIGNATURE);
+        bytes[VERSION..VERSION + 4].copy_from_slice(&1_u32.to_le_bytes());
+        bytes[START_FLAGS] = start_flags;
+        bytes[CONTROLLER_FLAGS] = controller_flags;
+        bytes[ROM_TITLE..ROM_TITLE + 4].copy_from_slice(b"TEST");
+
+        // Written the same way as VBA does
+        let rom_header = rom_header();
+        let checksum_address = address::GLOBAL_CHECKSUM as usize;
+        let vba_checksum = u16::from(rom_header[checksum_address])
+            | (u16::from(rom_header[checksum_address + 1]) << 8);
+        bytes[ROM_CHECKSUM..ROM_CHECKSUM + 2].copy_from_slice(&vba_checksum.to_le_bytes());
+
+        let controllers = controller_flags.count_ones() as usize;
+        let frame_count = frames.len() / controllers;
+        bytes[FRAME_COUNT..FRAME_COUNT + 4].copy_from_slice(&(frame_count as u32).to_le_bytes());
+        let data_offset = bytes.len() as u32;
+        bytes[CONTROLLER_DATA_OFFSET..CONTROLLER_DATA_OFFSET + 4]
+            .copy_from_slice(&data_offset.to_le_bytes());
+
+        for frame in frames {
+            bytes.extend(frame.to_le_bytes());
+        }
+        bytes
+    }
+
+    #[test]
+    fn parse_frames() {
+        let movie = parse(&vbm_bytes(0, 0x01, &[0x0000, 0x0009, 0x0080, 0x0400])).unwrap();
+        assert_eq!(Some(serialize::rom_identity(&rom_header())), movie.rom_identity);
+        assert_eq!(Some(("TEST".into(), 0x1234)), movie.rom_identity);
+        assert_eq!(
+            vec![0x00, 0x09, 0x80, 0x00],
+            movie.frames.iter().map(|frame| frame.buttons).collect::<Vec<_>>()
+        );
+        assert_eq!(vec![3], movie.unsupported_frames);
+
+        // Only controller 1's input is used
+        let movie = parse(&vbm_bytes(0, 0x03, &[0x0001, 0x0002, 0x0004, 0x0008])).unwrap();
+        assert_eq!(
+            vec![0x01, 0x04],
+            movie.frames.iter().map(|frame| frame.buttons).collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn invalid_movies() {
+        assert!(matches!(
+            parse(&vbm_bytes(START_FROM_SNAPSHOT, 0x01, &[])),
+            Err(MovieError::UnsupportedMovie { .. })
+        ));
+        assert!(matches!(
+            parse(&vbm_bytes(START_FROM_SRAM, 0x01, &[])),
+            Err(MovieError::UnsupportedMovie { .. })
+        ));
+
+        let bytes = vbm_bytes(0, 0x01, &[0x0001, 0x0002]);
+        assert!(matches!(parse(&bytes[..bytes.len() - 1]), Err(MovieError::InvalidFormat)));
+        assert!(matches!(parse(&bytes[1..]), Err(MovieError::InvalidFormat)));
+    }
+}
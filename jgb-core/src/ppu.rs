@@ -1,6 +1,9 @@
+mod compatibility;
+
 use crate::cpu::{ExecutionMode, InterruptType};
 use crate::memory::ioregisters::{IoRegister, IoRegisters, SpriteMode, TileDataRange};
 use crate::memory::{AddressSpace, VramBank, address};
+pub use compatibility::{CompatibilityPalettes, palettes_for_buttons, palettes_for_cartridge};
 use serde::{Deserialize, Serialize};
 use std::array;
 use std::collections::VecDeque;
@@ -14,12 +17,25 @@ pub const SCREEN_HEIGHT: u8 = 144;
 const DOTS_PER_M_CYCLE: u32 = 4;
 const DOTS_PER_SCANLINE: u32 = 456;
 const OAM_SCAN_DOTS: u32 = 80;
-const MIN_RENDER_DOTS: u32 = 172;
+
+// Mode 3 lasts for 172 dots when SCX is a multiple of 8, the window is not visible, and there are no
+// sprites on the current scanline. Each of those adds a delay to the pixel FIFO.
+const BASE_RENDER_DOTS: u32 = 172;
+const WINDOW_FETCH_DOTS: u32 = 6;
+const SPRITE_FETCH_DOTS: u32 = 6;
+// A sprite at OAM X=0 always incurs the maximum penalty
+const MAX_SPRITE_FETCH_DOTS: u32 = 11;
+// Sprites at OAM X>=168 are entirely offscreen and are never fetched
+const SPRITE_FETCH_X_LIMIT: u8 = 168;
 
 const LAST_VBLANK_SCANLINE: u8 = 153;
 
 const MAX_SPRITES_PER_SCANLINE: usize = 10;
 
+// OAM is organized as 20 rows of 8 bytes each, and the PPU reads 1 row per M-cycle while scanning
+const OAM_ROW_LEN: usize = 8;
+const OAM_ROWS: usize = 20;
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum PpuMode {
     HBlank,
@@ -152,6 +168,8 @@ struct RenderingScanlineStateData {
     window_y: u8,
     window_internal_y: Option<u8>,
     window_ends_line: bool,
+    // The dot at which this scanline's mode 3 ends and HBlank begins
+    render_end_dot: u32,
     sprites: Vec<(OamSpriteData, TileData)>,
     bg_pixel_queue: VecDeque<QueuedBgPixel>,
     sprite_pixel_queue: VecDeque<QueuedObjPixel>,
@@ -206,6 +224,9 @@ pub struct OamDmaStatus {
 
 impl OamDmaStatus {
     pub fn new(source_high_bits: u8) -> Self {
+        // The DMA unit reads sources in 0xE000-0xFFFF from working RAM, the same as echo RAM
+        let source_high_bits =
+            if source_high_bits >= 0xE0 { source_high_bits - 0x20 } else { source_high_bits };
         Self { source_high_bits: u16::from(source_high_bits) << 8, current_low_bits: 0x00 }
     }
 
@@ -281,6 +302,8 @@ pub struct PpuState {
     enabled: bool,
     state: State,
     oam_dma_status: Option<OamDmaStatus>,
+    // Set to the source of an OAM DMA transfer during the M-cycle when the CPU writes to DMA
+    oam_dma_request: Option<u8>,
     vram_dma_status: Option<VramDmaStatus>,
     #[serde(skip)]
     #[serde(default = "new_frame_buffer")]
@@ -306,6 +329,7 @@ impl PpuState {
                 sprites: Vec::new(),
             }),
             oam_dma_status: None,
+            oam_dma_request: None,
             vram_dma_status: None,
             frame_buffer: new_frame_buffer(),
             last_stat_interrupt_line: false,
@@ -318,6 +342,12 @@ impl PpuState {
         self.enabled
     }
 
+    /// Change the execution mode, which happens when the CGB boot ROM switches the hardware into
+    /// DMG compatibility mode.
+    pub fn set_execution_mode(&mut self, execution_mode: ExecutionMode) {
+        self.execution_mode = execution_mode;
+    }
+
     pub fn mode(&self) -> PpuMode {
         self.state.mode()
     }
@@ -351,27 +381,28 @@ impl PpuState {
 /// copying one byte and incrementing the OAM DMA status. If the transfer has completed then the
 /// OAM DMA status will be set to None.
 ///
-/// If an OAM DMA transfer is not in progress but has been requested, the transfer will be
-/// initialized and the first byte will be copied.
+/// Transfers start after a 1 M-cycle delay: nothing is copied during the M-cycle when the CPU
+/// writes to the DMA register or during the following M-cycle, and the first byte is copied during
+/// the M-cycle after that. If a transfer is already in progress when the CPU writes to DMA, the
+/// existing transfer continues during the delay and is then replaced by the new transfer.
 pub fn progress_oam_dma_transfer(ppu_state: &mut PpuState, address_space: &mut AddressSpace) {
-    if ppu_state.oam_dma_status.is_none()
-        && address_space.get_io_registers().get_dirty_bit(IoRegister::DMA)
-    {
-        address_space.get_io_registers_mut().clear_dirty_bit(IoRegister::DMA);
-
-        let source_high_bits = address_space.get_io_registers().read_register(IoRegister::DMA);
-        if source_high_bits <= 0xDF {
-            ppu_state.oam_dma_status = Some(OamDmaStatus::new(source_high_bits));
-        }
+    if let Some(oam_dma_status) = ppu_state.oam_dma_status {
+        address_space.copy_byte(
+            oam_dma_status.current_source_address(),
+            oam_dma_status.current_dest_address(),
+        );
+        ppu_state.oam_dma_status = oam_dma_status.increment();
     }
 
-    let Some(oam_dma_status) = ppu_state.oam_dma_status else {
-        return;
-    };
+    if let Some(source_high_bits) = ppu_state.oam_dma_request.take() {
+        ppu_state.oam_dma_status = Some(OamDmaStatus::new(source_high_bits));
+    }
 
-    address_space
-        .copy_byte(oam_dma_status.current_source_address(), oam_dma_status.current_dest_address());
-    ppu_state.oam_dma_status = oam_dma_status.increment();
+    if address_space.get_io_registers().get_dirty_bit(IoRegister::DMA) {
+        address_space.get_io_registers_mut().clear_dirty_bit(IoRegister::DMA);
+        ppu_state.oam_dma_request =
+            Some(address_space.get_io_registers().read_register(IoRegister::DMA));
+    }
 }
 
 /// Progress VRAM DMA transfer by one byte. This should be called twice per PPU M-cycle.
@@ -485,10 +516,11 @@ pub fn tick_m_cycle(ppu_state: &mut PpuState, address_space: &mut AddressSpace)
     if prev_enabled && !enabled {
         // If the PPU was just disabled then clear the frame buffer, clear the LY=LYC and mode bits
         // in STAT, and set LY to 0
+        let dmg_compatibility_mode = address_space.get_io_registers().dmg_compatibility_mode();
         ppu_state.frame_buffer = Box::new(array::from_fn(|_| {
             array::from_fn(|_| match ppu_state.execution_mode {
-                ExecutionMode::GameBoy => 0,
-                ExecutionMode::GameBoyColor => 0xFFFF,
+                ExecutionMode::GameBoy if !dmg_compatibility_mode => 0,
+                ExecutionMode::GameBoy | ExecutionMode::GameBoyColor => 0xFFFF,
             })
         }));
 
@@ -563,7 +595,7 @@ pub fn tick_m_cycle(ppu_state: &mut PpuState, address_space: &mut AddressSpace)
     update_stat_register(address_space.get_io_registers_mut(), lyc_match, new_mode);
 
     let stat_interrupt_line =
-        compute_stat_interrupt_line(address_space.get_io_registers(), lyc_match, new_mode);
+        compute_stat_interrupt_line(address_space.get_io_registers(), lyc_match, &new_state);
     if !ppu_state.last_stat_interrupt_line && stat_interrupt_line {
         ppu_state.stat_interrupt_pending = true;
     }
@@ -574,6 +606,113 @@ pub fn tick_m_cycle(ppu_state: &mut PpuState, address_space: &mut AddressSpace)
     ppu_state.last_stat_interrupt_line = stat_interrupt_line;
 }
 
+/// The kind of CPU bus activity that triggered the DMG OAM corruption bug. Each kind corrupts OAM
+/// using a different pattern.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OamBugAccess {
+    /// A read from the OAM address range.
+    Read,
+    /// A write to the OAM address range, or the 16-bit increment/decrement unit placing an OAM
+    /// address on the bus (INC rr, DEC rr, and the SP decrement before a stack push).
+    Write,
+    /// A read from the OAM address range in the same M-cycle that the address is incremented or
+    /// decremented, as in POP and LD A,(HL+).
+    ReadIncrement,
+}
+
+/// Emulate the DMG OAM corruption bug, where the CPU placing an address in 0xFE00-0xFEFF on the
+/// address bus while the PPU is scanning OAM corrupts the OAM row that the PPU is currently
+/// reading. The corruption pattern depends on the type of access.
+///
+/// This function does nothing on CGB hardware, which does not have this bug.
+pub fn trigger_oam_bug(
+    ppu_state: &PpuState,
+    address_space: &mut AddressSpace,
+    address: u16,
+    access: OamBugAccess,
+) {
+    if ppu_state.execution_mode != ExecutionMode::GameBoy
+        || address_space.get_io_registers().dmg_compatibility_mode()
+        || !ppu_state.enabled
+        || !(address::OAM_START..=address::UNUSABLE_END).contains(&address)
+    {
+        return;
+    }
+
+    let State::ScanningOAM(ScanningOAMStateData { dot, .. }) = &ppu_state.state else {
+        return;
+    };
+    let row = (dot / DOTS_PER_M_CYCLE) as usize;
+
+    let oam = address_space.get_oam_mut();
+    match access {
+        OamBugAccess::Read => corrupt_oam_read(oam, row),
+        OamBugAccess::Write => corrupt_oam_write(oam, row),
+        OamBugAccess::ReadIncrement => {
+            // The first 4 rows and the last row are not affected by the extra corruption, but they
+            // still get the read corruption
+            if (4..OAM_ROWS - 1).contains(&row) {
+                let a = oam_word(oam, row - 2, 0);
+                let b = oam_word(oam, row - 1, 0);
+                let c = oam_word(oam, row, 0);
+                let d = oam_word(oam, row - 1, 2);
+                set_oam_word(oam, row - 1, 0, (b & (a | c | d)) | (a & c & d));
+
+                let preceding_row = oam_row_range(row - 1);
+                oam.copy_within(preceding_row.clone(), oam_row_range(row).start);
+                oam.copy_within(preceding_row, oam_row_range(row - 2).start);
+            }
+            corrupt_oam_read(oam, row);
+        }
+    }
+}
+
+fn corrupt_oam_read(oam: &mut [u8; 160], row: usize) {
+    // The first row is never corrupted
+    if row == 0 {
+        return;
+    }
+
+    let a = oam_word(oam, row, 0);
+    let b = oam_word(oam, row - 1, 0);
+    let c = oam_word(oam, row - 1, 2);
+    set_oam_word(oam, row, 0, b | (a & c));
+    copy_last_oam_words(oam, row);
+}
+
+fn corrupt_oam_write(oam: &mut [u8; 160], row: usize) {
+    // The first row is never corrupted
+    if row == 0 {
+        return;
+    }
+
+    let a = oam_word(oam, row, 0);
+    let b = oam_word(oam, row - 1, 0);
+    let c = oam_word(oam, row - 1, 2);
+    set_oam_word(oam, row, 0, ((a ^ c) & (b ^ c)) ^ c);
+    copy_last_oam_words(oam, row);
+}
+
+// Copy the last 3 words of the preceding row to the given row
+fn copy_last_oam_words(oam: &mut [u8; 160], row: usize) {
+    let preceding_row = oam_row_range(row - 1);
+    oam.copy_within(preceding_row.start + 2..preceding_row.end, oam_row_range(row).start + 2);
+}
+
+fn oam_row_range(row: usize) -> std::ops::Range<usize> {
+    row * OAM_ROW_LEN..(row + 1) * OAM_ROW_LEN
+}
+
+fn oam_word(oam: &[u8; 160], row: usize, word: usize) -> u16 {
+    let address = row * OAM_ROW_LEN + 2 * word;
+    u16::from_le_bytes([oam[address], oam[address + 1]])
+}
+
+fn set_oam_word(oam: &mut [u8; 160], row: usize, word: usize, value: u16) {
+    let address = row * OAM_ROW_LEN + 2 * word;
+    oam[address..address + 2].copy_from_slice(&value.to_le_bytes());
+}
+
 fn update_stat_register(io_registers: &mut IoRegisters, lyc_match: bool, mode: PpuMode) {
     let mode_bits = mode.flag_bits();
 
@@ -583,8 +722,13 @@ fn update_stat_register(io_registers: &mut IoRegisters, lyc_match: bool, mode: P
     io_registers.ppu_set_stat(new_stat);
 }
 
-fn compute_stat_interrupt_line(io_registers: &IoRegisters, lyc_match: bool, mode: PpuMode) -> bool {
+// All enabled STAT sources are ORed into a single interrupt line, and a STAT interrupt is only
+// requested when that line goes from low to high. If one source becomes active while another is
+// already active (e.g. LY=LYC during the HBlank-to-OAM-scan transition), the line never goes low
+// and no additional interrupt is requested.
+fn compute_stat_interrupt_line(io_registers: &IoRegisters, lyc_match: bool, state: &State) -> bool {
     let stat = io_registers.read_register(IoRegister::STAT);
+    let mode = state.mode();
 
     let lyc_source = stat & 0x40 != 0;
     let scanning_oam_source = stat & 0x20 != 0;
@@ -592,7 +736,9 @@ fn compute_stat_interrupt_line(io_registers: &IoRegisters, lyc_match: bool, mode
     let hblank_source = stat & 0x08 != 0;
 
     (lyc_source && lyc_match)
-        || (scanning_oam_source && mode == PpuMode::ScanningOAM)
+        // The mode 2 source is also active on the first M-cycle of VBlank even though the PPU does
+        // not scan OAM on that line
+        || (scanning_oam_source && (mode == PpuMode::ScanningOAM || *state == VBLANK_START))
         || (vblank_source && mode == PpuMode::VBlank)
         || (hblank_source && mode == PpuMode::HBlank)
 }
@@ -679,6 +825,14 @@ fn process_scanning_oam_state(
 
         // Read WY only once per scanline
         let window_y = address_space.get_io_registers().read_register(IoRegister::WY);
+
+        let render_dots = compute_render_dots(
+            address_space.get_io_registers(),
+            &sprites_with_tiles,
+            scanline,
+            window_y,
+        );
+
         State::RenderingScanline(RenderingScanlineStateData {
             scanline,
             pixel: 0,
@@ -688,6 +842,7 @@ fn process_scanning_oam_state(
             window_y,
             window_internal_y,
             window_ends_line: false,
+            render_end_dot: new_dot + render_dots,
             sprites: sprites_with_tiles,
             bg_pixel_queue: VecDeque::with_capacity(16),
             sprite_pixel_queue: VecDeque::with_capacity(16),
@@ -732,41 +887,112 @@ fn scan_oam(
     }
 }
 
-// This function is not even remotely cycle-accurate but it attempts to approximate the pixel queue
-// behavior of actual hardware
+// Compute the length of mode 3 for the current scanline in dots, based on the SCX fine scroll,
+// whether the window is visible on this scanline, and the sprites that need to be fetched
+fn compute_render_dots(
+    io_registers: &IoRegisters,
+    sprites: &[(OamSpriteData, TileData)],
+    scanline: u8,
+    window_y: u8,
+) -> u32 {
+    let lcdc = io_registers.lcdc();
+    let viewport_x = io_registers.read_register(IoRegister::SCX);
+    let window_x_plus_7 = io_registers.read_register(IoRegister::WX);
+
+    // The PPU discards the first SCX % 8 pixels of the first BG tile
+    let mut render_dots = BASE_RENDER_DOTS + u32::from(viewport_x % 8);
+
+    // Starting the window requires an additional BG fetch
+    let window_visible =
+        lcdc.window_enabled() && scanline >= window_y && window_x_plus_7 < SCREEN_WIDTH + 7;
+    if window_visible {
+        render_dots += WINDOW_FETCH_DOTS;
+    }
+
+    if !lcdc.sprites_enabled() {
+        return render_dots;
+    }
+
+    let mut sprite_x_positions: ArrayVec<[u8; MAX_SPRITES_PER_SCANLINE]> = sprites
+        .iter()
+        .map(|(sprite, _)| sprite.x_pos)
+        .filter(|&x_pos| x_pos < SPRITE_FETCH_X_LIMIT)
+        .collect();
+    sprite_x_positions.sort_unstable();
+
+    // Each sprite fetch waits for the BG fetcher to finish the tile that contains the sprite's
+    // leftmost pixel, but only the first sprite in a given tile has to wait
+    let mut last_waited_tile = None;
+    for x_pos in sprite_x_positions {
+        if x_pos == 0 {
+            render_dots += MAX_SPRITE_FETCH_DOTS;
+            continue;
+        }
+
+        let sprite_left = i32::from(x_pos) - 8;
+        let window_left = i32::from(window_x_plus_7) - 7;
+        let (tile, tile_offset) = if window_visible && sprite_left >= window_left {
+            let window_x = sprite_left - window_left;
+            ((true, window_x.div_euclid(8)), window_x.rem_euclid(8))
+        } else {
+            let bg_x = sprite_left + i32::from(viewport_x % 8);
+            ((false, bg_x.div_euclid(8)), bg_x.rem_euclid(8))
+        };
+
+        if last_waited_tile != Some(tile) {
+            // The wait is the number of the tile's pixels to the right of the sprite's leftmost
+            // pixel, minus 2
+            render_dots += (5 - tile_offset).max(0) as u32;
+            last_waited_tile = Some(tile);
+        }
+
+        render_dots += SPRITE_FETCH_DOTS;
+    }
+
+    render_dots
+}
+
+// Rendering is not even remotely cycle-accurate, but it attempts to approximate the pixel queue
+// behavior of actual hardware. The timing of mode 3 is exact regardless; if rendering has not
+// finished by the time mode 3 ends, the rest of the scanline is rendered immediately.
 fn process_render_state(
     execution_mode: ExecutionMode,
     mut state_data: RenderingScanlineStateData,
     address_space: &AddressSpace,
     frame_buffer: &mut FrameBuffer,
 ) -> State {
-    let RenderingScanlineStateData {
-        scanline,
-        pixel,
-        dot,
-        window_internal_y,
-        window_ends_line,
-        ..
-    } = state_data;
+    let new_dot = state_data.dot + DOTS_PER_M_CYCLE;
 
-    // If we've finished this scanline, do nothing and simply advance to HBlank (if possible)
-    if pixel == SCREEN_WIDTH {
-        if dot >= MIN_RENDER_DOTS {
-            return State::HBlank {
-                scanline,
-                dot: dot + DOTS_PER_M_CYCLE,
-                window_internal_y: match (window_internal_y, window_ends_line) {
-                    (Some(window_internal_y), true) => Some(window_internal_y + 1),
-                    _ => window_internal_y,
-                },
-            };
+    if new_dot >= state_data.render_end_dot {
+        while state_data.pixel < SCREEN_WIDTH {
+            state_data = render_m_cycle(execution_mode, state_data, address_space, frame_buffer);
         }
-        return State::RenderingScanline(RenderingScanlineStateData {
-            dot: dot + DOTS_PER_M_CYCLE,
-            ..state_data
-        });
+
+        let RenderingScanlineStateData { scanline, window_internal_y, window_ends_line, .. } =
+            state_data;
+        return State::HBlank {
+            scanline,
+            dot: new_dot,
+            window_internal_y: match (window_internal_y, window_ends_line) {
+                (Some(window_internal_y), true) => Some(window_internal_y + 1),
+                _ => window_internal_y,
+            },
+        };
+    }
+
+    if state_data.pixel < SCREEN_WIDTH {
+        state_data = render_m_cycle(execution_mode, state_data, address_space, frame_buffer);
     }
 
+    State::RenderingScanline(RenderingScanlineStateData { dot: new_dot, ..state_data })
+}
+
+fn render_m_cycle(
+    execution_mode: ExecutionMode,
+    state_data: RenderingScanlineStateData,
+    address_space: &AddressSpace,
+    frame_buffer: &mut FrameBuffer,
+) -> RenderingScanlineStateData {
     log::trace!("LCDC: {:02X}", address_space.get_io_registers().read_register(IoRegister::LCDC));
 
     // If both pixel queues are full enough, render pixels to the frame buffer until one of the
@@ -775,13 +1001,8 @@ fn process_render_state(
         return render_to_frame_buffer(execution_mode, state_data, address_space, frame_buffer);
     }
 
-    state_data = populate_bg_pixel_queue(execution_mode, state_data, address_space);
-    state_data = populate_sprite_pixel_queue(state_data, address_space);
-
-    State::RenderingScanline(RenderingScanlineStateData {
-        dot: state_data.dot + DOTS_PER_M_CYCLE,
-        ..state_data
-    })
+    let state_data = populate_bg_pixel_queue(execution_mode, state_data, address_space);
+    populate_sprite_pixel_queue(state_data, address_space)
 }
 
 fn render_to_frame_buffer(
@@ -789,7 +1010,7 @@ fn render_to_frame_buffer(
     state_data: RenderingScanlineStateData,
     address_space: &AddressSpace,
     frame_buffer: &mut FrameBuffer,
-) -> State {
+) -> RenderingScanlineStateData {
     let RenderingScanlineStateData {
         scanline,
         mut pixel,
@@ -799,6 +1020,7 @@ fn render_to_frame_buffer(
         window_y,
         window_internal_y,
         window_ends_line,
+        render_end_dot,
         sprites,
         mut bg_pixel_queue,
         mut sprite_pixel_queue,
@@ -812,6 +1034,10 @@ fn render_to_frame_buffer(
     let cgb_bg_palettes = io_registers.get_bg_palette_ram();
     let cgb_obj_palettes = io_registers.get_obj_palette_ram();
 
+    // In DMG compatibility mode, the DMG palettes map to shades which then select colors from the
+    // first BG palette and the first two OBJ palettes
+    let dmg_compatibility_mode = io_registers.dmg_compatibility_mode();
+
     let bg_enabled = address_space.get_io_registers().lcdc().bg_enabled();
 
     while !bg_pixel_queue.is_empty() && !sprite_pixel_queue.is_empty() && pixel < SCREEN_WIDTH {
@@ -843,6 +1069,10 @@ fn render_to_frame_buffer(
                 && (sprite_pixel.bg_over_obj || bg_pixel.bg_priority))
         {
             match execution_mode {
+                ExecutionMode::GameBoy if dmg_compatibility_mode => {
+                    let shade = get_bg_pixel_color_gb(bg_pixel.color_id, bg_palette);
+                    get_pixel_color_gbc(shade as u8, 0, cgb_bg_palettes)
+                }
                 ExecutionMode::GameBoy => get_bg_pixel_color_gb(bg_pixel.color_id, bg_palette),
                 ExecutionMode::GameBoyColor => get_pixel_color_gbc(
                     bg_pixel.color_id,
@@ -853,11 +1083,16 @@ fn render_to_frame_buffer(
         } else {
             match execution_mode {
                 ExecutionMode::GameBoy => {
-                    let obj_palette = match sprite_pixel.obj_palette {
-                        SpritePalette::ObjPalette0 => obj_palette_0,
-                        SpritePalette::ObjPalette1 => obj_palette_1,
+                    let (obj_palette, cgb_palette_index) = match sprite_pixel.obj_palette {
+                        SpritePalette::ObjPalette0 => (obj_palette_0, 0),
+                        SpritePalette::ObjPalette1 => (obj_palette_1, 1),
                     };
-                    get_obj_pixel_color_gb(sprite_pixel.color_id, obj_palette)
+                    let shade = get_obj_pixel_color_gb(sprite_pixel.color_id, obj_palette);
+                    if dmg_compatibility_mode {
+                        get_pixel_color_gbc(shade as u8, cgb_palette_index, cgb_obj_palettes)
+                    } else {
+                        shade
+                    }
                 }
                 ExecutionMode::GameBoyColor => get_pixel_color_gbc(
                     sprite_pixel.color_id,
@@ -875,19 +1110,20 @@ fn render_to_frame_buffer(
         pixel += 1;
     }
 
-    State::RenderingScanline(RenderingScanlineStateData {
+    RenderingScanlineStateData {
         scanline,
         pixel,
         bg_fetcher_x,
         sprite_fetcher_x,
-        dot: dot + DOTS_PER_M_CYCLE,
+        dot,
         window_y,
         window_internal_y,
         window_ends_line,
+        render_end_dot,
         sprites,
         bg_pixel_queue,
         sprite_pixel_queue,
-    })
+    }
 }
 
 fn populate_bg_pixel_queue(
@@ -904,6 +1140,7 @@ fn populate_bg_pixel_queue(
         window_y,
         mut window_internal_y,
         mut window_ends_line,
+        render_end_dot,
         sprites,
         mut bg_pixel_queue,
         sprite_pixel_queue,
@@ -1048,6 +1285,7 @@ fn populate_bg_pixel_queue(
         window_y,
         window_internal_y,
         window_ends_line,
+        render_end_dot,
         sprites,
         bg_pixel_queue,
         sprite_pixel_queue,
@@ -1071,6 +1309,7 @@ fn populate_sprite_pixel_queue(
         window_y,
         window_internal_y,
         window_ends_line,
+        render_end_dot,
         sprites,
         bg_pixel_queue,
         mut sprite_pixel_queue,
@@ -1130,6 +1369,7 @@ fn populate_sprite_pixel_queue(
         window_y,
         window_internal_y,
         window_ends_line,
+        render_end_dot,
         sprites,
         bg_pixel_queue,
         sprite_pixel_queue,
@@ -1256,10 +1496,15 @@ mod tests {
 
         address_space.get_io_registers_mut().write_register(IoRegister::DMA, 0xC5);
 
+        // M-cycle of the DMA write
+        progress_oam_dma_transfer(&mut ppu_state, &mut address_space);
+        assert_eq!(None, ppu_state.oam_dma_status);
+
+        // Startup delay
         progress_oam_dma_transfer(&mut ppu_state, &mut address_space);
-        assert!(ppu_state.oam_dma_status.is_some());
+        assert_eq!(Some(OamDmaStatus::new(0xC5)), ppu_state.oam_dma_status);
 
-        for _ in 0..158 {
+        for _ in 0..159 {
             progress_oam_dma_transfer(&mut ppu_state, &mut address_space);
             assert!(ppu_state.oam_dma_status.is_some());
         }
@@ -1272,6 +1517,55 @@ mod tests {
         assert_eq!(0x34, address_space.read_address_u8(0xFE9F, &ppu_state));
     }
 
+    #[test]
+    fn oam_dma_bus_conflicts() {
+        let mut address_space = AddressSpace::new(
+            Cartridge::new(vec![0; 0x150], None, ControllerStates::default()).unwrap(),
+            ExecutionMode::GameBoy,
+        );
+        let mut ppu_state =
+            PpuState { state: VBLANK_START, ..PpuState::new(ExecutionMode::GameBoy) };
+
+        for i in 0..0xA0 {
+            address_space.write_address_u8(0xC100 + i, i as u8, &ppu_state);
+        }
+        address_space.write_address_u8(0x8000, 0x9A, &ppu_state);
+        address_space.write_address_u8(0xFF80, 0xBC, &ppu_state);
+
+        address_space.get_io_registers_mut().write_register(IoRegister::DMA, 0xC1);
+        progress_oam_dma_transfer(&mut ppu_state, &mut address_space);
+        progress_oam_dma_transfer(&mut ppu_state, &mut address_space);
+        progress_oam_dma_transfer(&mut ppu_state, &mut address_space);
+
+        // Reads from the external bus see the byte that the DMA is reading, while VRAM, HRAM, I/O
+        // registers, and OAM are unaffected or blocked
+        assert_eq!(0x01, address_space.read_address_u8(0x0000, &ppu_state));
+        assert_eq!(0x01, address_space.read_address_u8(0xD000, &ppu_state));
+        assert_eq!(0x9A, address_space.read_address_u8(0x8000, &ppu_state));
+        assert_eq!(0xBC, address_space.read_address_u8(0xFF80, &ppu_state));
+        assert_eq!(0xC1, address_space.read_address_u8(0xFF46, &ppu_state));
+        assert_eq!(0xFF, address_space.read_address_u8(0xFE00, &ppu_state));
+
+        // Writes to the external bus are ignored
+        address_space.write_address_u8(0xD000, 0x55, &ppu_state);
+        progress_oam_dma_transfer(&mut ppu_state, &mut address_space);
+        assert_eq!(0x02, address_space.read_address_u8(0xD000, &ppu_state));
+
+        // Restarting (here from an echo RAM source) replaces the existing transfer after the startup
+        // delay
+        address_space.get_io_registers_mut().write_register(IoRegister::DMA, 0xE1);
+        progress_oam_dma_transfer(&mut ppu_state, &mut address_space);
+        progress_oam_dma_transfer(&mut ppu_state, &mut address_space);
+        assert_eq!(Some(OamDmaStatus::new(0xC1)), ppu_state.oam_dma_status);
+
+        for _ in 0..160 {
+            progress_oam_dma_transfer(&mut ppu_state, &mut address_space);
+        }
+        assert_eq!(None, ppu_state.oam_dma_status);
+        assert_eq!(0x00, address_space.read_address_u8(0xD000, &ppu_state));
+        assert_eq!(0x9F, address_space.read_address_u8(0xFE9F, &ppu_state));
+    }
+
     #[test]
     fn scan_oam_basic_test() {
         let mut address_space = AddressSpace::new(
@@ -1300,4 +1594,165 @@ mod tests {
         scan_oam(&mut sprites, &address_space, 45, 20);
         assert_eq!(1, sprites.len());
     }
+
+    #[test]
+    fn oam_bug() {
+        fn run(
+            execution_mode: ExecutionMode,
+            dot: u32,
+            address: u16,
+            access: OamBugAccess,
+        ) -> [u8; 160] {
+            let mut address_space = AddressSpace::new(
+                Cartridge::new(vec![0; 0x150], None, ControllerStates::default()).unwrap(),
+                execution_mode,
+            );
+            let ppu_state = PpuState {
+                state: State::ScanningOAM(ScanningOAMStateData {
+                    scanline: 0,
+                    dot,
+                    window_internal_y: None,
+                    sprites: Vec::new(),
+                }),
+                ..PpuState::new(execution_mode)
+            };
+
+            let oam = address_space.get_oam_mut();
+            for (i, byte) in oam.iter_mut().enumerate() {
+                *byte = (i as u8).wrapping_mul(0x35) ^ 0xA6;
+            }
+            let original = *oam;
+
+            trigger_oam_bug(&ppu_state, &mut address_space, address, access);
+
+            let corrupted = *address_space.get_oam_mut();
+            // Everything outside of the affected rows should be untouched
+            let row = (dot / DOTS_PER_M_CYCLE) as usize;
+            let first_affected = if access == OamBugAccess::ReadIncrement { row - 2 } else { row };
+            assert_eq!(original[..8 * first_affected], corrupted[..8 * first_affected]);
+            assert_eq!(original[8 * (row + 1)..], corrupted[8 * (row + 1)..]);
+
+            corrupted
+        }
+
+        let mut original = [0; 160];
+        for (i, byte) in original.iter_mut().enumerate() {
+            *byte = (i as u8).wrapping_mul(0x35) ^ 0xA6;
+        }
+
+        let oam = run(ExecutionMode::GameBoy, 20, 0xFE10, OamBugAccess::Write);
+        let (a, b, c) =
+            (oam_word(&original, 5, 0), oam_word(&original, 4, 0), oam_word(&original, 4, 2));
+        assert_eq!(((a ^ c) & (b ^ c)) ^ c, oam_word(&oam, 5, 0));
+        assert_eq!(original[34..40], oam[42..48]);
+
+        let oam = run(ExecutionMode::GameBoy, 20, 0xFEFF, OamBugAccess::Read);
+        assert_eq!(b | (a & c), oam_word(&oam, 5, 0));
+        assert_eq!(original[34..40], oam[42..48]);
+
+        let oam = run(ExecutionMode::GameBoy, 20, 0xFE00, OamBugAccess::ReadIncrement);
+        let (a, b, c, d) = (
+            oam_word(&original, 3, 0),
+            oam_word(&original, 4, 0),
+            oam_word(&original, 5, 0),
+            oam_word(&original, 4, 2),
+        );
+        let preceding = (b & (a | c | d)) | (a & c & d);
+        assert_eq!(preceding, oam_word(&oam, 4, 0));
+        assert_eq!(preceding, oam_word(&oam, 3, 0));
+        assert_eq!(preceding | (preceding & oam_word(&original, 4, 2)), oam_word(&oam, 5, 0));
+        assert_eq!(oam[32..40], oam[24..32]);
+
+        // No corruption on the first row, outside of OAM, or in CGB mode
+        assert_eq!(original, run(ExecutionMode::GameBoy, 0, 0xFE00, OamBugAccess::Write));
+        assert_eq!(original, run(ExecutionMode::GameBoy, 20, 0xFDFF, OamBugAccess::Write));
+        assert_eq!(original, run(ExecutionMode::GameBoyColor, 20, 0xFE00, OamBugAccess::Write));
+    }
+
+    fn sprite_at(x_pos: u8) -> (OamSpriteData, TileData) {
+        (OamSpriteData { y_pos: 16, x_pos, tile_index: 0x00, flags: 0x00 }, TileData(0x00, 0x00))
+    }
+
+    #[test]
+    fn render_dots() {
+        let mut io_registers = IoRegisters::new(ExecutionMode::GameBoy);
+        io_registers.write_register(IoRegister::LCDC, 0x83);
+
+        assert_eq!(172, compute_render_dots(&io_registers, &[], 0, 0));
+
+        io_registers.write_register(IoRegister::SCX, 0x0B);
+        assert_eq!(175, compute_render_dots(&io_registers, &[], 0, 0));
+        io_registers.write_register(IoRegister::SCX, 0x00);
+
+        // X=0 always costs the maximum, sprites aligned with BG tiles cost 6 + 5, and sprites
+        // sharing a tile with an earlier sprite only cost 6
+        assert_eq!(172 + 11, compute_render_dots(&io_registers, &[sprite_at(0)], 0, 0));
+        assert_eq!(172 + 11, compute_render_dots(&io_registers, &[sprite_at(8)], 0, 0));
+        assert_eq!(172 + 6, compute_render_dots(&io_registers, &[sprite_at(14)], 0, 0));
+        assert_eq!(
+            172 + 11 + 6,
+            compute_render_dots(&io_registers, &[sprite_at(9), sprite_at(8)], 0, 0)
+        );
+
+        // Offscreen sprites are not fetched
+        assert_eq!(172, compute_render_dots(&io_registers, &[sprite_at(168)], 0, 0));
+
+        // Fine scroll shifts sprites relative to BG tiles
+        io_registers.write_register(IoRegister::SCX, 0x03);
+        assert_eq!(172 + 3 + 8, compute_render_dots(&io_registers, &[sprite_at(8)], 0, 0));
+        io_registers.write_register(IoRegister::SCX, 0x00);
+
+        // Sprite penalties are not applied when sprites are disabled
+        io_registers.write_register(IoRegister::LCDC, 0x81);
+        assert_eq!(172, compute_render_dots(&io_registers, &[sprite_at(8)], 0, 0));
+
+        // Window adds 6 dots, and sprites over the window are aligned to window tiles
+        io_registers.write_register(IoRegister::LCDC, 0xA3);
+        io_registers.write_register(IoRegister::WX, 0x0A);
+        assert_eq!(172, compute_render_dots(&io_registers, &[], 0, 5));
+        assert_eq!(172 + 6, compute_render_dots(&io_registers, &[], 5, 5));
+        assert_eq!(172 + 6 + 11, compute_render_dots(&io_registers, &[sprite_at(11)], 5, 5));
+    }
+
+    // Run the PPU for one full frame and count the STAT interrupts that were requested
+    fn count_stat_interrupts(stat: u8, lyc: u8) -> u32 {
+        let mut address_space = AddressSpace::new(
+            Cartridge::new(vec![0; 0x150], None, ControllerStates::default()).unwrap(),
+            ExecutionMode::GameBoy,
+        );
+        let mut ppu_state = PpuState::new(ExecutionMode::GameBoy);
+
+        let io_registers = address_space.get_io_registers_mut();
+        io_registers.write_register(IoRegister::STAT, stat);
+        io_registers.write_register(IoRegister::LYC, lyc);
+        io_registers.interrupt_flags().clear(InterruptType::LcdStatus);
+
+        let mut count = 0;
+        for _ in 0..u32::from(LAST_VBLANK_SCANLINE + 1) * DOTS_PER_SCANLINE / DOTS_PER_M_CYCLE {
+            tick_m_cycle(&mut ppu_state, &mut address_space);
+
+            let mut interrupt_flags = address_space.get_io_registers_mut().interrupt_flags();
+            if interrupt_flags.get(InterruptType::LcdStatus) {
+                interrupt_flags.clear(InterruptType::LcdStatus);
+                count += 1;
+            }
+        }
+        count
+    }
+
+    #[test]
+    fn stat_interrupt_blocking() {
+        // HBlank on every visible line
+        assert_eq!(144, count_stat_interrupts(0x08, 0xFF));
+
+        // Mode 2 on every visible line plus the start of VBlank
+        assert_eq!(145, count_stat_interrupts(0x20, 0xFF));
+
+        // LY=LYC begins while the HBlank source is still active, which blocks the LYC interrupt,
+        // and then LY=LYC is still active during line 10's HBlank, which blocks that interrupt
+        assert_eq!(143, count_stat_interrupts(0x48, 10));
+
+        // Mode 2 at the start of VBlank is blocked by the VBlank source
+        assert_eq!(145, count_stat_interrupts(0x30, 0xFF));
+    }
 }
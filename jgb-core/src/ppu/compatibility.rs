@@ -0,0 +1,281 @@
+//! The palettes that the CGB boot ROM assigns to cartridges that do not support CGB mode.
+//!
+//! The boot ROM computes a checksum of the cartridge title and looks it up in a table of known
+//! Nintendo-published games, using a default palette for everything else. The player can also
+//! override the automatic selection by holding one of 12 button combinations.
+
+use crate::input::{Button, JoypadState};
+use crate::memory::address;
+
+/// The colors (BGR555) for the first BG palette and the first two OBJ palettes in DMG
+/// compatibility mode.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CompatibilityPalettes {
+    pub bg: [u16; 4],
+    pub obj0: [u16; 4],
+    pub obj1: [u16; 4],
+}
+
+// All colors used in compatibility palettes. Palette combinations index into this array by color
+// rather than by palette, and a few of them deliberately straddle two palettes.
+#[rustfmt::skip]
+const COLORS: [u16; 120] = [
+    0x7FFF, 0x32BF, 0x00D0, 0x0000,
+    0x639F, 0x4279, 0x15B0, 0x04CB,
+    0x7FFF, 0x6E31, 0x454A, 0x0000,
+    0x7FFF, 0x1BEF, 0x0200, 0x0000,
+    0x7FFF, 0x421F, 0x1CF2, 0x0000,
+    0x7FFF, 0x5294, 0x294A, 0x0000,
+    0x7FFF, 0x03FF, 0x012F, 0x0000,
+    0x7FFF, 0x03EF, 0x01D6, 0x0000,
+    0x7FFF, 0x42B5, 0x3DC8, 0x0000,
+    0x7E74, 0x03FF, 0x0180, 0x0000,
+    0x67FF, 0x77AC, 0x1A13, 0x2D6B,
+    0x7ED6, 0x4BFF, 0x2175, 0x0000,
+    0x53FF, 0x4A5F, 0x7E52, 0x0000,
+    0x4FFF, 0x7ED2, 0x3A4C, 0x1CE0,
+    0x03ED, 0x7FFF, 0x255F, 0x0000,
+    0x036A, 0x021F, 0x03FF, 0x7FFF,
+    0x7FFF, 0x01DF, 0x0112, 0x0000,
+    0x231F, 0x035F, 0x00F2, 0x0009,
+    0x7FFF, 0x03EA, 0x011F, 0x0000,
+    0x299F, 0x001A, 0x000C, 0x0000,
+    0x7FFF, 0x027F, 0x001F, 0x0000,
+    0x7FFF, 0x03E0, 0x0206, 0x0120,
+    0x7FFF, 0x7EEB, 0x001F, 0x7C00,
+    0x7FFF, 0x3FFF, 0x7E00, 0x001F,
+    0x7FFF, 0x03FF, 0x001F, 0x0000,
+    0x03FF, 0x001F, 0x000C, 0x0000,
+    0x7FFF, 0x033F, 0x0193, 0x0000,
+    0x0000, 0x4200, 0x037F, 0x7FFF,
+    0x7FFF, 0x7E8C, 0x7C00, 0x0000,
+    0x7FFF, 0x1BEF, 0x6180, 0x0000,
+];
+
+// Offsets into COLORS for (OBJ0, OBJ1, BG)
+const fn combination(obj0: usize, obj1: usize, bg: usize) -> [usize; 3] {
+    [4 * obj0, 4 * obj1, 4 * bg]
+}
+
+const PALETTE_COMBINATIONS: [[usize; 3]; 51] = [
+    combination(4, 4, 29),
+    combination(18, 18, 18),
+    combination(20, 20, 20),
+    combination(24, 24, 24),
+    combination(9, 9, 9),
+    combination(0, 0, 0),
+    combination(27, 27, 27),
+    combination(5, 5, 5),
+    combination(12, 12, 12),
+    combination(26, 26, 26),
+    combination(16, 8, 8),
+    combination(4, 28, 28),
+    combination(4, 2, 2),
+    combination(3, 4, 4),
+    combination(4, 29, 29),
+    combination(28, 4, 28),
+    combination(2, 17, 2),
+    combination(16, 16, 8),
+    combination(4, 4, 7),
+    combination(4, 4, 18),
+    combination(4, 4, 20),
+    combination(19, 19, 9),
+    [4 * 4 - 1, 4 * 4 - 1, 4 * 11],
+    combination(17, 17, 2),
+    combination(4, 4, 2),
+    combination(4, 4, 3),
+    combination(28, 28, 0),
+    combination(3, 3, 0),
+    combination(0, 0, 1),
+    combination(18, 22, 18),
+    combination(20, 22, 20),
+    combination(24, 22, 24),
+    combination(16, 22, 8),
+    combination(17, 4, 13),
+    [28 * 4 - 1, 0, 4 * 14],
+    [28 * 4 - 1, 4 * 4, 4 * 15],
+    combination(19, 22, 9),
+    combination(16, 28, 10),
+    combination(4, 23, 28),
+    combination(17, 22, 2),
+    combination(4, 0, 2),
+    combination(4, 28, 3),
+    combination(28, 3, 0),
+    combination(3, 28, 4),
+    combination(21, 28, 4),
+    combination(3, 28, 0),
+    combination(25, 3, 28),
+    combination(0, 28, 8),
+    combination(4, 3, 28),
+    combination(28, 3, 6),
+    combination(4, 28, 29),
+];
+
+// Title checksums of recognized games. Checksums after the first 65 are not unique, so those games
+// are additionally matched on the 4th letter of their title.
+const TITLE_CHECKSUMS: [u8; 94] = [
+    0x00, 0x88, 0x16, 0x36, 0xD1, 0xDB, 0xF2, 0x3C, 0x8C, 0x92, 0x3D, 0x5C, 0x58, 0xC9, 0x3E, 0x70,
+    0x1D, 0x59, 0x69, 0x19, 0x35, 0xA8, 0x14, 0xAA, 0x75, 0x95, 0x99, 0x34, 0x6F, 0x15, 0xFF, 0x97,
+    0x4B, 0x90, 0x17, 0x10, 0x39, 0xF7, 0xF6, 0xA2, 0x49, 0x4E, 0x43, 0x68, 0xE0, 0x8B, 0xF0, 0xCE,
+    0x0C, 0x29, 0xE8, 0xB7, 0x86, 0x9A, 0x52, 0x01, 0x9D, 0x71, 0x9C, 0xBD, 0x5D, 0x6D, 0x67, 0x3F,
+    0x6B, 0xB3, 0x46, 0x28, 0xA5, 0xC6, 0xD3, 0x27, 0x61, 0x18, 0x66, 0x6A, 0xBF, 0x0D, 0xF4, 0xB3,
+    0x46, 0x28, 0xA5, 0xC6, 0xD3, 0x27, 0x61, 0x18, 0x66, 0x6A, 0xBF, 0x0D, 0xF4, 0xB3,
+];
+
+const FIRST_DUPLICATE_CHECKSUM_INDEX: usize = 65;
+
+const DUPLICATE_CHECKSUM_4TH_LETTERS: &[u8; 29] = b"BEFAARBEKEK R-URAR INAILICE R";
+
+// Palette combination for each entry in TITLE_CHECKSUMS
+const CHECKSUM_PALETTE_COMBINATIONS: [u8; 94] = [
+    0, 4, 5, 35, 34, 3, 31, 15, 10, 5, 19, 36, 7, 37, 30, 44, 21, 32, 31, 20, 5, 33, 13, 14, 5, 29,
+    5, 18, 9, 3, 2, 26, 25, 25, 41, 42, 26, 45, 42, 45, 36, 38, 26, 42, 30, 41, 34, 34, 5, 42, 6,
+    5, 33, 25, 42, 42, 40, 2, 16, 25, 42, 42, 5, 0, 39, 36, 22, 25, 6, 32, 12, 36, 11, 39, 18, 39,
+    24, 31, 50, 17, 46, 6, 27, 0, 47, 41, 41, 0, 0, 19, 34, 23, 18, 29,
+];
+
+// Used for cartridges not published by Nintendo and for unrecognized titles
+const DEFAULT_PALETTE_COMBINATION: u8 = 0;
+
+fn palettes_for_combination(combination: u8) -> CompatibilityPalettes {
+    let [obj0, obj1, bg] = PALETTE_COMBINATIONS[usize::from(combination)];
+    let palette_at = |offset: usize| -> [u16; 4] { COLORS[offset..offset + 4].try_into().unwrap() };
+
+    CompatibilityPalettes { bg: palette_at(bg), obj0: palette_at(obj0), obj1: palette_at(obj1) }
+}
+
+fn is_nintendo_licensee(rom_header: &[u8]) -> bool {
+    match rom_header[address::OLD_LICENSEE_CODE as usize] {
+        0x01 => true,
+        0x33 => {
+            let new_licensee_start = address::NEW_LICENSEE_CODE as usize;
+            &rom_header[new_licensee_start..new_licensee_start + 2] == b"01"
+        }
+        _ => false,
+    }
+}
+
+/// Select palettes for the given cartridge header the same way that the CGB boot ROM does.
+///
+/// # Panics
+///
+/// This function will panic if the header slice is shorter than 0x150 bytes.
+pub fn palettes_for_cartridge(rom_header: &[u8]) -> CompatibilityPalettes {
+    if !is_nintendo_licensee(rom_header) {
+        return palettes_for_combination(DEFAULT_PALETTE_COMBINATION);
+    }
+
+    let title = &rom_header[address::TITLE_START as usize..=address::TITLE_END as usize];
+    let checksum = title.iter().copied().fold(0_u8, u8::wrapping_add);
+    let fourth_letter = title[3];
+
+    let combination = TITLE_CHECKSUMS
+        .iter()
+        .enumerate()
+        .find(|&(i, &title_checksum)| {
+            title_checksum == checksum
+                && (i < FIRST_DUPLICATE_CHECKSUM_INDEX
+                    || DUPLICATE_CHECKSUM_4TH_LETTERS[i - FIRST_DUPLICATE_CHECKSUM_INDEX]
+                        == fourth_letter)
+        })
+        .map_or(DEFAULT_PALETTE_COMBINATION, |(i, _)| CHECKSUM_PALETTE_COMBINATIONS[i]);
+
+    log::info!(
+        "Title checksum is {checksum:02X}, using compatibility palette combination {combination}"
+    );
+
+    palettes_for_combination(combination)
+}
+
+/// Select palettes based on the button combination that is currently held, if any. On actual
+/// hardware this is checked while the boot logo is displayed.
+pub fn palettes_for_buttons(joypad_state: &JoypadState) -> Option<CompatibilityPalettes> {
+    let direction = [Button::Right, Button::Left, Button::Up, Button::Down]
+        .into_iter()
+        .find(|&button| joypad_state.is_pressed(button))?;
+
+    let combination =
+        match (direction, joypad_state.is_pressed(Button::A), joypad_state.is_pressed(Button::B)) {
+            (Button::Right, false, false) => 1,
+            (Button::Left, false, false) => 48,
+            (Button::Up, false, false) => 5,
+            (Button::Down, false, false) => 8,
+            (Button::Right, true, false) => 0,
+            (Button::Left, true, false) => 40,
+            (Button::Up, true, false) => 43,
+            (Button::Down, true, false) => 3,
+            (Button::Right, false, true) => 6,
+            (Button::Left, false, true) => 7,
+            (Button::Up, false, true) => 28,
+            (Button::Down, false, true) => 49,
+            _ => return None,
+        };
+
+    log::info!("Using compatibility palette combination {combination} from button combination");
+
+    Some(palettes_for_combination(combination))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn header(title: &[u8], old_licensee: u8, new_licensee: [u8; 2]) -> Vec<u8> {
+        let mut header = vec![0; 0x150];
+        header[address::TITLE_START as usize..address::TITLE_START as usize + title.len()]
+            .copy_from_slice(title);
+        header[address::NEW_LICENSEE_CODE as usize..address::NEW_LICENSEE_CODE as usize + 2]
+            .copy_from_slice(&new_licensee);
+        header[address::OLD_LICENSEE_CODE as usize] = old_licensee;
+        header
+    }
+
+    #[test]
+    fn unique_checksum() {
+        // POKEMON RED has a checksum of 0x14 which maps to combination 13
+        let palettes = palettes_for_cartridge(&header(b"POKEMON RED", 0x01, *b"\0\0"));
+        assert_eq!(palettes_for_combination(13), palettes);
+        assert_eq!([0x7FFF, 0x421F, 0x1CF2, 0x0000], palettes.bg);
+        assert_eq!([0x7FFF, 0x1BEF, 0x0200, 0x0000], palettes.obj0);
+
+        // New licensee code should also be checked
+        let palettes = palettes_for_cartridge(&header(b"POKEMON RED", 0x33, *b"01"));
+        assert_eq!(palettes_for_combination(13), palettes);
+    }
+
+    #[test]
+    fn duplicate_checksum() {
+        // SUPER MARIOLAND shares its checksum with another title and is matched on the 'E'
+        let palettes = palettes_for_cartridge(&header(b"SUPER MARIOLAND", 0x01, *b"\0\0"));
+        assert_eq!(palettes_for_combination(22), palettes);
+        assert_eq!([0x0000, 0x7FFF, 0x421F, 0x1CF2], palettes.obj0);
+
+        // Same checksum with a different 4th letter
+        let palettes = palettes_for_cartridge(&header(b"SUPRE MARIOLAND", 0x01, *b"\0\0"));
+        assert_eq!(palettes_for_combination(46), palettes);
+    }
+
+    #[test]
+    fn non_nintendo_uses_default() {
+        let default = palettes_for_combination(DEFAULT_PALETTE_COMBINATION);
+        assert_eq!(default, palettes_for_cartridge(&header(b"POKEMON RED", 0x33, *b"08")));
+        assert_eq!(default, palettes_for_cartridge(&header(b"POKEMON RED", 0x08, *b"\0\0")));
+        assert_eq!([0x7FFF, 0x1BEF, 0x6180, 0x0000], default.bg);
+    }
+
+    #[test]
+    fn button_combinations() {
+        let mut joypad_state = JoypadState::new();
+        assert_eq!(None, palettes_for_buttons(&joypad_state));
+
+        joypad_state.set_button(Button::A, true);
+        assert_eq!(None, palettes_for_buttons(&joypad_state));
+
+        joypad_state.set_button(Button::Left, true);
+        assert_eq!(Some(palettes_for_combination(40)), palettes_for_buttons(&joypad_state));
+
+        joypad_state.set_button(Button::A, false);
+        joypad_state.set_button(Button::B, true);
+        assert_eq!(Some(palettes_for_combination(7)), palettes_for_buttons(&joypad_state));
+    }
+}
@@ -0,0 +1,196 @@
+use crate::serialize::{self, SaveStateError};
+use crate::startup::EmulationState;
+use std::collections::VecDeque;
+
+// Take a snapshot every 5 frames, or 12 times per second at ~60fps
+const SNAPSHOT_INTERVAL_FRAMES: u32 = 5;
+const SNAPSHOTS_PER_SECOND: u32 = 60 / SNAPSHOT_INTERVAL_FRAMES;
+
+/// A bounded history of in-memory save states that the emulator can step backward through.
+///
+/// Only the most recent snapshot is stored in full. Every older snapshot is stored as the XOR of
+/// itself against the next newer snapshot with runs of zero bytes run-length encoded, which keeps
+/// memory usage low because very little of the emulation state changes over a few frames. Each
+/// delta only depends on newer snapshots, so the oldest snapshot can be dropped at any time.
+pub struct RewindBuffer {
+    capacity: usize,
+    latest: Option<Vec<u8>>,
+    // Ordered from oldest to newest
+    deltas: VecDeque<Vec<u8>>,
+    frames_since_snapshot: u32,
+}
+
+impl RewindBuffer {
+    /// Create a rewind buffer that holds roughly `buffer_secs` seconds of history. A length of 0
+    /// disables rewinding.
+    pub fn new(buffer_secs: u32) -> Self {
+        Self {
+            capacity: (buffer_secs * SNAPSHOTS_PER_SECOND) as usize,
+            latest: None,
+            deltas: VecDeque::new(),
+            frames_since_snapshot: 0,
+        }
+    }
+
+    /// Record that a frame has finished, taking a snapshot of the given state if enough frames
+    /// have passed since the last snapshot.
+    pub fn end_frame(&mut self, state: &EmulationState) -> Result<(), SaveStateError> {
+        if self.capacity == 0 {
+            return Ok(());
+        }
+
+        self.frames_since_snapshot += 1;
+        if self.latest.is_none() || self.frames_since_snapshot >= SNAPSHOT_INTERVAL_FRAMES {
+            self.push_snapshot(serialize::serialize_state(state)?);
+            self.frames_since_snapshot = 0;
+        }
+
+        Ok(())
+    }
+
+    /// Load the most recent snapshot into the given state and remove it from the buffer. The
+    /// oldest snapshot is never removed, so rewinding repeatedly will stop there.
+    ///
+    /// Returns false if there are no snapshots to rewind to.
+    pub fn rewind(&mut self, state: &mut EmulationState) -> Result<bool, SaveStateError> {
+        let Some(snapshot) = self.latest.as_ref() else {
+            return Ok(false);
+        };
+
+        serialize::deserialize_state(snapshot, state)?;
+
+        self.pop_snapshot();
+        self.frames_since_snapshot = 0;
+
+        Ok(true)
+    }
+
+    fn push_snapshot(&mut self, snapshot: Vec<u8>) {
+        if let Some(latest) = self.latest.take() {
+            self.deltas.push_back(encode_delta(&latest, &snapshot));
+            if self.deltas.len() >= self.capacity {
+                self.deltas.pop_front();
+            }
+        }
+
+        self.latest = Some(snapshot);
+    }
+
+    // Step the latest snapshot back by one, returning the snapshot that was removed. If only one
+    // snapshot remains then it is left in place
+    fn pop_snapshot(&mut self) -> Option<Vec<u8>> {
+        let latest = self.latest.take()?;
+
+        match self.deltas.pop_back() {
+            Some(delta) => {
+                self.latest = Some(decode_delta(&delta, &latest));
+                Some(latest)
+            }
+            None => {
+                self.latest = Some(latest.clone());
+                Some(latest)
+            }
+        }
+    }
+}
+
+// Encode `older` relative to `newer` as their XOR, treating any bytes past the end of `newer` as
+// zero. Each run of zero bytes in the XOR is encoded as a 0x00 byte followed by the run length
+fn encode_delta(older: &[u8], newer: &[u8]) -> Vec<u8> {
+    let mut encoded = Vec::new();
+
+    let mut zero_run = 0_u8;
+    for (i, &byte) in older.iter().enumerate() {
+        let xor = byte ^ newer.get(i).copied().unwrap_or(0);
+        if xor == 0 {
+            zero_run += 1;
+            if zero_run == u8::MAX {
+                encoded.extend([0x00, zero_run]);
+                zero_run = 0;
+            }
+        } else {
+            if zero_run > 0 {
+                encoded.extend([0x00, zero_run]);
+                zero_run = 0;
+            }
+            encoded.push(xor);
+        }
+    }
+
+    if zero_run > 0 {
+        encoded.extend([0x00, zero_run]);
+    }
+
+    encoded
+}
+
+fn decode_delta(delta: &[u8], newer: &[u8]) -> Vec<u8> {
+    let mut decoded = Vec::with_capacity(newer.len());
+
+    let mut bytes = delta.iter().copied();
+    while let Some(byte) = bytes.next() {
+        if byte == 0x00 {
+            let zero_run =
+                bytes.next().expect("zero byte in delta should always be followed by a run length");
+            decoded.extend(std::iter::repeat_n(0x00, zero_run.into()));
+        } else {
+            decoded.push(byte);
+        }
+    }
+
+    for (i, byte) in decoded.iter_mut().enumerate() {
+        *byte ^= newer.get(i).copied().unwrap_or(0);
+    }
+
+    decoded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn delta_round_trip() {
+        let newer: Vec<u8> = (0..1000).map(|i| (i % 251) as u8).collect();
+
+        let mut older = newer.clone();
+        older[3] = 0xFF;
+        older[700] ^= 0x01;
+        let delta = encode_delta(&older, &newer);
+        assert!(delta.len() < 20, "delta should be compressed: {} bytes", delta.len());
+        assert_eq!(older, decode_delta(&delta, &newer));
+
+        // Snapshots can change length if a variable-length field changes
+        let shorter = older[..900].to_vec();
+        assert_eq!(shorter, decode_delta(&encode_delta(&shorter, &newer), &newer));
+
+        let mut longer = older.clone();
+        longer.extend([0x00, 0x00, 0x12]);
+        assert_eq!(longer, decode_delta(&encode_delta(&longer, &newer), &newer));
+    }
+
+    #[test]
+    fn ring_buffer() {
+        let snapshot = |n: u8| vec![n; 600];
+
+        // 1 second of history
+        let mut buffer = RewindBuffer::new(1);
+        assert_eq!(None, buffer.pop_snapshot());
+
+        for n in 0..20 {
+            buffer.push_snapshot(snapshot(n));
+        }
+
+        // Only the most recent snapshots should be retained, and the oldest is never removed
+        for n in (8..20).rev() {
+            assert_eq!(Some(snapshot(n)), buffer.pop_snapshot());
+        }
+        assert_eq!(Some(snapshot(8)), buffer.pop_snapshot());
+        assert_eq!(Some(snapshot(8)), buffer.pop_snapshot());
+
+        // New snapshots continue from the rewound point
+        buffer.push_snapshot(snapshot(100));
+        assert_eq!(Some(snapshot(100)), buffer.pop_snapshot());
+        assert_eq!(Some(snapshot(8)), buffer.pop_snapshot());
+    }
+}
@@ -0,0 +1,284 @@
+mod linkcable;
+mod printer;
+mod socket;
+
+use crate::cpu::{ExecutionMode, InterruptType};
+use crate::memory::ioregisters::{IoRegister, IoRegisters};
+
+pub use linkcable::LinkCable;
+pub use printer::Printer;
+pub use socket::SocketLinkCable;
+
+/// A device connected to the Game Boy's serial link port.
+///
+/// Transfers are exchanged a full byte at a time: the port handles the bit-level timing and only
+/// calls into the device once all 8 bits would have been shifted.
+pub trait SerialDevice {
+    /// Shift a byte out to the device using this Game Boy's internal clock, returning the byte that
+    /// the device shifted back in. Devices that are not listening should return 0xFF, which is
+    /// what the Game Boy reads when nothing drives the serial input line.
+    fn transfer_internal_clock(&mut self, byte: u8) -> u8;
+
+    /// Poll whether the device has driven a transfer using its own clock.
+    ///
+    /// `waiting_byte` is the current contents of SB if this Game Boy is waiting for an external
+    /// clock transfer, or `None` if it is not. Returns the byte that the device shifted in if a
+    /// transfer occurred, in which case `waiting_byte` was shifted out to the device.
+    fn poll_external_clock(&mut self, waiting_byte: Option<u8>) -> Option<u8> {
+        let _ = waiting_byte;
+        None
+    }
+
+    /// Called after the emulator finishes each frame. Devices that need to stay in sync with
+    /// another emulator instance can block here.
+    fn end_frame(&mut self) {}
+}
+
+/// Nothing connected to the link port. Internal clock transfers always read 0xFF, and external
+/// clock transfers never complete.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Disconnected;
+
+impl SerialDevice for Disconnected {
+    fn transfer_internal_clock(&mut self, _byte: u8) -> u8 {
+        0xFF
+    }
+}
+
+/// A link cable with its output wired back into its input, so every internal clock transfer reads
+/// back the byte that was sent.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Loopback;
+
+impl SerialDevice for Loopback {
+    fn transfer_internal_clock(&mut self, byte: u8) -> u8 {
+        byte
+    }
+}
+
+// Number of 4MHz clock cycles per bit at the normal serial clock speed (8192 Hz)
+const NORMAL_CYCLES_PER_BIT: u64 = 512;
+// Number of 4MHz clock cycles per bit at the CGB fast serial clock speed (262144 Hz)
+const FAST_CYCLES_PER_BIT: u64 = 16;
+
+#[derive(Debug, Clone, Default)]
+pub struct SerialPort {
+    // Set while an internal clock transfer is in progress
+    transfer_cycles_remaining: Option<u64>,
+}
+
+impl SerialPort {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+/// Progress the serial port by the given number of clock cycles, exchanging a byte with the given
+/// device if a transfer completes.
+///
+/// This function will request a serial interrupt when a transfer completes.
+pub fn tick(
+    serial_port: &mut SerialPort,
+    io_registers: &mut IoRegisters,
+    device: &mut dyn SerialDevice,
+    execution_mode: ExecutionMode,
+    cycles: u64,
+) {
+    let sc = io_registers.read_register(IoRegister::SC);
+    let transfer_requested = sc & 0x80 != 0;
+    let internal_clock = sc & 0x01 != 0;
+
+    if !transfer_requested || !internal_clock {
+        // Writing to SC cancels any internal clock transfer in progress
+        serial_port.transfer_cycles_remaining = None;
+
+        let waiting_byte = transfer_requested.then(|| io_registers.read_register(IoRegister::SB));
+        if let Some(received_byte) = device.poll_external_clock(waiting_byte) {
+            complete_transfer(io_registers, received_byte);
+        }
+
+        return;
+    }
+
+    let cycles_remaining = match serial_port.transfer_cycles_remaining {
+        Some(cycles_remaining) => cycles_remaining,
+        None => {
+            // Bit 1 selects the fast clock speed, but only in CGB mode
+            let cycles_per_bit =
+                if matches!(execution_mode, ExecutionMode::GameBoyColor) && sc & 0x02 != 0 {
+                    FAST_CYCLES_PER_BIT
+                } else {
+                    NORMAL_CYCLES_PER_BIT
+                };
+            8 * cycles_per_bit
+        }
+    };
+
+    if cycles < cycles_remaining {
+        serial_port.transfer_cycles_remaining = Some(cycles_remaining - cycles);
+        return;
+    }
+
+    serial_port.transfer_cycles_remaining = None;
+
+    let sent_byte = io_registers.read_register(IoRegister::SB);
+    let received_byte = device.transfer_internal_clock(sent_byte);
+    log::trace!("Serial transfer complete, sent {sent_byte:02X}, received {received_byte:02X}");
+
+    complete_transfer(io_registers, received_byte);
+}
+
+fn complete_transfer(io_registers: &mut IoRegisters, received_byte: u8) {
+    io_registers.write_register(IoRegister::SB, received_byte);
+
+    let sc = io_registers.read_register(IoRegister::SC);
+    io_registers.write_register(IoRegister::SC, sc & 0x7F);
+
+    io_registers.interrupt_flags().set(InterruptType::Serial);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn new_io_registers(execution_mode: ExecutionMode) -> IoRegisters {
+        let mut io_registers = IoRegisters::new(execution_mode);
+        io_registers.interrupt_flags().clear(InterruptType::Serial);
+        io_registers
+    }
+
+    fn serial_interrupt_requested(io_registers: &mut IoRegisters) -> bool {
+        io_registers.interrupt_flags().get(InterruptType::Serial)
+    }
+
+    #[test]
+    fn internal_clock_disconnected() {
+        let mut io_registers = new_io_registers(ExecutionMode::GameBoy);
+        let mut serial_port = SerialPort::new();
+        let mut device = Disconnected;
+
+        io_registers.write_register(IoRegister::SB, 0x35);
+        io_registers.write_register(IoRegister::SC, 0x81);
+
+        for _ in 0..1023 {
+            tick(&mut serial_port, &mut io_registers, &mut device, ExecutionMode::GameBoy, 4);
+        }
+        assert_eq!(0x35, io_registers.read_register(IoRegister::SB));
+        assert_eq!(0xFF, io_registers.read_register(IoRegister::SC));
+        assert!(!serial_interrupt_requested(&mut io_registers));
+
+        tick(&mut serial_port, &mut io_registers, &mut device, ExecutionMode::GameBoy, 4);
+        assert_eq!(0xFF, io_registers.read_register(IoRegister::SB));
+        assert_eq!(0x7F, io_registers.read_register(IoRegister::SC));
+        assert!(serial_interrupt_requested(&mut io_registers));
+    }
+
+    #[test]
+    fn internal_clock_loopback() {
+        let mut io_registers = new_io_registers(ExecutionMode::GameBoy);
+        let mut serial_port = SerialPort::new();
+        let mut device = Loopback;
+
+        io_registers.write_register(IoRegister::SB, 0x35);
+        io_registers.write_register(IoRegister::SC, 0x81);
+
+        tick(&mut serial_port, &mut io_registers, &mut device, ExecutionMode::GameBoy, 4096);
+        assert_eq!(0x35, io_registers.read_register(IoRegister::SB));
+        assert!(serial_interrupt_requested(&mut io_registers));
+    }
+
+    #[test]
+    fn cgb_fast_clock() {
+        let mut io_registers = new_io_registers(ExecutionMode::GameBoyColor);
+        let mut serial_port = SerialPort::new();
+        let mut device = Loopback;
+
+        io_registers.write_register(IoRegister::SB, 0x35);
+        io_registers.write_register(IoRegister::SC, 0x83);
+
+        tick(&mut serial_port, &mut io_registers, &mut device, ExecutionMode::GameBoyColor, 124);
+        assert!(!serial_interrupt_requested(&mut io_registers));
+
+        tick(&mut serial_port, &mut io_registers, &mut device, ExecutionMode::GameBoyColor, 4);
+        assert!(serial_interrupt_requested(&mut io_registers));
+        assert_eq!(0x7F, io_registers.read_register(IoRegister::SC));
+
+        // Fast clock bit should be ignored in DMG mode
+        let mut io_registers = new_io_registers(ExecutionMode::GameBoy);
+        io_registers.write_register(IoRegister::SC, 0x83);
+
+        tick(&mut serial_port, &mut io_registers, &mut device, ExecutionMode::GameBoy, 128);
+        assert!(!serial_interrupt_requested(&mut io_registers));
+    }
+
+    #[test]
+    fn external_clock_never_completes_when_disconnected() {
+        let mut io_registers = new_io_registers(ExecutionMode::GameBoy);
+        let mut serial_port = SerialPort::new();
+        let mut device = Disconnected;
+
+        io_registers.write_register(IoRegister::SB, 0x35);
+        io_registers.write_register(IoRegister::SC, 0x80);
+
+        for _ in 0..10000 {
+            tick(&mut serial_port, &mut io_registers, &mut device, ExecutionMode::GameBoy, 4);
+        }
+        assert_eq!(0x35, io_registers.read_register(IoRegister::SB));
+        assert_eq!(0xFE, io_registers.read_register(IoRegister::SC));
+        assert!(!serial_interrupt_requested(&mut io_registers));
+    }
+
+    #[test]
+    fn linked_transfer() {
+        let mut master_io_registers = new_io_registers(ExecutionMode::GameBoy);
+        let mut master_serial_port = SerialPort::new();
+        let mut slave_io_registers = new_io_registers(ExecutionMode::GameBoy);
+        let mut slave_serial_port = SerialPort::new();
+        let (mut master_cable, mut slave_cable) = LinkCable::new_pair();
+
+        slave_io_registers.write_register(IoRegister::SB, 0x5A);
+        slave_io_registers.write_register(IoRegister::SC, 0x80);
+        tick(
+            &mut slave_serial_port,
+            &mut slave_io_registers,
+            &mut slave_cable,
+            ExecutionMode::GameBoy,
+            4,
+        );
+
+        master_io_registers.write_register(IoRegister::SB, 0xC3);
+        master_io_registers.write_register(IoRegister::SC, 0x81);
+        tick(
+            &mut master_serial_port,
+            &mut master_io_registers,
+            &mut master_cable,
+            ExecutionMode::GameBoy,
+            4096,
+        );
+        assert_eq!(0x5A, master_io_registers.read_register(IoRegister::SB));
+        assert!(serial_interrupt_requested(&mut master_io_registers));
+
+        assert!(!serial_interrupt_requested(&mut slave_io_registers));
+        tick(
+            &mut slave_serial_port,
+            &mut slave_io_registers,
+            &mut slave_cable,
+            ExecutionMode::GameBoy,
+            4,
+        );
+        assert_eq!(0xC3, slave_io_registers.read_register(IoRegister::SB));
+        assert_eq!(0x7E, slave_io_registers.read_register(IoRegister::SC));
+        assert!(serial_interrupt_requested(&mut slave_io_registers));
+
+        // The slave is no longer waiting, so the next transfer from the master should read 0xFF
+        master_io_registers.write_register(IoRegister::SC, 0x81);
+        tick(
+            &mut master_serial_port,
+            &mut master_io_registers,
+            &mut master_cable,
+            ExecutionMode::GameBoy,
+            4096,
+        );
+        assert_eq!(0xFF, master_io_registers.read_register(IoRegister::SB));
+    }
+}
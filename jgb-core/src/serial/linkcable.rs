@@ -0,0 +1,60 @@
+use crate::serial::SerialDevice;
+use std::cell::RefCell;
+use std::rc::Rc;
+
+#[derive(Debug, Clone, Copy, Default)]
+struct CableEnd {
+    // The contents of this side's SB register while it is waiting for an external clock transfer
+    waiting_byte: Option<u8>,
+    // A byte that the other side shifted in using its internal clock, not yet picked up
+    received_byte: Option<u8>,
+}
+
+/// One end of a link cable connecting two emulator instances running in the same process.
+///
+/// Whichever side starts a transfer using its internal clock exchanges bytes with the other side,
+/// which must be waiting for an external clock transfer. The other side picks up the received
+/// byte the next time its serial port is ticked.
+#[derive(Debug)]
+pub struct LinkCable {
+    ends: Rc<RefCell<[CableEnd; 2]>>,
+    side: usize,
+}
+
+impl LinkCable {
+    /// Create both ends of a new link cable.
+    #[must_use]
+    pub fn new_pair() -> (Self, Self) {
+        let ends = Rc::new(RefCell::new([CableEnd::default(); 2]));
+        (Self { ends: Rc::clone(&ends), side: 0 }, Self { ends, side: 1 })
+    }
+}
+
+impl SerialDevice for LinkCable {
+    fn transfer_internal_clock(&mut self, byte: u8) -> u8 {
+        let mut ends = self.ends.borrow_mut();
+        let other_end = &mut ends[1 - self.side];
+
+        match other_end.waiting_byte.take() {
+            Some(other_byte) => {
+                other_end.received_byte = Some(byte);
+                other_byte
+            }
+            None => 0xFF,
+        }
+    }
+
+    fn poll_external_clock(&mut self, waiting_byte: Option<u8>) -> Option<u8> {
+        let mut ends = self.ends.borrow_mut();
+        let end = &mut ends[self.side];
+
+        let received_byte = end.received_byte.take();
+        if waiting_byte.is_some() && received_byte.is_some() {
+            end.waiting_byte = None;
+            return received_byte;
+        }
+
+        end.waiting_byte = waiting_byte;
+        None
+    }
+}
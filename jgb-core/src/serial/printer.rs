@@ -0,0 +1,505 @@
+use crate::serial::SerialDevice;
+use std::ffi::OsString;
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::{Path, PathBuf};
+use std::{io, mem};
+use thiserror::Error;
+
+// Printer images are always 160 pixels (20 tiles) wide
+const IMAGE_WIDTH: usize = 160;
+const BYTES_PER_TILE_ROW: usize = 20 * 16;
+
+// The printer has 8KB of RAM to buffer image data between print commands
+const IMAGE_BUFFER_SIZE: usize = 0x2000;
+
+// How many status packets to report the printer as busy for after a print command
+const PRINT_BUSY_STATUS_PACKETS: u32 = 5;
+
+// Shades 0-3 from lightest to darkest
+const SHADE_TO_GRAYSCALE: [u8; 4] = [0xFF, 0xAA, 0x55, 0x00];
+
+const MAGIC_1: u8 = 0x88;
+const MAGIC_2: u8 = 0x33;
+const ALIVE: u8 = 0x81;
+
+const STATUS_CHECKSUM_ERROR: u8 = 0x01;
+const STATUS_PRINTING: u8 = 0x02;
+const STATUS_IMAGE_DATA_FULL: u8 = 0x04;
+const STATUS_UNPROCESSED_DATA: u8 = 0x08;
+const STATUS_PACKET_ERROR: u8 = 0x10;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Command {
+    Initialize,
+    Print,
+    Data,
+    Status,
+    Unknown(u8),
+}
+
+impl Command {
+    fn from_byte(byte: u8) -> Self {
+        match byte {
+            0x01 => Self::Initialize,
+            0x02 => Self::Print,
+            0x04 => Self::Data,
+            0x0F => Self::Status,
+            _ => Self::Unknown(byte),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum PacketState {
+    Magic1,
+    Magic2,
+    Command,
+    Compression,
+    LengthLow,
+    LengthHigh,
+    Data,
+    ChecksumLow,
+    ChecksumHigh,
+    KeepAlive,
+    Status,
+}
+
+#[derive(Error, Debug)]
+enum PrintError {
+    #[error("error creating file {}: {source}", path.display())]
+    FileCreate {
+        path: PathBuf,
+        #[source]
+        source: io::Error,
+    },
+    #[error("error encoding PNG: {source}")]
+    PngEncode {
+        #[from]
+        source: png::EncodingError,
+    },
+}
+
+/// A Game Boy Printer connected to the link port.
+///
+/// Each printed image is written to a PNG file next to the ROM file, named
+/// `<rom name>-print-<n>.png`. Consecutive print commands with no bottom margin between them are
+/// treated as parts of the same image, since games use this to print images that are larger than
+/// the printer's image buffer.
+pub struct Printer {
+    output_path_base: PathBuf,
+    state: PacketState,
+    command: Command,
+    compressed: bool,
+    data_length: u16,
+    data: Vec<u8>,
+    checksum: u16,
+    received_checksum: u16,
+    status: u8,
+    busy_status_packets_remaining: u32,
+    // 2bpp tile data received via data commands since the last print command
+    image_buffer: Vec<u8>,
+    // Shades (0-3) of the pixel rows printed since the last image was written to disk
+    printed_rows: Vec<u8>,
+}
+
+impl Printer {
+    /// Create a printer that will write printed images next to the given ROM file.
+    pub fn new<P>(gb_file_path: P) -> Self
+    where
+        P: AsRef<Path>,
+    {
+        Self {
+            output_path_base: gb_file_path.as_ref().with_extension(""),
+            state: PacketState::Magic1,
+            command: Command::Status,
+            compressed: false,
+            data_length: 0,
+            data: Vec::new(),
+            checksum: 0,
+            received_checksum: 0,
+            status: 0,
+            busy_status_packets_remaining: 0,
+            image_buffer: Vec::with_capacity(IMAGE_BUFFER_SIZE),
+            printed_rows: Vec::new(),
+        }
+    }
+
+    fn process_byte(&mut self, byte: u8) -> u8 {
+        match self.state {
+            PacketState::Magic1 => {
+                if byte == MAGIC_1 {
+                    self.state = PacketState::Magic2;
+                }
+            }
+            PacketState::Magic2 => {
+                self.state = match byte {
+                    MAGIC_2 => PacketState::Command,
+                    MAGIC_1 => PacketState::Magic2,
+                    _ => PacketState::Magic1,
+                };
+            }
+            PacketState::Command => {
+                self.command = Command::from_byte(byte);
+                self.checksum = byte.into();
+                self.state = PacketState::Compression;
+            }
+            PacketState::Compression => {
+                self.compressed = byte & 0x01 != 0;
+                self.checksum = self.checksum.wrapping_add(byte.into());
+                self.state = PacketState::LengthLow;
+            }
+            PacketState::LengthLow => {
+                self.data_length = byte.into();
+                self.checksum = self.checksum.wrapping_add(byte.into());
+                self.state = PacketState::LengthHigh;
+            }
+            PacketState::LengthHigh => {
+                self.data_length |= u16::from(byte) << 8;
+                self.checksum = self.checksum.wrapping_add(byte.into());
+                self.data.clear();
+                self.state = if self.data_length == 0 {
+                    PacketState::ChecksumLow
+                } else {
+                    PacketState::Data
+                };
+            }
+            PacketState::Data => {
+                self.data.push(byte);
+                self.checksum = self.checksum.wrapping_add(byte.into());
+                if self.data.len() == usize::from(self.data_length) {
+                    self.state = PacketState::ChecksumLow;
+                }
+            }
+            PacketState::ChecksumLow => {
+                self.received_checksum = byte.into();
+                self.state = PacketState::ChecksumHigh;
+            }
+            PacketState::ChecksumHigh => {
+                self.received_checksum |= u16::from(byte) << 8;
+                self.state = PacketState::KeepAlive;
+            }
+            PacketState::KeepAlive => {
+                // The printer responds to the first byte after the checksum to show that it is
+                // connected, and it executes the packet's command at the same time
+                self.execute_command();
+                self.state = PacketState::Status;
+                return ALIVE;
+            }
+            PacketState::Status => {
+                self.state = PacketState::Magic1;
+                return self.status;
+            }
+        }
+
+        0x00
+    }
+
+    fn execute_command(&mut self) {
+        if self.checksum != self.received_checksum {
+            log::warn!(
+                "Printer packet checksum mismatch: expected {:04X}, received {:04X}",
+                self.checksum,
+                self.received_checksum
+            );
+            self.status |= STATUS_CHECKSUM_ERROR;
+            return;
+        }
+        self.status &= !(STATUS_CHECKSUM_ERROR | STATUS_PACKET_ERROR);
+
+        log::trace!("Executing printer command {:?}", self.command);
+
+        match self.command {
+            Command::Initialize => {
+                self.image_buffer.clear();
+                self.busy_status_packets_remaining = 0;
+                self.status = 0;
+            }
+            Command::Data => {
+                // An empty data packet marks the end of the image data; nothing to do
+                if self.compressed {
+                    decompress(&self.data, &mut self.image_buffer);
+                } else {
+                    self.image_buffer.extend_from_slice(&self.data);
+                }
+                self.image_buffer.truncate(IMAGE_BUFFER_SIZE);
+
+                if !self.image_buffer.is_empty() {
+                    self.status |= STATUS_UNPROCESSED_DATA;
+                }
+                if self.image_buffer.len() == IMAGE_BUFFER_SIZE {
+                    self.status |= STATUS_IMAGE_DATA_FULL;
+                }
+            }
+            Command::Print => {
+                // The 4th byte is exposure (darkness), which is ignored
+                let &[sheets, margins, palette, _] = self.data.as_slice() else {
+                    log::warn!("Invalid printer print command length: {}", self.data.len());
+                    self.status |= STATUS_PACKET_ERROR;
+                    return;
+                };
+
+                // 0 sheets means to only feed paper without printing
+                if sheets != 0 {
+                    self.print_image_buffer(palette);
+                }
+                self.image_buffer.clear();
+
+                self.status &= !(STATUS_UNPROCESSED_DATA | STATUS_IMAGE_DATA_FULL);
+                self.status |= STATUS_PRINTING;
+                self.busy_status_packets_remaining = PRINT_BUSY_STATUS_PACKETS;
+
+                // A bottom margin feeds the paper out of the printer, which ends the current image
+                if margins & 0x0F != 0 {
+                    self.finish_image();
+                }
+            }
+            Command::Status => {
+                if self.busy_status_packets_remaining > 0 {
+                    self.busy_status_packets_remaining -= 1;
+                    if self.busy_status_packets_remaining == 0 {
+                        self.status &= !STATUS_PRINTING;
+                    }
+                }
+            }
+            Command::Unknown(byte) => {
+                log::warn!("Unknown printer command: {byte:02X}");
+                self.status |= STATUS_PACKET_ERROR;
+            }
+        }
+    }
+
+    fn print_image_buffer(&mut self, palette: u8) {
+        // Games that don't care about the palette commonly send 0x00 to mean the default palette
+        let palette = if palette == 0x00 { 0xE4 } else { palette };
+
+        let tile_rows = self.image_buffer.len() / BYTES_PER_TILE_ROW;
+        for tile_row in 0..tile_rows {
+            for line in 0..8 {
+                for x in 0..IMAGE_WIDTH {
+                    let address = tile_row * BYTES_PER_TILE_ROW + (x / 8) * 16 + line * 2;
+                    let bit = 7 - (x % 8);
+                    let lsb = (self.image_buffer[address] >> bit) & 0x01;
+                    let msb = (self.image_buffer[address + 1] >> bit) & 0x01;
+                    let color = (msb << 1) | lsb;
+
+                    let shade = (palette >> (2 * color)) & 0x03;
+                    self.printed_rows.push(shade);
+                }
+            }
+        }
+    }
+
+    fn finish_image(&mut self) {
+        if self.printed_rows.is_empty() {
+            return;
+        }
+
+        let printed_rows = mem::take(&mut self.printed_rows);
+        match self.write_png(&printed_rows) {
+            Ok(path) => log::info!("Wrote printed image to {}", path.display()),
+            Err(err) => log::error!("Error writing printed image: {err}"),
+        }
+    }
+
+    fn next_output_path(&self) -> PathBuf {
+        let mut n = 1;
+        loop {
+            let mut file_name = OsString::from(self.output_path_base.as_os_str());
+            file_name.push(format!("-print-{n:03}.png"));
+
+            let path = PathBuf::from(file_name);
+            if !path.exists() {
+                return path;
+            }
+
+            n += 1;
+        }
+    }
+
+    fn write_png(&self, printed_rows: &[u8]) -> Result<PathBuf, PrintError> {
+        let path = self.next_output_path();
+        let file = File::create(&path)
+            .map_err(|err| PrintError::FileCreate { path: path.clone(), source: err })?;
+
+        let height = printed_rows.len() / IMAGE_WIDTH;
+        let mut encoder =
+            png::Encoder::new(BufWriter::new(file), IMAGE_WIDTH as u32, height as u32);
+        encoder.set_color(png::ColorType::Grayscale);
+        encoder.set_depth(png::BitDepth::Eight);
+
+        let pixels: Vec<_> =
+            printed_rows.iter().map(|&shade| SHADE_TO_GRAYSCALE[usize::from(shade)]).collect();
+
+        let mut writer = encoder.write_header()?;
+        writer.write_image_data(&pixels)?;
+        writer.finish()?;
+
+        Ok(path)
+    }
+}
+
+impl SerialDevice for Printer {
+    fn transfer_internal_clock(&mut self, byte: u8) -> u8 {
+        self.process_byte(byte)
+    }
+}
+
+impl Drop for Printer {
+    fn drop(&mut self) {
+        // Don't lose an image if the game never fed the paper out
+        self.finish_image();
+    }
+}
+
+// Printer data packets use a simple run-length encoding. Each run starts with a control byte; if
+// bit 7 is set then the next byte is repeated (control & 0x7F) + 2 times, otherwise the next
+// (control + 1) bytes are copied as-is.
+fn decompress(data: &[u8], out: &mut Vec<u8>) {
+    let mut i = 0;
+    while i < data.len() {
+        let control = data[i];
+        i += 1;
+
+        if control & 0x80 != 0 {
+            let Some(&byte) = data.get(i) else { break };
+            i += 1;
+
+            let len = usize::from(control & 0x7F) + 2;
+            out.extend(std::iter::repeat_n(byte, len));
+        } else {
+            let len = usize::from(control) + 1;
+            let end = (i + len).min(data.len());
+            out.extend_from_slice(&data[i..end]);
+            i = end;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn packet(command: u8, compression: u8, data: &[u8]) -> Vec<u8> {
+        let length = data.len() as u16;
+        let mut bytes =
+            vec![MAGIC_1, MAGIC_2, command, compression, length as u8, (length >> 8) as u8];
+        bytes.extend_from_slice(data);
+
+        let checksum = bytes[2..].iter().fold(0_u16, |sum, &byte| sum.wrapping_add(byte.into()));
+        bytes.extend([checksum as u8, (checksum >> 8) as u8, 0x00, 0x00]);
+
+        bytes
+    }
+
+    // Send a packet to the printer, returning the keep-alive and status responses
+    fn send_packet(printer: &mut Printer, packet: &[u8]) -> (u8, u8) {
+        let responses: Vec<_> =
+            packet.iter().map(|&byte| printer.transfer_internal_clock(byte)).collect();
+
+        assert!(responses[..responses.len() - 2].iter().all(|&response| response == 0x00));
+        (responses[responses.len() - 2], responses[responses.len() - 1])
+    }
+
+    fn test_output_dir(test_name: &str) -> PathBuf {
+        let dir = std::env::temp_dir()
+            .join(format!("jgb-printer-test-{}-{test_name}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn read_png(path: &Path) -> (u32, u32, Vec<u8>) {
+        let decoder = png::Decoder::new(File::open(path).unwrap());
+        let mut reader = decoder.read_info().unwrap();
+        let mut buf = vec![0; reader.output_buffer_size()];
+        let info = reader.next_frame(&mut buf).unwrap();
+        buf.truncate(info.buffer_size());
+        (info.width, info.height, buf)
+    }
+
+    #[test]
+    fn decompression() {
+        let mut out = Vec::new();
+        decompress(&[0x02, 0x01, 0x02, 0x03, 0x81, 0xAA, 0x00, 0x04], &mut out);
+        assert_eq!(vec![0x01, 0x02, 0x03, 0xAA, 0xAA, 0xAA, 0x04], out);
+    }
+
+    #[test]
+    fn status_and_checksum_error() {
+        let mut printer = Printer::new("/nonexistent/test.gb");
+
+        assert_eq!((ALIVE, 0x00), send_packet(&mut printer, &packet(0x0F, 0x00, &[])));
+
+        let mut bad_packet = packet(0x04, 0x00, &[0x12, 0x34]);
+        bad_packet[8] ^= 0xFF;
+        assert_eq!((ALIVE, STATUS_CHECKSUM_ERROR), send_packet(&mut printer, &bad_packet));
+
+        assert_eq!((ALIVE, 0x00), send_packet(&mut printer, &packet(0x01, 0x00, &[])));
+    }
+
+    #[test]
+    fn print_image() {
+        let dir = test_output_dir("print_image");
+        let mut printer = Printer::new(dir.join("game.gb"));
+
+        send_packet(&mut printer, &packet(0x01, 0x00, &[]));
+
+        // One tile row where every tile uses color 1 for the top line and color 2 for the rest,
+        // followed by one compressed tile row that is entirely color 3
+        let mut tile_row = Vec::new();
+        for _ in 0..20 {
+            tile_row.extend([0xFF, 0x00]);
+            tile_row.extend([0x00, 0xFF].repeat(7));
+        }
+        let compressed_tile_row = [0xFF, 0xFF, 0xFF, 0xFF, 0xBC, 0xFF];
+
+        let (_, status) = send_packet(&mut printer, &packet(0x04, 0x00, &tile_row));
+        assert_eq!(STATUS_UNPROCESSED_DATA, status);
+        send_packet(&mut printer, &packet(0x04, 0x01, &compressed_tile_row));
+        send_packet(&mut printer, &packet(0x04, 0x00, &[]));
+
+        // 1 sheet, bottom margin, palette 0xE4 (identity), default exposure
+        let (_, status) = send_packet(&mut printer, &packet(0x02, 0x00, &[0x01, 0x03, 0xE4, 0x40]));
+        assert_eq!(0x00, status & STATUS_CHECKSUM_ERROR);
+
+        let (_, status) = send_packet(&mut printer, &packet(0x0F, 0x00, &[]));
+        assert_eq!(STATUS_PRINTING, status);
+        for _ in 0..PRINT_BUSY_STATUS_PACKETS {
+            send_packet(&mut printer, &packet(0x0F, 0x00, &[]));
+        }
+        let (_, status) = send_packet(&mut printer, &packet(0x0F, 0x00, &[]));
+        assert_eq!(0x00, status);
+
+        let (width, height, pixels) = read_png(&dir.join("game-print-001.png"));
+        assert_eq!(160, width);
+        assert_eq!(16, height);
+        assert!(pixels[..160].iter().all(|&pixel| pixel == SHADE_TO_GRAYSCALE[1]));
+        assert!(pixels[160..8 * 160].iter().all(|&pixel| pixel == SHADE_TO_GRAYSCALE[2]));
+        assert!(pixels[8 * 160..].iter().all(|&pixel| pixel == SHADE_TO_GRAYSCALE[3]));
+
+        drop(printer);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn images_without_bottom_margin_are_joined() {
+        let dir = test_output_dir("joined");
+        let mut printer = Printer::new(dir.join("game.gb"));
+
+        let tile_row = vec![0x00; BYTES_PER_TILE_ROW];
+        for margins in [0x10, 0x00, 0x03] {
+            send_packet(&mut printer, &packet(0x01, 0x00, &[]));
+            send_packet(&mut printer, &packet(0x04, 0x00, &tile_row));
+            send_packet(&mut printer, &packet(0x02, 0x00, &[0x01, margins, 0xE4, 0x40]));
+        }
+
+        let (_, height, _) = read_png(&dir.join("game-print-001.png"));
+        assert_eq!(24, height);
+        assert!(!dir.join("game-print-002.png").exists());
+
+        drop(printer);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
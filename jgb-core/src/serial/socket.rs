@@ -0,0 +1,436 @@
+use crate::config::LinkCableConfig;
+use crate::serial::SerialDevice;
+use std::io::{Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::mpsc;
+use std::sync::mpsc::{Receiver, RecvError, TryRecvError};
+use std::{io, thread};
+
+#[cfg(unix)]
+use std::fs;
+#[cfg(unix)]
+use std::os::unix::net::{UnixListener, UnixStream};
+
+// Addresses with this prefix are treated as Unix domain socket paths rather than TCP addresses
+const UNIX_SOCKET_PREFIX: &str = "unix:";
+
+// Only check the socket for incoming transfers once every this many polls while waiting for an
+// external clock transfer. Polls happen every M-cycle, and this is still well under the 128
+// M-cycles that a single bit takes at the normal serial clock speed.
+const POLL_INTERVAL: u32 = 64;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Message {
+    // The sender started a transfer using its internal clock and shifted out this byte
+    Transfer(u8),
+    // Response to a Transfer message containing the byte shifted back in
+    TransferReply(u8),
+    // The sender finished emulating a frame
+    FrameSync,
+}
+
+impl Message {
+    fn to_bytes(self) -> [u8; 2] {
+        match self {
+            Self::Transfer(byte) => [0x01, byte],
+            Self::TransferReply(byte) => [0x02, byte],
+            Self::FrameSync => [0x03, 0x00],
+        }
+    }
+
+    fn from_bytes(bytes: [u8; 2]) -> Option<Self> {
+        match bytes[0] {
+            0x01 => Some(Self::Transfer(bytes[1])),
+            0x02 => Some(Self::TransferReply(bytes[1])),
+            0x03 => Some(Self::FrameSync),
+            _ => None,
+        }
+    }
+}
+
+/// A link cable to another jgb process over a local TCP or Unix domain socket.
+///
+/// The two sides run in lockstep: at the end of every frame each side sends a sync message and then
+/// waits for the other side's sync message for the same frame, so neither side can run more than
+/// one frame ahead of the other. Transfers started with the internal clock block until the other
+/// side responds.
+///
+/// If the connection is lost, the cable behaves as if nothing is connected.
+pub struct SocketLinkCable {
+    writer: Box<dyn Write + Send>,
+    receiver: Receiver<Message>,
+    connected: bool,
+    // The contents of SB as of the last poll if waiting for an external clock transfer
+    waiting_byte: Option<u8>,
+    // A byte received from an external clock transfer that was serviced while blocked
+    received_byte: Option<u8>,
+    frames_sent: u64,
+    frames_received: u64,
+    polls_since_check: u32,
+}
+
+impl SocketLinkCable {
+    /// Open a link cable using the given config, blocking until the other side connects if
+    /// listening.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if unable to bind/connect to the configured address.
+    pub fn open(config: &LinkCableConfig) -> Result<Self, io::Error> {
+        match config {
+            LinkCableConfig::Listen(address) => Self::listen(address),
+            LinkCableConfig::Connect(address) => Self::connect(address),
+        }
+    }
+
+    /// Listen on the given address and block until the other side connects. Addresses of the form
+    /// `unix:<path>` are Unix domain socket paths, and all other addresses are TCP addresses.
+    ///
+    /// Binding to a Unix socket path fails if a file already exists there, since another instance
+    /// may be listening on it. The socket file is removed once the other side connects.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if unable to bind to the address or accept a connection.
+    pub fn listen(address: &str) -> Result<Self, io::Error> {
+        #[cfg(unix)]
+        if let Some(path) = address.strip_prefix(UNIX_SOCKET_PREFIX) {
+            log::info!("Waiting for link cable connection on Unix socket {path}");
+            let listener = UnixListener::bind(path).map_err(|err| {
+                if err.kind() == io::ErrorKind::AddrInUse {
+                    io::Error::new(
+                        io::ErrorKind::AddrInUse,
+                        format!(
+                            "{path} already exists; another jgb instance may be listening on it, or it was left behind by an instance that exited while waiting and can be deleted"
+                        ),
+                    )
+                } else {
+                    err
+                }
+            })?;
+            let accept_result = listener.accept();
+
+            // The socket file is not needed after accepting, and leaving it in place would make
+            // binding to the same path fail next time
+            if let Err(err) = fs::remove_file(path) {
+                log::warn!("Unable to remove Unix socket file {path}: {err}");
+            }
+
+            let (stream, _) = accept_result?;
+            log::info!("Link cable connected");
+            return Self::from_unix_stream(stream);
+        }
+
+        log::info!("Waiting for link cable connection on {address}");
+        let listener = TcpListener::bind(address)?;
+        let (stream, peer_address) = listener.accept()?;
+        log::info!("Link cable connected to {peer_address}");
+        Self::from_tcp_stream(stream)
+    }
+
+    /// Connect to the given address, which should be another jgb instance that is listening.
+    /// Addresses of the form `unix:<path>` are Unix domain socket paths, and all other addresses
+    /// are TCP addresses.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if unable to connect to the address.
+    pub fn connect(address: &str) -> Result<Self, io::Error> {
+        #[cfg(unix)]
+        if let Some(path) = address.strip_prefix(UNIX_SOCKET_PREFIX) {
+            let stream = UnixStream::connect(path)?;
+            log::info!("Link cable connected to Unix socket {path}");
+            return Self::from_unix_stream(stream);
+        }
+
+        let stream = TcpStream::connect(address)?;
+        log::info!("Link cable connected to {address}");
+        Self::from_tcp_stream(stream)
+    }
+
+    fn from_tcp_stream(stream: TcpStream) -> Result<Self, io::Error> {
+        // Transfers are tiny and latency-sensitive
+        stream.set_nodelay(true)?;
+        let reader = stream.try_clone()?;
+        Ok(Self::new(reader, stream))
+    }
+
+    #[cfg(unix)]
+    fn from_unix_stream(stream: UnixStream) -> Result<Self, io::Error> {
+        let reader = stream.try_clone()?;
+        Ok(Self::new(reader, stream))
+    }
+
+    fn new<R, W>(mut reader: R, writer: W) -> Self
+    where
+        R: Read + Send + 'static,
+        W: Write + Send + 'static,
+    {
+        // Read messages on a separate thread so that polling for messages does not require a
+        // syscall
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(move || {
+            let mut buf = [0; 2];
+            while reader.read_exact(&mut buf).is_ok() {
+                let Some(message) = Message::from_bytes(buf) else {
+                    log::error!("Received invalid link cable message: {buf:02X?}");
+                    break;
+                };
+
+                if sender.send(message).is_err() {
+                    break;
+                }
+            }
+        });
+
+        Self {
+            writer: Box::new(writer),
+            receiver,
+            connected: true,
+            waiting_byte: None,
+            received_byte: None,
+            frames_sent: 0,
+            frames_received: 0,
+            polls_since_check: 0,
+        }
+    }
+
+    fn send(&mut self, message: Message) {
+        if !self.connected {
+            return;
+        }
+
+        if let Err(err) = self.writer.write_all(&message.to_bytes()) {
+            log::error!("Link cable disconnected, error sending message: {err}");
+            self.connected = false;
+        }
+    }
+
+    fn receive_blocking(&mut self) -> Option<Message> {
+        if !self.connected {
+            return None;
+        }
+
+        match self.receiver.recv() {
+            Ok(message) => Some(message),
+            Err(RecvError) => {
+                log::error!("Link cable disconnected");
+                self.connected = false;
+                None
+            }
+        }
+    }
+
+    // Handle a message that is not a reply to a transfer started by this side
+    fn handle_message(&mut self, message: Message) {
+        match message {
+            Message::Transfer(byte) => {
+                // Only shift bytes if this side is actually waiting for an external clock
+                // transfer; otherwise the other side reads 0xFF
+                if let Some(waiting_byte) = self.waiting_byte.take() {
+                    self.received_byte = Some(byte);
+                    self.send(Message::TransferReply(waiting_byte));
+                } else {
+                    self.send(Message::TransferReply(0xFF));
+                }
+            }
+            Message::TransferReply(byte) => {
+                log::warn!("Ignoring unexpected link cable transfer reply {byte:02X}");
+            }
+            Message::FrameSync => {
+                self.frames_received += 1;
+            }
+        }
+    }
+}
+
+impl SerialDevice for SocketLinkCable {
+    fn transfer_internal_clock(&mut self, byte: u8) -> u8 {
+        // This side is driving the clock, so it is not waiting for an external clock transfer
+        self.waiting_byte = None;
+        self.received_byte = None;
+
+        self.send(Message::Transfer(byte));
+
+        // Block until the other side responds, handling its messages in the meantime. If both
+        // sides start a transfer at the same time then both will read 0xFF.
+        while let Some(message) = self.receive_blocking() {
+            match message {
+                Message::TransferReply(received_byte) => return received_byte,
+                _ => self.handle_message(message),
+            }
+        }
+
+        0xFF
+    }
+
+    fn poll_external_clock(&mut self, waiting_byte: Option<u8>) -> Option<u8> {
+        if waiting_byte.is_none() {
+            self.waiting_byte = None;
+            self.received_byte = None;
+            return None;
+        }
+
+        if let Some(received_byte) = self.received_byte.take() {
+            self.waiting_byte = None;
+            return Some(received_byte);
+        }
+
+        self.waiting_byte = waiting_byte;
+
+        self.polls_since_check += 1;
+        if self.polls_since_check < POLL_INTERVAL || !self.connected {
+            return None;
+        }
+        self.polls_since_check = 0;
+
+        loop {
+            match self.receiver.try_recv() {
+                Ok(message) => self.handle_message(message),
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => {
+                    log::error!("Link cable disconnected");
+                    self.connected = false;
+                    break;
+                }
+            }
+        }
+
+        let received_byte = self.received_byte.take();
+        if received_byte.is_some() {
+            self.waiting_byte = None;
+        }
+        received_byte
+    }
+
+    fn end_frame(&mut self) {
+        self.send(Message::FrameSync);
+        self.frames_sent += 1;
+
+        while self.frames_received < self.frames_sent {
+            let Some(message) = self.receive_blocking() else { break };
+            self.handle_message(message);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cpu::{ExecutionMode, InterruptType};
+    use crate::memory::ioregisters::{IoRegister, IoRegisters};
+    use crate::serial;
+    use crate::serial::SerialPort;
+
+    // Run one side of the link for the given number of frames, returning the final SB value and
+    // the frame on which the transfer completed
+    fn run_side(mut cable: SocketLinkCable, sb: u8, sc: u8, frames: usize) -> (u8, Option<usize>) {
+        let mut io_registers = IoRegisters::new(ExecutionMode::GameBoy);
+        io_registers.interrupt_flags().clear(InterruptType::Serial);
+        let mut serial_port = SerialPort::new();
+
+        io_registers.write_register(IoRegister::SB, sb);
+        io_registers.write_register(IoRegister::SC, sc);
+
+        let mut completed_frame = None;
+        for frame in 0..frames {
+            for _ in 0..17556 {
+                serial::tick(
+                    &mut serial_port,
+                    &mut io_registers,
+                    &mut cable,
+                    ExecutionMode::GameBoy,
+                    4,
+                );
+            }
+
+            if completed_frame.is_none()
+                && io_registers.interrupt_flags().get(InterruptType::Serial)
+            {
+                completed_frame = Some(frame);
+            }
+
+            cable.end_frame();
+        }
+
+        (io_registers.read_register(IoRegister::SB), completed_frame)
+    }
+
+    #[test]
+    fn tcp_transfer() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+
+        let slave = thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let cable = SocketLinkCable::from_tcp_stream(stream).unwrap();
+            run_side(cable, 0x5A, 0x80, 5)
+        });
+
+        let cable = SocketLinkCable::connect(&address.to_string()).unwrap();
+        let (master_sb, master_completed_frame) = run_side(cable, 0xC3, 0x81, 5);
+
+        let (slave_sb, slave_completed_frame) = slave.join().unwrap();
+
+        assert_eq!(0x5A, master_sb);
+        assert_eq!(0xC3, slave_sb);
+        assert_eq!(Some(0), master_completed_frame);
+        // The slave may not see the transfer until it syncs at the end of the frame, in which case
+        // the transfer completes during its next frame
+        assert!(slave_completed_frame.is_some_and(|frame| frame <= 1));
+    }
+
+    #[test]
+    fn both_sides_internal_clock() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+
+        let other = thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let cable = SocketLinkCable::from_tcp_stream(stream).unwrap();
+            run_side(cable, 0x5A, 0x81, 2)
+        });
+
+        let cable = SocketLinkCable::connect(&address.to_string()).unwrap();
+        let (sb, completed_frame) = run_side(cable, 0xC3, 0x81, 2);
+
+        let (other_sb, other_completed_frame) = other.join().unwrap();
+
+        // Neither side was listening for an external clock, so both should read 0xFF
+        assert_eq!(0xFF, sb);
+        assert_eq!(0xFF, other_sb);
+        assert_eq!(Some(0), completed_frame);
+        assert_eq!(Some(0), other_completed_frame);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn unix_socket_transfer() {
+        let path = std::env::temp_dir().join(format!("jgb-link-test-{}.sock", std::process::id()));
+        let address = format!("{UNIX_SOCKET_PREFIX}{}", path.display());
+
+        let listen_address = address.clone();
+        let slave = thread::spawn(move || {
+            let cable = SocketLinkCable::listen(&listen_address).unwrap();
+            run_side(cable, 0x5A, 0x80, 5)
+        });
+
+        while !path.exists() {
+            thread::sleep(std::time::Duration::from_millis(10));
+        }
+
+        // A second listener on the same path fails without disturbing the first
+        let err = SocketLinkCable::listen(&address).err().unwrap();
+        assert_eq!(io::ErrorKind::AddrInUse, err.kind());
+        assert!(path.exists());
+
+        let cable = SocketLinkCable::connect(&address).unwrap();
+        let (master_sb, _) = run_side(cable, 0xC3, 0x81, 5);
+
+        let (slave_sb, _) = slave.join().unwrap();
+
+        assert_eq!(0x5A, master_sb);
+        assert_eq!(0xC3, slave_sb);
+        assert!(!path.exists());
+    }
+}
@@ -1,12 +1,20 @@
-use crate::apu::ApuState;
-use crate::memory::AddressSpace;
-use crate::startup::EmulationState;
-use serde::de::{SeqAccess, Visitor};
+use crate::memory::address;
+#[cfg(feature = "sdl")]
+use crate::ppu::FrameBuffer;
+use crate::ppu::{SCREEN_HEIGHT, SCREEN_WIDTH};
+use crate::startup::{ControllerStates, EmulationState};
+#[cfg(feature = "sdl")]
+use crate::{GbColorScheme, GbcColorCorrection, graphics};
+use bincode::Options;
+use serde::de::{DeserializeOwned, SeqAccess, Visitor};
 use serde::ser::SerializeTuple;
 use serde::{Deserialize, Deserializer, Serialize, Serializer, de};
+#[cfg(feature = "sdl")]
+use std::array;
 use std::fmt::Formatter;
 use std::marker::PhantomData;
 use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
 use std::{fs, io};
 use thiserror::Error;
 
@@ -22,6 +30,153 @@ pub enum SaveStateError {
         #[from]
         source: io::Error,
     },
+    #[error("save state file is invalid or truncated")]
+    InvalidFormat,
+    #[error(
+        "save state format version {version} is not supported; this version of jgb can load versions {}-{}",
+        OLDEST_SUPPORTED_FORMAT_VERSION,
+        SAVE_STATE_FORMAT_VERSION
+    )]
+    IncompatibleVersion { version: u32 },
+    #[error("save state is missing the '{tag}' section")]
+    MissingSection { tag: String },
+    #[error("error deserializing the '{tag}' section of save state: {source}")]
+    Section { tag: String, source: bincode::Error },
+    #[error(
+        "save state is for a different ROM ('{state_title}', checksum {state_checksum:04X}); current ROM is '{rom_title}', checksum {rom_checksum:04X}"
+    )]
+    RomMismatch { state_title: String, state_checksum: u16, rom_title: String, rom_checksum: u16 },
+}
+
+/// The number of numbered save state slots available for each ROM.
+pub const SAVE_STATE_SLOTS: u8 = 10;
+
+// Save state files are laid out as:
+//   the magic bytes "JGBS"
+//   format version (u32, little-endian)
+//   section count (u32, little-endian)
+//   for each section: a 4-byte tag, data length (u64, little-endian), and the section's data
+// Each section contains one bincode-serialized value. Sections with unknown tags are ignored, so
+// adding a section does not require a format version bump
+const SAVE_STATE_MAGIC: [u8; 4] = *b"JGBS";
+
+// Bump this whenever a change to a serialized struct would prevent existing states from
+// deserializing, and add an entry to MIGRATIONS that upgrades states from the previous version
+const SAVE_STATE_FORMAT_VERSION: u32 = 2;
+const OLDEST_SUPPORTED_FORMAT_VERSION: u32 = 1;
+
+// Save states written before the format was versioned are the raw bincode-serialized
+// EmulationState with no magic bytes, and are reported as this version
+const LEGACY_FORMAT_VERSION: u32 = 0;
+
+type SectionTag = [u8; 4];
+
+const HEADER_SECTION: SectionTag = *b"HEAD";
+const EXECUTION_MODE_SECTION: SectionTag = *b"MODE";
+const ADDRESS_SPACE_SECTION: SectionTag = *b"MEM ";
+const CPU_SECTION: SectionTag = *b"CPU ";
+const PPU_SECTION: SectionTag = *b"PPU ";
+const APU_SECTION: SectionTag = *b"APU ";
+const TIMER_SECTION: SectionTag = *b"TIMR";
+
+// Upgrades the sections of a save state from the paired format version to the next version
+type Migration = fn(&mut SaveStateSections) -> Result<(), SaveStateError>;
+
+// Version 1 predates sections and is converted directly to version 2 in read_v1_sections
+const MIGRATIONS: &[(u32, Migration)] = &[];
+
+const THUMBNAIL_LEN: usize = SCREEN_WIDTH as usize * SCREEN_HEIGHT as usize;
+
+/// Metadata stored at the start of every save state file, which can be read without loading the
+/// rest of the state.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SaveStateHeader {
+    pub format_version: u32,
+    pub rom_title: String,
+    /// The global checksum from the cartridge header.
+    pub rom_checksum: u16,
+    /// When the state was saved, in seconds since the Unix epoch.
+    pub timestamp: u64,
+    /// Whether the thumbnail contains GBC colors rather than GB shades; see
+    /// [`FrameOutput`](crate::FrameOutput).
+    pub gbc_colors: bool,
+    /// The contents of the frame buffer when the state was saved, in row-major order.
+    pub thumbnail: Vec<u16>,
+}
+
+impl SaveStateHeader {
+    fn new(state: &EmulationState) -> Self {
+        let (rom_title, rom_checksum) = rom_identity(state.address_space.cartridge_header());
+        let timestamp =
+            SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |duration| duration.as_secs());
+
+        Self {
+            format_version: SAVE_STATE_FORMAT_VERSION,
+            rom_title,
+            rom_checksum,
+            timestamp,
+            gbc_colors: state.gbc_colors(),
+            thumbnail: state.ppu_state.frame_buffer().iter().flatten().copied().collect(),
+        }
+    }
+
+    fn validate(&self) -> Result<(), SaveStateError> {
+        let valid_thumbnail = self.thumbnail.len() == THUMBNAIL_LEN
+            && (self.gbc_colors || self.thumbnail.iter().all(|&color| color <= 3));
+        if valid_thumbnail { Ok(()) } else { Err(SaveStateError::InvalidFormat) }
+    }
+
+    fn check_rom(&self, state: &EmulationState) -> Result<(), SaveStateError> {
+        let (rom_title, rom_checksum) = rom_identity(state.address_space.cartridge_header());
+        if rom_title != self.rom_title || rom_checksum != self.rom_checksum {
+            return Err(SaveStateError::RomMismatch {
+                state_title: self.rom_title.clone(),
+                state_checksum: self.rom_checksum,
+                rom_title,
+                rom_checksum,
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Convert the thumbnail to packed 24-bit RGB pixels, using the same colors that are used to
+    /// render frames to the emulator window.
+    #[cfg(feature = "sdl")]
+    #[must_use]
+    pub fn thumbnail_rgb(
+        &self,
+        color_scheme: GbColorScheme,
+        gbc_color_correction: GbcColorCorrection,
+    ) -> Vec<u8> {
+        let frame_buffer: Box<FrameBuffer> = Box::new(array::from_fn(|row| {
+            array::from_fn(|col| {
+                self.thumbnail.get(row * SCREEN_WIDTH as usize + col).copied().unwrap_or(0)
+            })
+        }));
+
+        graphics::frame_buffer_to_rgb(
+            self.gbc_colors,
+            &frame_buffer,
+            color_scheme,
+            gbc_color_correction,
+        )
+    }
+}
+
+// Identify a ROM by the title and global checksum in its header
+pub(crate) fn rom_identity(header: &[u8]) -> (String, u16) {
+    let title = &header[address::TITLE_START as usize..=address::TITLE_END as usize];
+    let title: String = title
+        .iter()
+        .take_while(|&&byte| byte == b' ' || byte.is_ascii_graphic())
+        .map(|&byte| char::from(byte))
+        .collect();
+
+    let checksum_address = address::GLOBAL_CHECKSUM as usize;
+    let checksum = u16::from_be_bytes([header[checksum_address], header[checksum_address + 1]]);
+
+    (title.trim_end().into(), checksum)
 }
 
 pub fn serialize_array<S, T, const N: usize>(
@@ -91,55 +246,364 @@ where
     deserializer.deserialize_tuple(N, DeserializeArrayVisitor::new())
 }
 
-pub fn determine_save_state_path(gb_file_path: &str) -> PathBuf {
-    Path::new(gb_file_path).with_extension("ss0")
+/// Determine the path of the save state file in the given slot, which is the ROM file path with
+/// the extension replaced by `ss<slot>`.
+#[must_use]
+pub fn determine_save_state_path(gb_file_path: &str, slot: u8) -> PathBuf {
+    Path::new(gb_file_path).with_extension(format!("ss{slot}"))
 }
 
 pub fn save_state<P>(state: &EmulationState, path: P) -> Result<(), SaveStateError>
 where
     P: AsRef<Path>,
 {
-    let serialized_state = bincode::serialize(state)?;
-    fs::write(path.as_ref(), serialized_state)?;
+    fs::write(path.as_ref(), save_state_to_bytes(state)?)?;
 
     log::info!("Successfully wrote save state to '{}'", path.as_ref().display());
 
     Ok(())
 }
 
-pub fn load_state<P>(
-    path: P,
-    existing_apu_state: ApuState,
-    existing_address_space: AddressSpace,
-) -> Result<EmulationState, (SaveStateError, Box<AddressSpace>, Box<ApuState>)>
+/// Read only the header of the save state at the given path.
+///
+/// # Errors
+///
+/// This function will return an error if unable to read the file or if the file is not a valid
+/// save state.
+pub fn read_save_state_header<P>(path: P) -> Result<SaveStateHeader, SaveStateError>
+where
+    P: AsRef<Path>,
+{
+    let serialized_state = fs::read(path.as_ref())?;
+    let (_, header) = SaveStateSections::from_bytes(&serialized_state)?;
+    Ok(header)
+}
+
+/// Load a save state from the given path into `existing_state`, carrying over any fields that are
+/// not serialized. States saved by older versions of jgb are migrated to the current format.
+/// `existing_state` is left unchanged if loading fails, including if the state was saved from a
+/// different ROM.
+///
+/// # Errors
+///
+/// This function will return an error if unable to read the file, if the file's format version is
+/// not supported, if deserialization fails, or if the state was saved from a different ROM.
+pub fn load_state<P>(path: P, existing_state: &mut EmulationState) -> Result<(), SaveStateError>
 where
     P: AsRef<Path>,
 {
-    let serialized_state = match fs::read(path.as_ref()) {
-        Ok(serialized_state) => serialized_state,
-        Err(err) => {
-            return Err((
-                err.into(),
-                Box::new(existing_address_space),
-                Box::new(existing_apu_state),
-            ));
+    let serialized_state = fs::read(path.as_ref())?;
+    let header = load_state_from_bytes(&serialized_state, existing_state)?;
+
+    log::info!(
+        "Successfully loaded save state (format version {}) from '{}'",
+        header.format_version,
+        path.as_ref().display()
+    );
+
+    Ok(())
+}
+
+/// Serialize a state in the same versioned format as save state files, for embedding in other
+/// files.
+pub(crate) fn save_state_to_bytes(state: &EmulationState) -> Result<Vec<u8>, SaveStateError> {
+    let sections = SaveStateSections::from_state(&SaveStateHeader::new(state), state)?;
+    Ok(sections.to_bytes())
+}
+
+/// Load a state produced by [`save_state_to_bytes`] into `existing_state`, returning the state's
+/// header. `existing_state` is left unchanged if loading fails.
+pub(crate) fn load_state_from_bytes(
+    serialized_state: &[u8],
+    existing_state: &mut EmulationState,
+) -> Result<SaveStateHeader, SaveStateError> {
+    let (sections, header) = SaveStateSections::from_bytes(serialized_state)?;
+    header.check_rom(existing_state)?;
+    replace_state(sections.to_state()?, existing_state);
+
+    Ok(header)
+}
+
+pub fn serialize_state(state: &EmulationState) -> Result<Vec<u8>, SaveStateError> {
+    Ok(bincode::serialize(state)?)
+}
+
+/// Deserialize an in-memory save state into `existing_state`, carrying over any fields that are
+/// not serialized. `existing_state` is left unchanged if deserialization fails.
+///
+/// Unlike save state files, in-memory states are not versioned.
+///
+/// # Errors
+///
+/// This function will return an error if deserialization fails.
+pub fn deserialize_state(
+    serialized_state: &[u8],
+    existing_state: &mut EmulationState,
+) -> Result<(), SaveStateError> {
+    let state: EmulationState = bincode::deserialize(serialized_state)?;
+    replace_state(state, existing_state);
+
+    Ok(())
+}
+
+fn replace_state(mut state: EmulationState, existing_state: &mut EmulationState) {
+    std::mem::swap(existing_state, &mut state);
+    let EmulationState { address_space, apu_state, controller_states, .. } = state;
+    existing_state.address_space.move_unserializable_fields_from(address_space);
+    existing_state.apu_state.move_unserializable_fields_from(apu_state);
+    existing_state.controller_states = controller_states;
+}
+
+#[derive(Debug, Default)]
+struct SaveStateSections(Vec<(SectionTag, Vec<u8>)>);
+
+impl SaveStateSections {
+    fn from_state(
+        header: &SaveStateHeader,
+        state: &EmulationState,
+    ) -> Result<Self, SaveStateError> {
+        let mut sections = Self::default();
+        sections.push(HEADER_SECTION, header)?;
+        sections.push(EXECUTION_MODE_SECTION, &state.execution_mode)?;
+        sections.push(ADDRESS_SPACE_SECTION, &state.address_space)?;
+        sections.push(CPU_SECTION, &state.cpu_registers)?;
+        sections.push(PPU_SECTION, &state.ppu_state)?;
+        sections.push(APU_SECTION, &state.apu_state)?;
+        sections.push(TIMER_SECTION, &state.timer_counter)?;
+
+        Ok(sections)
+    }
+
+    fn to_state(&self) -> Result<EmulationState, SaveStateError> {
+        Ok(EmulationState {
+            execution_mode: self.get(EXECUTION_MODE_SECTION)?,
+            address_space: self.get(ADDRESS_SPACE_SECTION)?,
+            cpu_registers: self.get(CPU_SECTION)?,
+            ppu_state: self.get(PPU_SECTION)?,
+            apu_state: self.get(APU_SECTION)?,
+            timer_counter: self.get(TIMER_SECTION)?,
+            controller_states: ControllerStates::default(),
+        })
+    }
+
+    fn push<T: Serialize>(&mut self, tag: SectionTag, value: &T) -> Result<(), SaveStateError> {
+        self.0.push((tag, bincode::serialize(value)?));
+        Ok(())
+    }
+
+    fn get<T: DeserializeOwned>(&self, tag: SectionTag) -> Result<T, SaveStateError> {
+        let tag_name = || String::from_utf8_lossy(&tag).trim_end().to_string();
+
+        let (_, data) = self
+            .0
+            .iter()
+            .find(|(section_tag, _)| *section_tag == tag)
+            .ok_or_else(|| SaveStateError::MissingSection { tag: tag_name() })?;
+
+        bincode::deserialize(data)
+            .map_err(|source| SaveStateError::Section { tag: tag_name(), source })
+    }
+
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = SAVE_STATE_MAGIC.to_vec();
+        bytes.extend(SAVE_STATE_FORMAT_VERSION.to_le_bytes());
+        bytes.extend((self.0.len() as u32).to_le_bytes());
+
+        for (tag, data) in &self.0 {
+            bytes.extend(tag);
+            bytes.extend((data.len() as u64).to_le_bytes());
+            bytes.extend(data);
         }
-    };
-    let mut state: EmulationState = match bincode::deserialize(&serialized_state) {
-        Ok(state) => state,
-        Err(err) => {
-            return Err((
-                err.into(),
-                Box::new(existing_address_space),
-                Box::new(existing_apu_state),
-            ));
+
+        bytes
+    }
+
+    // Parse a save state file of any supported format version, migrating it to the current
+    // version. Also returns the header, with the format version set to the file's version
+    fn from_bytes(mut bytes: &[u8]) -> Result<(Self, SaveStateHeader), SaveStateError> {
+        if take_array(&mut bytes)? != SAVE_STATE_MAGIC {
+            return Err(SaveStateError::IncompatibleVersion { version: LEGACY_FORMAT_VERSION });
         }
-    };
 
-    state.address_space.move_unserializable_fields_from(existing_address_space);
-    state.apu_state.move_unserializable_fields_from(existing_apu_state);
+        let bytes_after_magic = bytes;
+        let version = u32::from_le_bytes(take_array(&mut bytes)?);
+        if !(OLDEST_SUPPORTED_FORMAT_VERSION..=SAVE_STATE_FORMAT_VERSION).contains(&version) {
+            return Err(SaveStateError::IncompatibleVersion { version });
+        }
+
+        let mut sections = if version == 1 {
+            Self::read_v1_sections(bytes_after_magic)?
+        } else {
+            let section_count = u32::from_le_bytes(take_array(&mut bytes)?);
+            let mut sections = Vec::new();
+            for _ in 0..section_count {
+                let tag = take_array(&mut bytes)?;
+                let len = usize::try_from(u64::from_le_bytes(take_array(&mut bytes)?))
+                    .map_err(|_| SaveStateError::InvalidFormat)?;
+                let (data, remaining) =
+                    bytes.split_at_checked(len).ok_or(SaveStateError::InvalidFormat)?;
+                sections.push((tag, data.to_vec()));
+                bytes = remaining;
+            }
+            Self(sections)
+        };
+
+        for &(migration_version, migration) in MIGRATIONS {
+            if migration_version >= version {
+                migration(&mut sections)?;
+            }
+        }
+
+        let mut header: SaveStateHeader = sections.get(HEADER_SECTION)?;
+        header.validate()?;
+        header.format_version = version;
+
+        Ok((sections, header))
+    }
 
-    log::info!("Successfully loaded save state from '{}'", path.as_ref().display());
+    // Version 1 states consist of the bincode-serialized header (which starts with the format
+    // version) followed by the bincode-serialized EmulationState. The version 1 layout of every
+    // struct is identical to version 2, so they can be deserialized as the current types. This
+    // will no longer be true once a migration is added, at which point version 1 support should be
+    // dropped by raising OLDEST_SUPPORTED_FORMAT_VERSION
+    fn read_v1_sections(bytes_after_magic: &[u8]) -> Result<Self, SaveStateError> {
+        // Same options as bincode::deserialize, but with a limit so that a corrupted length can't
+        // cause a huge allocation
+        let options = bincode::DefaultOptions::new()
+            .with_fixint_encoding()
+            .allow_trailing_bytes()
+            .with_limit(bytes_after_magic.len() as u64);
+
+        let mut reader = bytes_after_magic;
+        let header: SaveStateHeader = options.deserialize_from(&mut reader)?;
+        let state: EmulationState = options.deserialize_from(&mut reader)?;
+
+        Self::from_state(&header, &state)
+    }
+}
+
+fn take_array<const N: usize>(bytes: &mut &[u8]) -> Result<[u8; N], SaveStateError> {
+    let (array, remaining) = bytes.split_first_chunk::<N>().ok_or(SaveStateError::InvalidFormat)?;
+    *bytes = remaining;
+    Ok(*array)
+}
 
-    Ok(state)
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn new_test_state(title: &[u8]) -> EmulationState {
+        EmulationState::new_test_state(0x00, title)
+    }
+
+    #[test]
+    fn save_state_round_trip() {
+        let mut state = new_test_state(b"TEST");
+        state.cpu_registers.pc = 0x1234;
+        let bytes = save_state_to_bytes(&state).unwrap();
+
+        state.cpu_registers.pc = 0x5678;
+        load_state_from_bytes(&bytes, &mut state).unwrap();
+        assert_eq!(0x1234, state.cpu_registers.pc);
+
+        let (_, header) = SaveStateSections::from_bytes(&bytes).unwrap();
+        assert_eq!(SAVE_STATE_FORMAT_VERSION, header.format_version);
+        assert_eq!("TEST", header.rom_title);
+        assert_eq!(THUMBNAIL_LEN, header.thumbnail.len());
+
+        let mut other_state = new_test_state(b"OTHER");
+        assert!(matches!(
+            load_state_from_bytes(&bytes, &mut other_state),
+            Err(SaveStateError::RomMismatch { .. })
+        ));
+    }
+
+    #[test]
+    fn save_state_format_versions() {
+        let mut state = new_test_state(b"TEST");
+        state.cpu_registers.pc = 0x1234;
+        let bytes = save_state_to_bytes(&state).unwrap();
+        state.cpu_registers.pc = 0x0000;
+
+        assert!(matches!(
+            load_state_from_bytes(b"JGB", &mut state),
+            Err(SaveStateError::InvalidFormat)
+        ));
+        assert!(matches!(
+            load_state_from_bytes(&bytes[..bytes.len() - 1], &mut state),
+            Err(SaveStateError::InvalidFormat)
+        ));
+
+        let mut future_bytes = bytes.clone();
+        future_bytes[4..8].copy_from_slice(&(SAVE_STATE_FORMAT_VERSION + 1).to_le_bytes());
+        assert!(matches!(
+            load_state_from_bytes(&future_bytes, &mut state),
+            Err(SaveStateError::IncompatibleVersion { version }) if version == SAVE_STATE_FORMAT_VERSION + 1
+        ));
+
+        // Sections with unknown tags are skipped
+        let mut sections = SaveStateSections::from_bytes(&bytes).unwrap().0;
+        sections.0.push((*b"NEW ", vec![1, 2, 3]));
+        load_state_from_bytes(&sections.to_bytes(), &mut state).unwrap();
+        assert_eq!(0x1234, state.cpu_registers.pc);
+
+        sections.0.retain(|&(tag, _)| tag != PPU_SECTION);
+        assert!(matches!(
+            load_state_from_bytes(&sections.to_bytes(), &mut state),
+            Err(SaveStateError::MissingSection { tag }) if tag == "PPU"
+        ));
+    }
+
+    #[test]
+    fn version_1_migration() {
+        let mut state = new_test_state(b"TEST");
+        state.cpu_registers.pc = 0x1234;
+
+        let mut header = SaveStateHeader::new(&state);
+        header.format_version = 1;
+        let mut v1_bytes = SAVE_STATE_MAGIC.to_vec();
+        bincode::serialize_into(&mut v1_bytes, &header).unwrap();
+        v1_bytes.extend(serialize_state(&state).unwrap());
+
+        state.cpu_registers.pc = 0x0000;
+        load_state_from_bytes(&v1_bytes, &mut state).unwrap();
+        assert_eq!(0x1234, state.cpu_registers.pc);
+
+        let (_, header) = SaveStateSections::from_bytes(&v1_bytes).unwrap();
+        assert_eq!(1, header.format_version);
+    }
+
+    #[test]
+    fn legacy_save_state() {
+        // Unversioned save states are the bincode-serialized EmulationState with no header
+        let mut state = new_test_state(b"TEST");
+        let legacy_bytes = bincode::serialize(&state).unwrap();
+
+        state.cpu_registers.pc = 0x1234;
+        assert!(matches!(
+            load_state_from_bytes(&legacy_bytes, &mut state),
+            Err(SaveStateError::IncompatibleVersion { version: LEGACY_FORMAT_VERSION })
+        ));
+        assert_eq!(0x1234, state.cpu_registers.pc);
+    }
+
+    #[test]
+    fn rom_identity_from_header() {
+        let mut header = vec![0; 0x0150];
+        header[0x0134..0x0134 + 9].copy_from_slice(b"POKEMON Y");
+        header[0x0143] = 0x80;
+        header[0x014E] = 0x12;
+        header[0x014F] = 0x34;
+        assert_eq!(("POKEMON Y".into(), 0x1234), rom_identity(&header));
+
+        // Titles that fill the entire field are not terminated
+        header[0x0134..=0x0143].copy_from_slice(b"SUPER MARIOLAND ");
+        assert_eq!(("SUPER MARIOLAND".into(), 0x1234), rom_identity(&header));
+    }
+
+    #[test]
+    fn save_state_paths() {
+        assert_eq!(PathBuf::from("roms/game.ss0"), determine_save_state_path("roms/game.gb", 0));
+        assert_eq!(PathBuf::from("roms/game.ss9"), determine_save_state_path("roms/game.gbc", 9));
+    }
 }
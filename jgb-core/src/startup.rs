@@ -1,25 +1,41 @@
+use crate::HardwareMode;
 use crate::apu::ApuState;
+use crate::camera::{self, CameraImageError, CameraImageSource};
 use crate::config::RunConfig;
 use crate::cpu::{CpuRegisters, ExecutionMode};
 use crate::debug::FileApuDebugSink;
-use crate::graphics::GraphicsError;
 use crate::input::AccelerometerState;
 use crate::memory::{AddressSpace, Cartridge, CartridgeLoadError};
+use crate::ppu;
 use crate::ppu::PpuState;
-use crate::{HardwareMode, audio, graphics};
+use crate::timer::TimerCounter;
+use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::time::SystemTime;
+use std::{fs, io};
+use thiserror::Error;
+
+#[cfg(feature = "sdl")]
+use crate::graphics::GraphicsError;
+#[cfg(feature = "sdl")]
+use crate::{audio, graphics};
+#[cfg(feature = "sdl")]
 use sdl2::audio::AudioQueue;
+#[cfg(feature = "sdl")]
 use sdl2::event::EventType;
+#[cfg(feature = "sdl")]
 use sdl2::render::{TextureCreator, WindowCanvas};
+#[cfg(feature = "sdl")]
 use sdl2::ttf::Sdl2TtfContext;
+#[cfg(feature = "sdl")]
 use sdl2::video::{WindowBuildError, WindowContext};
+#[cfg(feature = "sdl")]
 use sdl2::{EventPump, GameControllerSubsystem, JoystickSubsystem, ttf};
-use serde::{Deserialize, Serialize};
-use std::cell::RefCell;
+#[cfg(feature = "sdl")]
 use std::ffi::OsStr;
-use std::io;
+#[cfg(feature = "sdl")]
 use std::path::Path;
-use std::rc::Rc;
-use thiserror::Error;
 
 #[derive(Error, Debug)]
 pub enum StartupError {
@@ -29,27 +45,58 @@ pub enum StartupError {
         #[source]
         source: CartridgeLoadError,
     },
+    #[cfg(feature = "sdl")]
     #[error("unable to get file name from path: {file_path}")]
     FileName { file_path: String },
+    #[error("error reading boot ROM from {file_path}: {source}")]
+    BootRomRead {
+        file_path: String,
+        #[source]
+        source: io::Error,
+    },
+    #[error(
+        "boot ROM at {file_path} is {len} bytes, expected {expected_len} bytes for hardware mode {hardware_mode}"
+    )]
+    BootRomLength {
+        file_path: String,
+        len: usize,
+        expected_len: usize,
+        hardware_mode: HardwareMode,
+    },
+    #[error("error loading Pocket Camera image source: {source}")]
+    CameraImage {
+        #[from]
+        source: CameraImageError,
+    },
+    #[error("error connecting link cable: {source}")]
+    LinkCable {
+        #[source]
+        source: io::Error,
+    },
     #[error("error initializing audio debugging sink: {source}")]
     AudioDebugInit {
         #[source]
         source: io::Error,
     },
+    #[cfg(feature = "sdl")]
     #[error("SDL2 error: {sdl_error}")]
     GenericSdl { sdl_error: String },
+    #[cfg(feature = "sdl")]
     #[error("error building SDL2 window: {source}")]
     SdlWindowBuild {
         #[from]
         source: WindowBuildError,
     },
+    #[cfg(feature = "sdl")]
     #[error("error building SDL2 canvas: {source}")]
     SdlCanvasBuild {
         #[from]
         source: GraphicsError,
     },
+    #[cfg(feature = "sdl")]
     #[error("SDL2 audio initialization error: {msg}")]
     SdlAudioInit { msg: String },
+    #[cfg(feature = "sdl")]
     #[error("SDL2 TTF initialization error: {source}")]
     SdlTtfInit {
         #[from]
@@ -57,6 +104,7 @@ pub enum StartupError {
     },
 }
 
+#[cfg(feature = "sdl")]
 impl From<String> for StartupError {
     fn from(value: String) -> Self {
         Self::GenericSdl { sdl_error: value }
@@ -69,6 +117,12 @@ pub struct ControllerStates {
     pub rumble_motor_on: Rc<RefCell<bool>>,
     // Set in the main loop based on input events, read in the MBC7 mapper when latching state
     pub accelerometer_state: Rc<RefCell<AccelerometerState>>,
+    // Set at startup, read in the Pocket Camera mapper when capturing an image. A blank image is
+    // captured if this is not set
+    pub camera_image_source: Rc<RefCell<Option<Box<dyn CameraImageSource>>>>,
+    // Set by the emulator while recording or playing back a movie, read in the MBC3, HuC-3, and
+    // TAMA5 mappers instead of the system time so that the real-time clock is deterministic
+    pub rtc_emulated_time: Rc<RefCell<Option<SystemTime>>>,
 }
 
 #[derive(Serialize, Deserialize)]
@@ -78,10 +132,72 @@ pub struct EmulationState {
     pub cpu_registers: CpuRegisters,
     pub ppu_state: PpuState,
     pub apu_state: ApuState,
+    pub timer_counter: TimerCounter,
     #[serde(skip)]
     pub controller_states: ControllerStates,
 }
 
+impl EmulationState {
+    /// Whether the PPU frame buffer contains GBC colors rather than GB shades. This is true in GBC
+    /// mode and also when running in DMG compatibility mode on GBC hardware.
+    pub fn gbc_colors(&self) -> bool {
+        self.execution_mode == ExecutionMode::GameBoyColor
+            || self.address_space.get_io_registers().dmg_compatibility_mode()
+    }
+
+    /// Create a GB state for a synthesized 32KB ROM with the given mapper byte and title, for tests
+    /// that need a complete state without a ROM file. Cartridges with RAM have 8KB.
+    #[cfg(test)]
+    pub(crate) fn new_test_state(mapper_byte: u8, title: &[u8]) -> Self {
+        let mut rom = vec![0x00; 0x8000];
+        rom[0x0134..0x0134 + title.len()].copy_from_slice(title);
+        rom[0x0147] = mapper_byte;
+        rom[0x0149] = 0x02;
+
+        let controller_states = ControllerStates::default();
+        let cartridge = Cartridge::new(rom, None, controller_states.clone())
+            .expect("synthesized test ROM should be valid");
+        let address_space = AddressSpace::new(cartridge, ExecutionMode::GameBoy);
+        let timer_counter = TimerCounter::new(address_space.get_io_registers());
+
+        Self {
+            execution_mode: ExecutionMode::GameBoy,
+            address_space,
+            cpu_registers: CpuRegisters::new(ExecutionMode::GameBoy),
+            ppu_state: PpuState::new(ExecutionMode::GameBoy),
+            apu_state: ApuState::new(),
+            timer_counter,
+            controller_states,
+        }
+    }
+}
+
+// The DMG boot ROM is 256 bytes, and the CGB boot ROM is 2304 bytes (0x0000-0x08FF minus the 256
+// bytes for the cartridge header at 0x0100-0x01FF, which are included in the file as padding)
+const DMG_BOOT_ROM_LEN: usize = 0x0100;
+const CGB_BOOT_ROM_LEN: usize = 0x0900;
+
+fn read_boot_rom(file_path: &str, hardware_mode: HardwareMode) -> Result<Vec<u8>, StartupError> {
+    let boot_rom = fs::read(file_path)
+        .map_err(|err| StartupError::BootRomRead { file_path: file_path.into(), source: err })?;
+
+    let expected_len = match hardware_mode {
+        HardwareMode::GameBoy => DMG_BOOT_ROM_LEN,
+        HardwareMode::GameBoyColor => CGB_BOOT_ROM_LEN,
+    };
+    if boot_rom.len() != expected_len {
+        return Err(StartupError::BootRomLength {
+            file_path: file_path.into(),
+            len: boot_rom.len(),
+            expected_len,
+            hardware_mode,
+        });
+    }
+
+    Ok(boot_rom)
+}
+
+#[cfg(feature = "sdl")]
 pub struct SdlState {
     pub audio_playback_queue: Option<AudioQueue<f32>>,
     pub joystick_subsystem: JoystickSubsystem,
@@ -94,6 +210,10 @@ pub struct SdlState {
 
 pub fn init_emulation_state(run_config: &RunConfig) -> Result<EmulationState, StartupError> {
     let controller_states = ControllerStates::default();
+    if let Some(camera_image_path) = &run_config.camera_image_path {
+        *controller_states.camera_image_source.borrow_mut() =
+            Some(camera::image_source_from_path(camera_image_path)?);
+    }
 
     let cartridge = match Cartridge::from_file(&run_config.gb_file_path, controller_states.clone())
     {
@@ -106,23 +226,48 @@ pub fn init_emulation_state(run_config: &RunConfig) -> Result<EmulationState, St
         }
     };
 
+    let boot_rom = match &run_config.boot_rom_path {
+        Some(boot_rom_path) => Some(read_boot_rom(boot_rom_path, run_config.hardware_mode)?),
+        None => None,
+    };
+
     let execution_mode = match run_config.hardware_mode {
         HardwareMode::GameBoy => ExecutionMode::GameBoy,
         HardwareMode::GameBoyColor => {
-            if cartridge.supports_cgb_mode() {
+            // With a boot ROM, the CGB boot ROM itself is responsible for switching to DMG
+            // compatibility mode if the cartridge does not support CGB mode
+            if cartridge.supports_cgb_mode() || boot_rom.is_some() {
                 ExecutionMode::GameBoyColor
             } else {
                 log::info!(concat!(
                     "GBC hardware mode was specified but cartridge does not support ",
-                    "CGB mode enhancements, running in GB mode",
+                    "CGB mode enhancements, running in DMG compatibility mode",
                 ));
                 ExecutionMode::GameBoy
             }
         }
     };
 
-    let address_space = AddressSpace::new(cartridge, execution_mode);
-    let cpu_registers = CpuRegisters::new(execution_mode);
+    // Without a boot ROM, do what the CGB boot ROM would have done for cartridges that do not
+    // support CGB mode
+    let compatibility_palettes = (matches!(run_config.hardware_mode, HardwareMode::GameBoyColor)
+        && !cartridge.supports_cgb_mode()
+        && boot_rom.is_none())
+    .then(|| ppu::palettes_for_cartridge(cartridge.header()));
+
+    let (mut address_space, cpu_registers) = match boot_rom {
+        Some(boot_rom) => (
+            AddressSpace::new_with_boot_rom(cartridge, execution_mode, boot_rom),
+            CpuRegisters::new_pre_boot(execution_mode),
+        ),
+        None => (AddressSpace::new(cartridge, execution_mode), CpuRegisters::new(execution_mode)),
+    };
+
+    if let Some(compatibility_palettes) = compatibility_palettes {
+        let io_registers = address_space.get_io_registers_mut();
+        io_registers.load_compatibility_palettes(&compatibility_palettes);
+        io_registers.enter_dmg_compatibility_mode();
+    }
     let ppu_state = PpuState::new(execution_mode);
     let apu_state = if run_config.audio_enabled && run_config.audio_debugging_enabled {
         let debug_sink =
@@ -131,6 +276,7 @@ pub fn init_emulation_state(run_config: &RunConfig) -> Result<EmulationState, St
     } else {
         ApuState::new()
     };
+    let timer_counter = TimerCounter::new(address_space.get_io_registers());
 
     Ok(EmulationState {
         execution_mode,
@@ -138,10 +284,12 @@ pub fn init_emulation_state(run_config: &RunConfig) -> Result<EmulationState, St
         cpu_registers,
         ppu_state,
         apu_state,
+        timer_counter,
         controller_states,
     })
 }
 
+#[cfg(feature = "sdl")]
 #[allow(clippy::if_then_some_else_none)]
 pub fn init_sdl_state(run_config: &RunConfig) -> Result<SdlState, StartupError> {
     let sdl = sdl2::init()?;
@@ -186,6 +334,7 @@ pub fn init_sdl_state(run_config: &RunConfig) -> Result<SdlState, StartupError>
     })
 }
 
+#[cfg(feature = "sdl")]
 fn get_window_title(gb_file_path: &str) -> Result<String, StartupError> {
     let file_name = Path::new(gb_file_path).file_name().and_then(OsStr::to_str);
     match file_name {
@@ -1,16 +1,42 @@
 use crate::cpu::InterruptType;
 use crate::memory::ioregisters::{IoRegister, IoRegisters};
+use serde::{Deserialize, Serialize};
+
+// The number of clock cycles that TIMA reads 0x00 after overflowing before it is reloaded from TMA,
+// and also the number of clock cycles after the reload during which TIMA writes are ignored
+const TIMA_RELOAD_DELAY: u8 = 4;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+enum TimaState {
+    Running,
+    // TIMA overflowed and currently reads 0x00. It will be reloaded from TMA and the timer interrupt
+    // will be requested after the given number of clock cycles, unless the CPU writes to TIMA first.
+    Overflowed(u8),
+    // TIMA was just reloaded from TMA. For the given number of clock cycles, CPU writes to TIMA are
+    // ignored and CPU writes to TMA are also copied to TIMA.
+    Reloaded(u8),
+}
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-pub struct TimerCounter(u64);
+/// The timer's internal state. The DIV register is the upper 8 bits of a 16-bit divider that
+/// increments every clock cycle, and TIMA increments whenever a TAC-selected bit of that divider
+/// goes from 1 to 0 while the timer is enabled.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TimerCounter {
+    divider: u16,
+    timer_signal: bool,
+    tima_state: TimaState,
+}
 
 impl TimerCounter {
-    pub fn new() -> Self {
-        Self(0)
+    /// Create a new timer state, starting the divider from the current value of DIV.
+    pub fn new(io_registers: &IoRegisters) -> Self {
+        let divider = u16::from(io_registers.read_register(IoRegister::DIV)) << 8;
+        let timer_signal = timer_signal(divider, io_registers.read_register(IoRegister::TAC));
+        Self { divider, timer_signal, tima_state: TimaState::Running }
     }
 }
 
-const DIV_UPDATE_FREQUENCY: u64 = 256;
+const MAX_CYCLES_PER_UPDATE: u64 = 256;
 
 /// Read the current value of the TMA hardware register (timer modulo).
 pub fn read_timer_modulo(io_registers: &IoRegisters) -> u8 {
@@ -21,7 +47,11 @@ pub fn read_timer_modulo(io_registers: &IoRegisters) -> u8 {
 /// the TAC hardware register (timer control). The DIV register is always updated, but the TIMA
 /// register may or may not be updated depending on whether the timer is enabled in TAC.
 ///
-/// This function will request a timer interrupt if the TIMA register overflows.
+/// CPU writes to the timer registers since the last update are applied first. Writing to DIV
+/// resets the entire divider and writing to TAC can change the selected divider bit, and either
+/// can cause a falling edge that increments TIMA.
+///
+/// This function will request a timer interrupt when TIMA is reloaded after overflowing.
 ///
 /// # Panics
 ///
@@ -29,57 +59,123 @@ pub fn read_timer_modulo(io_registers: &IoRegisters) -> u8 {
 pub fn update_timer_registers(
     io_registers: &mut IoRegisters,
     counter: &mut TimerCounter,
-    timer_modulo: u8,
     cycles: u64,
 ) {
     assert!(
-        cycles <= DIV_UPDATE_FREQUENCY,
-        "cycles must be <= {DIV_UPDATE_FREQUENCY}, was {cycles}"
+        cycles <= MAX_CYCLES_PER_UPDATE,
+        "cycles must be <= {MAX_CYCLES_PER_UPDATE}, was {cycles}"
     );
 
-    let old_cycles = counter.0;
-    let new_cycles = old_cycles + cycles;
-    counter.0 = new_cycles;
+    apply_register_writes(io_registers, counter);
 
-    if old_cycles / DIV_UPDATE_FREQUENCY != new_cycles / DIV_UPDATE_FREQUENCY {
-        let old_div = io_registers.read_register(IoRegister::DIV);
-        io_registers.privileged_set_div(old_div.wrapping_add(1));
+    for _ in 0..cycles {
+        tick_clock(io_registers, counter);
     }
 
-    let timer_control = io_registers.read_register(IoRegister::TAC);
-    if timer_control & 0x04 == 0 {
-        // TIMA updates are disabled
-        return;
+    io_registers.privileged_set_div((counter.divider >> 8) as u8);
+}
+
+fn apply_register_writes(io_registers: &mut IoRegisters, counter: &mut TimerCounter) {
+    if io_registers.get_dirty_bit(IoRegister::DIV) {
+        io_registers.clear_dirty_bit(IoRegister::DIV);
+
+        // All CPU writes to DIV reset the entire divider, which can cause a falling edge
+        counter.divider = 0;
+        update_timer_signal(io_registers, counter);
     }
 
-    let tima_update_frequency_bits = match timer_control & 0x03 {
-        0x00 => 10, // 1024
-        0x01 => 4,  // 16
-        0x02 => 6,  // 64
-        0x03 => 8,  // 256
-        _ => panic!("{timer_control} & 0x03 produced a number that was not 0x00/0x01/0x02/0x03"),
-    };
+    if io_registers.get_dirty_bit(IoRegister::TAC) {
+        io_registers.clear_dirty_bit(IoRegister::TAC);
+
+        // Disabling the timer or selecting a different divider bit can cause a falling edge
+        update_timer_signal(io_registers, counter);
+    }
 
-    let tima_diff =
-        (new_cycles >> tima_update_frequency_bits) - (old_cycles >> tima_update_frequency_bits);
-    let tima_diff: u8 = tima_diff.try_into().expect("TIMA diff should always be less than 256 due to this function not accepting large cycle values");
+    if io_registers.get_dirty_bit(IoRegister::TIMA) {
+        io_registers.clear_dirty_bit(IoRegister::TIMA);
 
-    // This is not the most efficient but generally this loop will only execute 0 or 1 times
-    for _ in 0..tima_diff {
-        let old_tima = io_registers.read_register(IoRegister::TIMA);
-        match old_tima.overflowing_add(1) {
-            (new_tima, false) => {
-                io_registers.write_register(IoRegister::TIMA, new_tima);
+        match counter.tima_state {
+            TimaState::Overflowed(..) => {
+                // Writing to TIMA before the reload cancels both the reload and the interrupt
+                counter.tima_state = TimaState::Running;
             }
-            (_, true) => {
+            TimaState::Reloaded(..) => {
+                // Writes to TIMA on the same cycle as the reload are ignored
+                let timer_modulo = read_timer_modulo(io_registers);
                 io_registers.write_register(IoRegister::TIMA, timer_modulo);
-
-                io_registers.interrupt_flags().set(InterruptType::Timer);
+                io_registers.clear_dirty_bit(IoRegister::TIMA);
             }
+            TimaState::Running => {}
+        }
+    }
+
+    if io_registers.get_dirty_bit(IoRegister::TMA) {
+        io_registers.clear_dirty_bit(IoRegister::TMA);
+
+        // Writes to TMA on the same cycle as the reload also go to TIMA
+        if let TimaState::Reloaded(..) = counter.tima_state {
+            let timer_modulo = read_timer_modulo(io_registers);
+            io_registers.write_register(IoRegister::TIMA, timer_modulo);
+            io_registers.clear_dirty_bit(IoRegister::TIMA);
         }
     }
 }
 
+fn tick_clock(io_registers: &mut IoRegisters, counter: &mut TimerCounter) {
+    counter.tima_state = match counter.tima_state {
+        TimaState::Overflowed(1) => {
+            let timer_modulo = read_timer_modulo(io_registers);
+            io_registers.write_register(IoRegister::TIMA, timer_modulo);
+            io_registers.clear_dirty_bit(IoRegister::TIMA);
+            io_registers.interrupt_flags().set(InterruptType::Timer);
+
+            TimaState::Reloaded(TIMA_RELOAD_DELAY)
+        }
+        TimaState::Overflowed(cycles) => TimaState::Overflowed(cycles - 1),
+        TimaState::Reloaded(1) | TimaState::Running => TimaState::Running,
+        TimaState::Reloaded(cycles) => TimaState::Reloaded(cycles - 1),
+    };
+
+    counter.divider = counter.divider.wrapping_add(1);
+    update_timer_signal(io_registers, counter);
+}
+
+// Increment TIMA if the timer signal has gone from 1 to 0
+fn update_timer_signal(io_registers: &mut IoRegisters, counter: &mut TimerCounter) {
+    let timer_signal = timer_signal(counter.divider, io_registers.read_register(IoRegister::TAC));
+    if counter.timer_signal && !timer_signal {
+        increment_tima(io_registers, counter);
+    }
+    counter.timer_signal = timer_signal;
+}
+
+fn timer_signal(divider: u16, timer_control: u8) -> bool {
+    // The selected bit has a falling edge every 2^(bit + 1) clock cycles
+    let divider_bit = match timer_control & 0x03 {
+        0x00 => 9, // 1024
+        0x01 => 3, // 16
+        0x02 => 5, // 64
+        0x03 => 7, // 256
+        _ => panic!("{timer_control} & 0x03 produced a number that was not 0x00/0x01/0x02/0x03"),
+    };
+
+    timer_control & 0x04 != 0 && divider & (1 << divider_bit) != 0
+}
+
+fn increment_tima(io_registers: &mut IoRegisters, counter: &mut TimerCounter) {
+    let old_tima = io_registers.read_register(IoRegister::TIMA);
+    let new_tima = match old_tima.overflowing_add(1) {
+        (new_tima, false) => new_tima,
+        (_, true) => {
+            counter.tima_state = TimaState::Overflowed(TIMA_RELOAD_DELAY);
+            0x00
+        }
+    };
+
+    io_registers.write_register(IoRegister::TIMA, new_tima);
+    io_registers.clear_dirty_bit(IoRegister::TIMA);
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -100,7 +196,7 @@ mod tests {
     #[test]
     fn divider_register() {
         let mut io_registers = new_io_registers();
-        let mut timer_counter = TimerCounter::new();
+        let mut timer_counter = TimerCounter::new(&io_registers);
 
         // DIV should ignore the timer enabled bit
         io_registers.write_register(IoRegister::TAC, 0x00);
@@ -109,95 +205,176 @@ mod tests {
         io_registers.write_register(IoRegister::DIV, 0x46);
         assert_eq!(0x00, io_registers.read_register(IoRegister::DIV));
 
-        update_timer_registers(&mut io_registers, &mut timer_counter, 0, 20);
+        update_timer_registers(&mut io_registers, &mut timer_counter, 20);
         assert_eq!(0x00, io_registers.read_register(IoRegister::DIV));
-        assert_eq!(20, timer_counter.0);
+        assert_eq!(20, timer_counter.divider);
 
-        update_timer_registers(&mut io_registers, &mut timer_counter, 0, 40);
+        update_timer_registers(&mut io_registers, &mut timer_counter, 40);
         assert_eq!(0x00, io_registers.read_register(IoRegister::DIV));
-        assert_eq!(60, timer_counter.0);
+        assert_eq!(60, timer_counter.divider);
 
-        update_timer_registers(&mut io_registers, &mut timer_counter, 0, 195);
+        update_timer_registers(&mut io_registers, &mut timer_counter, 195);
         assert_eq!(0x00, io_registers.read_register(IoRegister::DIV));
-        assert_eq!(255, timer_counter.0);
+        assert_eq!(255, timer_counter.divider);
 
-        update_timer_registers(&mut io_registers, &mut timer_counter, 0, 1);
+        update_timer_registers(&mut io_registers, &mut timer_counter, 1);
         assert_eq!(0x01, io_registers.read_register(IoRegister::DIV));
-        assert_eq!(256, timer_counter.0);
+        assert_eq!(256, timer_counter.divider);
 
         for _ in 0..254 {
-            update_timer_registers(&mut io_registers, &mut timer_counter, 0, 256);
+            update_timer_registers(&mut io_registers, &mut timer_counter, 256);
         }
 
         assert_eq!(0xFF, io_registers.read_register(IoRegister::DIV));
-        assert_eq!(256 * 255, timer_counter.0);
+        assert_eq!(256 * 255, timer_counter.divider);
 
-        update_timer_registers(&mut io_registers, &mut timer_counter, 0, 256);
+        update_timer_registers(&mut io_registers, &mut timer_counter, 256);
         assert_eq!(0x00, io_registers.read_register(IoRegister::DIV));
-        assert_eq!(256 * 256, timer_counter.0);
+        assert_eq!(0, timer_counter.divider);
     }
 
     #[test]
     fn tima_register() {
         let mut io_registers = new_io_registers();
-        let mut timer_counter = TimerCounter::new();
-
-        let timer_modulo = 0x78;
+        let mut timer_counter = TimerCounter::new(&io_registers);
 
         io_registers.interrupt_flags().clear(InterruptType::Timer);
 
         // Timer enabled, TIMA update frequency 16
+        io_registers.write_register(IoRegister::DIV, 0x00);
         io_registers.write_register(IoRegister::TAC, 0x05);
 
+        io_registers.write_register(IoRegister::TMA, 0x78);
         io_registers.write_register(IoRegister::TIMA, 0xE0);
 
-        update_timer_registers(&mut io_registers, &mut timer_counter, timer_modulo, 15);
+        update_timer_registers(&mut io_registers, &mut timer_counter, 15);
         assert_eq!(0xE0, io_registers.read_register(IoRegister::TIMA));
 
-        update_timer_registers(&mut io_registers, &mut timer_counter, timer_modulo, 1);
+        update_timer_registers(&mut io_registers, &mut timer_counter, 1);
         assert_eq!(0xE1, io_registers.read_register(IoRegister::TIMA));
 
-        update_timer_registers(&mut io_registers, &mut timer_counter, timer_modulo, 40);
+        update_timer_registers(&mut io_registers, &mut timer_counter, 40);
         assert_eq!(0xE3, io_registers.read_register(IoRegister::TIMA));
-        assert_eq!(56, timer_counter.0);
+        assert_eq!(56, timer_counter.divider);
 
-        update_timer_registers(&mut io_registers, &mut timer_counter, timer_modulo, 40);
+        update_timer_registers(&mut io_registers, &mut timer_counter, 40);
         assert_eq!(0xE6, io_registers.read_register(IoRegister::TIMA));
-        assert_eq!(96, timer_counter.0);
+        assert_eq!(96, timer_counter.divider);
 
         for _ in 0..(0xFF - 0xE6) {
-            update_timer_registers(&mut io_registers, &mut timer_counter, timer_modulo, 16);
+            update_timer_registers(&mut io_registers, &mut timer_counter, 16);
         }
 
         assert_eq!(0xFF, io_registers.read_register(IoRegister::TIMA));
         assert!(!io_registers.interrupt_flags().get(InterruptType::Timer));
 
-        update_timer_registers(&mut io_registers, &mut timer_counter, timer_modulo, 16);
+        // TIMA should read 0x00 for 4 cycles after overflowing before it is reloaded from TMA
+        update_timer_registers(&mut io_registers, &mut timer_counter, 16);
+        assert_eq!(0x00, io_registers.read_register(IoRegister::TIMA));
+        assert!(!io_registers.interrupt_flags().get(InterruptType::Timer));
+
+        update_timer_registers(&mut io_registers, &mut timer_counter, 4);
         assert_eq!(0x78, io_registers.read_register(IoRegister::TIMA));
+        assert!(io_registers.interrupt_flags().get(InterruptType::Timer));
 
         // Change update frequency to 64
         io_registers.write_register(IoRegister::TAC, 0x06);
 
-        update_timer_registers(&mut io_registers, &mut timer_counter, timer_modulo, 32);
+        update_timer_registers(&mut io_registers, &mut timer_counter, 32);
         assert_eq!(0x78, io_registers.read_register(IoRegister::TIMA));
-        assert!(io_registers.interrupt_flags().get(InterruptType::Timer));
 
-        update_timer_registers(&mut io_registers, &mut timer_counter, timer_modulo, 40);
+        update_timer_registers(&mut io_registers, &mut timer_counter, 40);
         assert_eq!(0x79, io_registers.read_register(IoRegister::TIMA));
 
         // Disable timer
         io_registers.write_register(IoRegister::TAC, 0x02);
 
-        update_timer_registers(&mut io_registers, &mut timer_counter, timer_modulo, 256);
+        update_timer_registers(&mut io_registers, &mut timer_counter, 256);
         assert_eq!(0x79, io_registers.read_register(IoRegister::TIMA));
     }
 
+    #[test]
+    fn falling_edge_glitches() {
+        let mut io_registers = new_io_registers();
+        let mut timer_counter = TimerCounter::new(&io_registers);
+
+        io_registers.write_register(IoRegister::DIV, 0x00);
+        io_registers.write_register(IoRegister::TAC, 0x05);
+        io_registers.write_register(IoRegister::TIMA, 0x00);
+
+        // Resetting DIV while the selected bit is set should increment TIMA
+        update_timer_registers(&mut io_registers, &mut timer_counter, 8);
+        io_registers.write_register(IoRegister::DIV, 0x00);
+        update_timer_registers(&mut io_registers, &mut timer_counter, 0);
+        assert_eq!(0x01, io_registers.read_register(IoRegister::TIMA));
+
+        // Resetting DIV while the selected bit is clear should not
+        update_timer_registers(&mut io_registers, &mut timer_counter, 4);
+        io_registers.write_register(IoRegister::DIV, 0x00);
+        update_timer_registers(&mut io_registers, &mut timer_counter, 0);
+        assert_eq!(0x01, io_registers.read_register(IoRegister::TIMA));
+
+        // Selecting a divider bit that is clear while the current bit is set should increment TIMA
+        update_timer_registers(&mut io_registers, &mut timer_counter, 8);
+        io_registers.write_register(IoRegister::TAC, 0x04);
+        update_timer_registers(&mut io_registers, &mut timer_counter, 0);
+        assert_eq!(0x02, io_registers.read_register(IoRegister::TIMA));
+
+        // As should disabling the timer while the selected bit is set
+        io_registers.write_register(IoRegister::TAC, 0x05);
+        update_timer_registers(&mut io_registers, &mut timer_counter, 0);
+        io_registers.write_register(IoRegister::TAC, 0x01);
+        update_timer_registers(&mut io_registers, &mut timer_counter, 0);
+        assert_eq!(0x03, io_registers.read_register(IoRegister::TIMA));
+    }
+
+    #[test]
+    fn tima_reload_writes() {
+        let mut io_registers = new_io_registers();
+        let mut timer_counter = TimerCounter::new(&io_registers);
+
+        io_registers.interrupt_flags().clear(InterruptType::Timer);
+        io_registers.write_register(IoRegister::DIV, 0x00);
+        io_registers.write_register(IoRegister::TAC, 0x05);
+        io_registers.write_register(IoRegister::TMA, 0x40);
+        io_registers.write_register(IoRegister::TIMA, 0xFF);
+
+        // Writing TIMA before the reload should cancel both the reload and the interrupt
+        update_timer_registers(&mut io_registers, &mut timer_counter, 16);
+        assert_eq!(0x00, io_registers.read_register(IoRegister::TIMA));
+        io_registers.write_register(IoRegister::TIMA, 0x10);
+        update_timer_registers(&mut io_registers, &mut timer_counter, 4);
+        assert_eq!(0x10, io_registers.read_register(IoRegister::TIMA));
+        assert!(!io_registers.interrupt_flags().get(InterruptType::Timer));
+
+        // Writing TIMA on the reload cycle should be ignored
+        io_registers.write_register(IoRegister::TIMA, 0xFF);
+        update_timer_registers(&mut io_registers, &mut timer_counter, 12);
+        update_timer_registers(&mut io_registers, &mut timer_counter, 4);
+        assert!(io_registers.interrupt_flags().get(InterruptType::Timer));
+        io_registers.write_register(IoRegister::TIMA, 0x10);
+        update_timer_registers(&mut io_registers, &mut timer_counter, 0);
+        assert_eq!(0x40, io_registers.read_register(IoRegister::TIMA));
+
+        // Writing TMA on the reload cycle should also write TIMA
+        io_registers.write_register(IoRegister::TMA, 0x50);
+        update_timer_registers(&mut io_registers, &mut timer_counter, 0);
+        assert_eq!(0x50, io_registers.read_register(IoRegister::TIMA));
+
+        // After the reload cycle, TIMA and TMA writes should behave normally
+        update_timer_registers(&mut io_registers, &mut timer_counter, 4);
+        io_registers.write_register(IoRegister::TIMA, 0x10);
+        io_registers.write_register(IoRegister::TMA, 0x60);
+        update_timer_registers(&mut io_registers, &mut timer_counter, 0);
+        assert_eq!(0x10, io_registers.read_register(IoRegister::TIMA));
+    }
+
     #[test]
     #[should_panic(expected = "cycles must be <= 256")]
     fn cycle_limit() {
         let mut io_registers = new_io_registers();
-        let mut timer_counter = TimerCounter::new();
+        let mut timer_counter = TimerCounter::new(&io_registers);
 
-        update_timer_registers(&mut io_registers, &mut timer_counter, 0, 257);
+        update_timer_registers(&mut io_registers, &mut timer_counter, 257);
     }
 }
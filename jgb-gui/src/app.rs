@@ -1,15 +1,18 @@
 mod config;
 mod input;
+mod savestates;
 
 use anyhow::Context;
 use eframe::Frame;
 use eframe::epaint::Color32;
 use egui::{
-    Align, Button, CentralPanel, Direction, Key, KeyboardShortcut, Layout, Modifiers, TextEdit,
-    TopBottomPanel, ViewportCommand, Widget, Window, menu,
+    Align, Button, CentralPanel, Direction, Key, KeyboardShortcut, Layout, Modifiers, Slider,
+    TextEdit, TopBottomPanel, Ui, ViewportCommand, Widget, Window, menu,
 };
 use egui_extras::{Column, TableBuilder};
-use jgb_core::{EmulationError, GbColorScheme, GbcColorCorrection, HardwareMode, RunConfig};
+use jgb_core::{
+    CpuAccuracy, EmulationError, GbColorScheme, GbcColorCorrection, HardwareMode, RunConfig,
+};
 use rfd::FileDialog;
 use std::ffi::OsStr;
 use std::fs::File;
@@ -24,6 +27,7 @@ use crate::app::config::FullscreenMode;
 use crate::app::input::{
     ControllerSettingsWidget, HotkeySettingsWidget, InputThread, KeyboardSettingsWidget,
 };
+use crate::app::savestates::SaveStatePicker;
 pub use config::AppConfig;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -32,6 +36,7 @@ enum OpenWindow {
     Keyboard,
     Controller,
     Hotkey,
+    SaveStates,
     About,
 }
 
@@ -80,6 +85,7 @@ struct AppState {
     window_height_invalid: bool,
     deadzone_text: String,
     rom_search_results: Vec<RomSearchResult>,
+    save_state_picker: Option<SaveStatePicker>,
 }
 
 impl AppState {
@@ -201,7 +207,8 @@ impl JgbApp {
         if let Some(file) = file.and_then(|file| file.to_str().map(String::from)) {
             self.stop_emulator_if_running();
 
-            self.state.running_emulator = Some(launch_emulator(&file, &self.config, hardware_mode));
+            self.state.running_emulator =
+                Some(launch_emulator(&file, &self.config, hardware_mode, None));
         }
     }
 
@@ -370,6 +377,22 @@ impl JgbApp {
                 ui.checkbox(&mut self.config.audio_60hz_hack_enabled, "Audio 60Hz hack enabled")
                     .on_hover_text("Very slightly increases audio frequency to time audio to 60Hz display speed instead of ~59.7Hz");
 
+                ui.group(|ui| {
+                    ui.label("CPU timing accuracy");
+                    ui.horizontal(|ui| {
+                        ui.radio_value(&mut self.config.cpu_accuracy, CpuAccuracy::Instruction, "Instruction")
+                            .on_hover_text("Execute whole CPU instructions at a time; fastest, and compatible with almost every game");
+                        ui.radio_value(&mut self.config.cpu_accuracy, CpuAccuracy::Cycle, "M-cycle")
+                            .on_hover_text("Run the rest of the hardware between every CPU memory access; slower but more accurate");
+                    });
+                });
+
+                ui.checkbox(&mut self.config.printer_enabled, "Game Boy Printer connected")
+                    .on_hover_text("Printed images are saved as PNG files next to the ROM file");
+
+                ui.add(Slider::new(&mut self.config.rewind_buffer_secs, 0..=60).text("Rewind buffer length (seconds)"))
+                    .on_hover_text("Set to 0 to disable rewind");
+
                 ui.horizontal(|ui| {
                     if !TextEdit::singleline(&mut self.state.window_width_text)
                         .id("window_width".into())
@@ -436,6 +459,14 @@ impl JgbApp {
                     }
                 });
 
+                optional_file_picker(ui, &mut self.config.dmg_boot_rom_path, "DMG boot ROM");
+                optional_file_picker(ui, &mut self.config.cgb_boot_rom_path, "CGB boot ROM");
+                optional_file_picker(
+                    ui,
+                    &mut self.config.camera_image_path,
+                    "Pocket Camera image (PNG)",
+                );
+
                 ui.add_space(20.0);
 
                 ui.with_layout(Layout::top_down(Align::Center), |ui| {
@@ -508,6 +539,38 @@ impl JgbApp {
         }
     }
 
+    fn render_save_state_window(&mut self, ctx: &egui::Context) {
+        let Some(picker) = &self.state.save_state_picker else {
+            self.state.open_window = None;
+            return;
+        };
+
+        let mut picker_open = true;
+        let selected_slot = Window::new(format!("Save States - {}", picker.rom_name))
+            .id("save_states".into())
+            .resizable(false)
+            .open(&mut picker_open)
+            .show(ctx, |ui| picker.ui(ui))
+            .and_then(|response| response.inner.flatten());
+
+        if let Some(slot) = selected_slot {
+            let picker = self.state.save_state_picker.take().unwrap();
+            self.stop_emulator_if_running();
+            self.state.running_emulator = Some(launch_emulator(
+                &picker.gb_file_path,
+                &self.config,
+                picker.hardware_mode,
+                Some(slot),
+            ));
+            picker_open = false;
+        }
+
+        if !picker_open {
+            self.state.save_state_picker = None;
+            self.state.open_window = None;
+        }
+    }
+
     fn render_about_window(&mut self, ctx: &egui::Context) {
         let mut about_open = true;
         Window::new("About").id("about".into()).resizable(false).open(&mut about_open).show(
@@ -548,7 +611,7 @@ impl JgbApp {
                         .auto_shrink([false; 2])
                         .striped(true)
                         .cell_layout(Layout::left_to_right(Align::Center))
-                        .columns(Column::auto(), 2)
+                        .columns(Column::auto(), 3)
                         .column(Column::remainder())
                         .header(30.0, |mut row| {
                             row.col(|ui| {
@@ -560,6 +623,9 @@ impl JgbApp {
                             row.col(|ui| {
                                 ui.heading("Size");
                             });
+                            row.col(|ui| {
+                                ui.heading("Save States");
+                            });
                         })
                         .body(|mut body| {
                             for search_result in self.state.rom_search_results.clone() {
@@ -573,6 +639,7 @@ impl JgbApp {
                                                 search_result
                                                     .cgb_support_type
                                                     .default_hardware_mode(),
+                                                None,
                                             ));
                                         }
                                     });
@@ -587,6 +654,21 @@ impl JgbApp {
                                     row.col(|ui| {
                                         ui.label(format!("{}KB", search_result.file_size_kb));
                                     });
+                                    row.col(|ui| {
+                                        if ui.button("Load State").clicked() {
+                                            self.state.save_state_picker =
+                                                Some(SaveStatePicker::new(
+                                                    ctx,
+                                                    search_result.full_path.clone(),
+                                                    search_result.file_name_no_ext.clone(),
+                                                    search_result
+                                                        .cgb_support_type
+                                                        .default_hardware_mode(),
+                                                    &self.config,
+                                                ));
+                                            self.state.open_window = Some(OpenWindow::SaveStates);
+                                        }
+                                    });
                                 });
                             }
                         });
@@ -635,6 +717,9 @@ impl eframe::App for JgbApp {
             Some(OpenWindow::Hotkey) => {
                 self.render_hotkey_settings_window(ctx);
             }
+            Some(OpenWindow::SaveStates) => {
+                self.render_save_state_window(ctx);
+            }
             Some(OpenWindow::About) => {
                 self.render_about_window(ctx);
             }
@@ -664,17 +749,44 @@ struct EmulatorInstance {
     quit_signal: Arc<AtomicBool>,
 }
 
+fn optional_file_picker(ui: &mut Ui, path: &mut Option<String>, label: &str) {
+    ui.horizontal(|ui| {
+        let path_text = match path {
+            Some(path) => path.clone(),
+            None => "<None>".into(),
+        };
+        if ui.button(path_text).clicked() {
+            if let Some(new_path) = FileDialog::new().pick_file() {
+                if let Some(new_path) = new_path.to_str().map(String::from) {
+                    *path = Some(new_path);
+                }
+            }
+        }
+
+        ui.label(label);
+
+        if ui.button("Clear").clicked() {
+            *path = None;
+        }
+    });
+}
+
 #[must_use]
 fn launch_emulator(
     gb_file: &str,
     app_config: &AppConfig,
     hardware_mode: HardwareMode,
+    load_state_slot: Option<u8>,
 ) -> EmulatorInstance {
     log::info!("Launching emulator instance for file path '{gb_file}'");
 
     let run_config = RunConfig {
         gb_file_path: gb_file.into(),
         hardware_mode,
+        boot_rom_path: match hardware_mode {
+            HardwareMode::GameBoy => app_config.dmg_boot_rom_path.clone(),
+            HardwareMode::GameBoyColor => app_config.cgb_boot_rom_path.clone(),
+        },
         audio_enabled: app_config.audio_enabled,
         sync_to_audio: app_config.audio_sync_enabled,
         vsync_enabled: app_config.vsync_enabled,
@@ -685,11 +797,19 @@ fn launch_emulator(
         window_height: app_config.window_height,
         audio_debugging_enabled: false,
         audio_60hz: app_config.audio_60hz_hack_enabled,
+        cpu_accuracy: app_config.cpu_accuracy,
         color_scheme: app_config.color_scheme,
         gbc_color_correction: app_config.gbc_color_correction,
         input_config: app_config.input.clone(),
         hotkey_config: app_config.hotkeys.clone(),
         controller_config: app_config.controller.clone(),
+        link_cable: None,
+        printer_enabled: app_config.printer_enabled,
+        camera_image_path: app_config.camera_image_path.clone(),
+        rewind_buffer_secs: app_config.rewind_buffer_secs,
+        load_state_slot,
+        record_movie_path: None,
+        play_movie_path: None,
     };
 
     let quit_signal = Arc::new(AtomicBool::new(false));
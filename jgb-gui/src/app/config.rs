@@ -1,5 +1,7 @@
 use anyhow::Context;
-use jgb_core::{ControllerConfig, GbColorScheme, GbcColorCorrection, HotkeyConfig, InputConfig};
+use jgb_core::{
+    ControllerConfig, CpuAccuracy, GbColorScheme, GbcColorCorrection, HotkeyConfig, InputConfig,
+};
 use serde::{Deserialize, Serialize};
 use std::fs;
 use std::path::Path;
@@ -45,6 +47,15 @@ pub struct AppConfig {
     #[serde(default)]
     pub audio_60hz_hack_enabled: bool,
 
+    #[serde(default)]
+    pub cpu_accuracy: CpuAccuracy,
+
+    #[serde(default)]
+    pub printer_enabled: bool,
+
+    #[serde(default = "default_rewind_buffer_secs")]
+    pub rewind_buffer_secs: u32,
+
     #[serde(default = "default_window_width")]
     pub window_width: u32,
 
@@ -53,6 +64,13 @@ pub struct AppConfig {
 
     pub rom_search_dir: Option<String>,
 
+    pub dmg_boot_rom_path: Option<String>,
+
+    pub cgb_boot_rom_path: Option<String>,
+
+    #[serde(default)]
+    pub camera_image_path: Option<String>,
+
     #[serde(default)]
     pub input: InputConfig,
 
@@ -68,6 +86,10 @@ fn true_fn() -> bool {
     true
 }
 
+fn default_rewind_buffer_secs() -> u32 {
+    10
+}
+
 fn default_window_width() -> u32 {
     4 * 160
 }
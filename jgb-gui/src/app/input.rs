@@ -38,7 +38,10 @@ pub enum ConfigurableInput {
     HotkeyToggleFullscreen,
     HotkeySaveState,
     HotkeyLoadState,
+    HotkeyNextSaveSlot,
+    HotkeyPrevSaveSlot,
     HotkeyFastForward,
+    HotkeyRewind,
 }
 
 impl ConfigurableInput {
@@ -56,7 +59,10 @@ impl ConfigurableInput {
             Self::HotkeyToggleFullscreen => "Toggle Fullscreen",
             Self::HotkeySaveState => "Save State",
             Self::HotkeyLoadState => "Load State",
+            Self::HotkeyNextSaveSlot => "Next Save Slot",
+            Self::HotkeyPrevSaveSlot => "Previous Save Slot",
             Self::HotkeyFastForward => "Fast Forward",
+            Self::HotkeyRewind => "Rewind",
         }
     }
 }
@@ -194,12 +200,30 @@ impl<'a> HotkeySettingsWidget<'a> {
                     )
                     .add_clear_button(&mut self.hotkey_config.load_state)
                     .ui(ui),
+                    SingleInput::new(
+                        ConfigurableInput::HotkeyNextSaveSlot,
+                        self.hotkey_config.next_save_slot.clone(),
+                    )
+                    .add_clear_button(&mut self.hotkey_config.next_save_slot)
+                    .ui(ui),
+                    SingleInput::new(
+                        ConfigurableInput::HotkeyPrevSaveSlot,
+                        self.hotkey_config.prev_save_slot.clone(),
+                    )
+                    .add_clear_button(&mut self.hotkey_config.prev_save_slot)
+                    .ui(ui),
                     SingleInput::new(
                         ConfigurableInput::HotkeyFastForward,
                         self.hotkey_config.fast_forward.clone(),
                     )
                     .add_clear_button(&mut self.hotkey_config.fast_forward)
                     .ui(ui),
+                    SingleInput::new(
+                        ConfigurableInput::HotkeyRewind,
+                        self.hotkey_config.rewind.clone(),
+                    )
+                    .add_clear_button(&mut self.hotkey_config.rewind)
+                    .ui(ui),
                 ]
                 .into_iter()
                 .reduce(Option::or)
@@ -343,9 +367,18 @@ pub fn handle_input_thread_result(thread: InputThread, config: &mut AppConfig) {
                 ConfigurableInput::HotkeyLoadState => {
                     config.hotkeys.load_state = Some(input_str);
                 }
+                ConfigurableInput::HotkeyNextSaveSlot => {
+                    config.hotkeys.next_save_slot = Some(input_str);
+                }
+                ConfigurableInput::HotkeyPrevSaveSlot => {
+                    config.hotkeys.prev_save_slot = Some(input_str);
+                }
                 ConfigurableInput::HotkeyFastForward => {
                     config.hotkeys.fast_forward = Some(input_str);
                 }
+                ConfigurableInput::HotkeyRewind => {
+                    config.hotkeys.rewind = Some(input_str);
+                }
             }
         }
         InputPress::Controller(controller_input) => match button {
@@ -377,7 +410,10 @@ pub fn handle_input_thread_result(thread: InputThread, config: &mut AppConfig) {
             | ConfigurableInput::HotkeyToggleFullscreen
             | ConfigurableInput::HotkeySaveState
             | ConfigurableInput::HotkeyLoadState
-            | ConfigurableInput::HotkeyFastForward => {
+            | ConfigurableInput::HotkeyNextSaveSlot
+            | ConfigurableInput::HotkeyPrevSaveSlot
+            | ConfigurableInput::HotkeyFastForward
+            | ConfigurableInput::HotkeyRewind => {
                 panic!("should never attempt to set a hotkey to a controller input");
             }
         },
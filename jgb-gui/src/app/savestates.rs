@@ -0,0 +1,115 @@
+use crate::AppConfig;
+use egui::{ColorImage, Grid, TextureHandle, TextureOptions, Ui};
+use jgb_core::{HardwareMode, SAVE_STATE_SLOTS, SCREEN_HEIGHT, SCREEN_WIDTH, SaveStateHeader};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+struct SaveStateSlot {
+    slot: u8,
+    header: Option<SaveStateHeader>,
+    thumbnail: Option<TextureHandle>,
+}
+
+pub struct SaveStatePicker {
+    pub gb_file_path: String,
+    pub rom_name: String,
+    pub hardware_mode: HardwareMode,
+    slots: Vec<SaveStateSlot>,
+}
+
+// TextureHandle does not implement Debug
+impl std::fmt::Debug for SaveStatePicker {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SaveStatePicker")
+            .field("gb_file_path", &self.gb_file_path)
+            .field("rom_name", &self.rom_name)
+            .field("hardware_mode", &self.hardware_mode)
+            .finish_non_exhaustive()
+    }
+}
+
+impl SaveStatePicker {
+    /// Read the headers of every save state slot for the given ROM, rendering thumbnails with the
+    /// configured colors.
+    pub fn new(
+        ctx: &egui::Context,
+        gb_file_path: String,
+        rom_name: String,
+        hardware_mode: HardwareMode,
+        app_config: &AppConfig,
+    ) -> Self {
+        let slots = (0..SAVE_STATE_SLOTS)
+            .map(|slot| {
+                let path = jgb_core::determine_save_state_path(&gb_file_path, slot);
+                let header = if path.exists() {
+                    jgb_core::read_save_state_header(&path)
+                        .map_err(|err| {
+                            log::error!(
+                                "Error reading save state header from '{}': {err}",
+                                path.display()
+                            );
+                        })
+                        .ok()
+                } else {
+                    None
+                };
+
+                let thumbnail = header.as_ref().map(|header| {
+                    let rgb = header
+                        .thumbnail_rgb(app_config.color_scheme, app_config.gbc_color_correction);
+                    ctx.load_texture(
+                        format!("save_state_thumbnail_{slot}"),
+                        ColorImage::from_rgb([SCREEN_WIDTH.into(), SCREEN_HEIGHT.into()], &rgb),
+                        TextureOptions::NEAREST,
+                    )
+                });
+
+                SaveStateSlot { slot, header, thumbnail }
+            })
+            .collect();
+
+        Self { gb_file_path, rom_name, hardware_mode, slots }
+    }
+
+    /// Render the slot list. Returns the slot to load if one was selected.
+    #[must_use]
+    pub fn ui(&self, ui: &mut Ui) -> Option<u8> {
+        let now =
+            SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |duration| duration.as_secs());
+
+        Grid::new("save_state_grid")
+            .show(ui, |ui| {
+                let mut selected_slot = None;
+
+                for SaveStateSlot { slot, header, thumbnail } in &self.slots {
+                    ui.label(format!("Slot {slot}"));
+
+                    match (header, thumbnail) {
+                        (Some(header), Some(thumbnail)) => {
+                            ui.image(thumbnail);
+                            ui.label(format_age(now.saturating_sub(header.timestamp)));
+                            if ui.button("Load").clicked() {
+                                selected_slot = Some(*slot);
+                            }
+                        }
+                        _ => {
+                            ui.label("Empty");
+                        }
+                    }
+
+                    ui.end_row();
+                }
+
+                selected_slot
+            })
+            .inner
+    }
+}
+
+fn format_age(age_secs: u64) -> String {
+    match age_secs {
+        0..=59 => "Saved just now".into(),
+        60..=3599 => format!("Saved {} minute(s) ago", age_secs / 60),
+        3600..=86399 => format!("Saved {} hour(s) ago", age_secs / 3600),
+        _ => format!("Saved {} day(s) ago", age_secs / 86400),
+    }
+}